@@ -18,11 +18,52 @@ use tc_error::*;
 use tc_transact::fs::*;
 use tc_transact::lock::{Mutable, TxnLock};
 use tc_transact::{Transact, Transaction, TxnId};
-use tc_value::{Value, ValueCollator};
+use tc_value::{Number, Value, ValueCollator};
 use tcgeneric::{Instance, TCBoxTryFuture, TCTryStream, Tuple};
 
 use super::{validate_range, BTree, BTreeInstance, BTreeSlice, BTreeType, Key, Range, RowSchema};
 
+/// `bad_request!("expected {} but found {} for column {}", want, got, name)`
+/// builds one formatted message via `format_args!` and feeds it straight to
+/// `TCError::bad_request`--whose `info` parameter already accepts anything
+/// `fmt::Display`, which `fmt::Arguments` is, so this needs no intermediate
+/// `String`--for diagnostics with several interpolated values that don't fit
+/// the constructor's plain "message, one value" shape. The existing
+/// `bad_request!(msg, cause)` two-argument form (a literal message plus a
+/// single contextual value, as used throughout this crate) is matched first
+/// and passed straight through unchanged, so that convention keeps working.
+macro_rules! bad_request {
+    ($msg:expr, $cause:expr $(,)?) => {
+        TCError::bad_request($msg, $cause)
+    };
+    ($fmt:expr, $($arg:expr),+ $(,)?) => {
+        TCError::bad_request(std::format_args!($fmt, $($arg),+), "")
+    };
+}
+
+/// See [`bad_request!`]. `not_found`/`unsupported`/`internal`/`forbidden`/
+/// `bad_gateway` each take a single message, so these just forward a format
+/// string and its arguments through to build that one message in place.
+macro_rules! not_found {
+    ($($arg:tt)*) => { TCError::not_found(std::format_args!($($arg)*)) };
+}
+
+macro_rules! unsupported {
+    ($($arg:tt)*) => { TCError::unsupported(std::format_args!($($arg)*)) };
+}
+
+macro_rules! internal {
+    ($($arg:tt)*) => { TCError::internal(std::format_args!($($arg)*)) };
+}
+
+macro_rules! forbidden {
+    ($($arg:tt)*) => { TCError::forbidden(std::format_args!($($arg)*)) };
+}
+
+macro_rules! bad_gateway {
+    ($($arg:tt)*) => { TCError::bad_gateway(std::format_args!($($arg)*)) };
+}
+
 type Selection<'a> = FuturesOrdered<
     Pin<Box<dyn Future<Output = TCResult<TCTryStream<'a, Key>>> + Send + Unpin + 'a>>,
 >;
@@ -90,13 +131,115 @@ impl fmt::Display for NodeKey {
     }
 }
 
+/// A monoidal reduction over a node's keys, cached in `Node::reduced` and
+/// maintained incrementally so that aggregates over a `Range` don't need to
+/// stream every matching key through `rows_in_range`. Modeled as a trait so
+/// other reductions (e.g. a sum over a numeric column) have somewhere to
+/// plug in, though `Node::reduced` below is hardcoded to [`Count`] for
+/// now--making the reduction a schema-level choice would mean threading a
+/// generic reduction type through every `Node`/`BTreeFile` signature in this
+/// file, which is more invasive than this first reductor calls for.
+trait Reductor {
+    type Output: Clone + Send + Sync;
+
+    fn identity() -> Self::Output;
+    fn reduce_keys(keys: &[NodeKey]) -> Self::Output;
+    fn combine(a: Self::Output, b: Self::Output) -> Self::Output;
+}
+
+/// The built-in reductor: the number of non-deleted keys in a subtree.
+struct Count;
+
+impl Reductor for Count {
+    type Output = usize;
+
+    fn identity() -> usize {
+        0
+    }
+
+    fn reduce_keys(keys: &[NodeKey]) -> usize {
+        keys.iter().filter(|key| !key.deleted).count()
+    }
+
+    fn combine(a: usize, b: usize) -> usize {
+        a + b
+    }
+}
+
+/// Per-column sort direction for a `BTreeFile`'s keys--e.g. `[true, false]`
+/// sorts ascending on column 0 and descending on column 1, so `keys()` and
+/// every other scan are already in the index's intended order instead of
+/// every consumer post-sorting or reversing an ascending stream. Threaded
+/// through `create` (and, ascending-only, `load`--see its comment) rather
+/// than carried on `RowSchema` itself, since `Column` is defined outside
+/// this crate and isn't ours to extend with a direction field.
+///
+/// Only `compare` and `compare_slice` are overridden below; `bisect` and
+/// `bisect_left` are left to `Collate`'s own default implementations, which
+/// are assumed (per that trait's contract, defined outside this crate) to
+/// be built in terms of `compare_slice` rather than duplicating its
+/// comparison loop, so direction-awareness reaches them for free.
+#[derive(Clone)]
+pub struct SchemaCollator {
+    value: ValueCollator,
+    ascending: Arc<Vec<bool>>,
+}
+
+impl SchemaCollator {
+    pub fn new(ascending: Vec<bool>) -> Self {
+        Self {
+            value: ValueCollator::default(),
+            ascending: Arc::new(ascending),
+        }
+    }
+
+    fn is_ascending(&self, column: usize) -> bool {
+        self.ascending.get(column).copied().unwrap_or(true)
+    }
+}
+
+impl Default for SchemaCollator {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+impl Collate for SchemaCollator {
+    type Value = Value;
+
+    fn compare(&self, left: &Value, right: &Value) -> Ordering {
+        self.value.compare(left, right)
+    }
+
+    fn compare_slice<L: AsRef<[Value]>, R: AsRef<[Value]>>(&self, left: L, right: R) -> Ordering {
+        let left = left.as_ref();
+        let right = right.as_ref();
+
+        for (i, (l, r)) in left.iter().zip(right.iter()).enumerate() {
+            let order = self.value.compare(l, r);
+            let order = if self.is_ascending(i) {
+                order
+            } else {
+                order.reverse()
+            };
+
+            if order != Ordering::Equal {
+                return order;
+            }
+        }
+
+        left.len().cmp(&right.len())
+    }
+}
+
 #[derive(Clone, Eq, PartialEq)]
 pub struct Node {
     leaf: bool,
     keys: Vec<NodeKey>,
     parent: Option<NodeId>,
     children: Vec<NodeId>,
-    rebalance: bool, // TODO: implement rebalancing to clear deleted values
+    rebalance: bool, // set by `_delete_range`, cleared by `BTreeFile::compact`
+    reduced: usize,  // `Count` over this node's subtree; see `Reductor`
 }
 
 impl Node {
@@ -107,6 +250,7 @@ impl Node {
             parent,
             children: vec![],
             rebalance: false,
+            reduced: 0,
         }
     }
 }
@@ -127,12 +271,13 @@ impl de::FromStream for Node {
 
     async fn from_stream<D: de::Decoder>(cxt: (), decoder: &mut D) -> Result<Self, D::Error> {
         de::FromStream::from_stream(cxt, decoder)
-            .map_ok(|(leaf, keys, parent, children, rebalance)| Self {
+            .map_ok(|(leaf, keys, parent, children, rebalance, reduced)| Self {
                 leaf,
                 keys,
                 parent,
                 children,
                 rebalance,
+                reduced,
             })
             .await
     }
@@ -147,6 +292,7 @@ impl<'en> en::ToStream<'en> for Node {
                 &self.parent,
                 &self.children,
                 &self.rebalance,
+                &self.reduced,
             ),
             encoder,
         )
@@ -162,6 +308,7 @@ impl<'en> en::IntoStream<'en> for Node {
                 self.parent,
                 self.children,
                 self.rebalance,
+                self.reduced,
             ),
             encoder,
         )
@@ -200,7 +347,8 @@ struct Inner<F, D, T> {
     file: F,
     schema: RowSchema,
     order: usize,
-    collator: ValueCollator,
+    collator: SchemaCollator,
+    constraints: Vec<Option<ColumnConstraint>>,
     root: TxnLock<Mutable<NodeId>>,
     dir: PhantomData<D>,
     txn: PhantomData<T>,
@@ -215,13 +363,21 @@ impl<F: File<Node>, D: Dir, T: Transaction<D>> BTreeFile<F, D, T>
 where
     Self: Clone,
 {
-    fn new(file: F, schema: RowSchema, order: usize, root: NodeId) -> Self {
+    fn new(
+        file: F,
+        schema: RowSchema,
+        order: usize,
+        root: NodeId,
+        collator: SchemaCollator,
+        constraints: Vec<Option<ColumnConstraint>>,
+    ) -> Self {
         BTreeFile {
             inner: Arc::new(Inner {
                 file,
                 schema,
                 order,
-                collator: ValueCollator::default(),
+                collator,
+                constraints,
                 root: TxnLock::new("BTree root", root.into()),
                 dir: PhantomData,
                 txn: PhantomData,
@@ -229,7 +385,20 @@ where
         }
     }
 
-    pub async fn create(file: F, schema: RowSchema, txn_id: TxnId) -> TCResult<Self> {
+    /// `collation[i]` is `true` to sort column `i` ascending (the default
+    /// for any column past the end of `collation`, so `vec![]` is a plain
+    /// all-ascending index) or `false` to sort it descending. `constraints[i]`
+    /// (if present and not `None`) is an extra check on column `i`'s value,
+    /// beyond `dtype` coercion, enforced by `validate_key` on every insert--see
+    /// `ColumnConstraint`'s comment for why this rides alongside the schema
+    /// instead of living on `Column` itself.
+    pub async fn create(
+        file: F,
+        schema: RowSchema,
+        collation: Vec<bool>,
+        constraints: Vec<Option<ColumnConstraint>>,
+        txn_id: TxnId,
+    ) -> TCResult<Self> {
         if !file.is_empty(&txn_id).await? {
             return Err(TCError::internal(
                 "Tried to create a new BTree without a new File",
@@ -243,15 +412,195 @@ where
             .create_block(txn_id, root.clone(), Node::new(true, None))
             .await?;
 
-        Ok(BTreeFile::new(file, schema, order, root))
+        Ok(BTreeFile::new(
+            file,
+            schema,
+            order,
+            root,
+            SchemaCollator::new(collation),
+            constraints,
+        ))
+    }
+
+    /// Build a new BTree from `keys`, which must already be in ascending
+    /// order per `schema`'s (ascending) collation--an out-of-order or
+    /// duplicate key is an error. Unlike repeated `insert` calls, which
+    /// re-descend from the root and split nodes along the way, this packs
+    /// leaves to capacity and builds each level directly above the one
+    /// below it, writing every block exactly once.
+    ///
+    /// Node IDs are assigned before any block is written (`File::unique_id`
+    /// doesn't write anything), so a child's `parent` pointer--read back by
+    /// `compact`'s rebalancing--is correct from that single write, with no
+    /// separate patch-up pass. `load` can't carry a non-default collation
+    /// (see its comment), so neither can this; it always builds ascending.
+    pub async fn load_sorted(
+        file: F,
+        schema: RowSchema,
+        constraints: Vec<Option<ColumnConstraint>>,
+        txn_id: TxnId,
+        mut keys: TCTryStream<'_, Key>,
+    ) -> TCResult<Self> {
+        if !file.is_empty(&txn_id).await? {
+            return Err(TCError::internal(
+                "Tried to load a BTree without a new File",
+            ));
+        }
+
+        let order = validate_schema(&schema)?;
+        let collator = SchemaCollator::default();
+
+        let mut all_keys: Vec<NodeKey> = Vec::new();
+        let mut prev: Option<Key> = None;
+
+        while let Some(key) = keys.try_next().await? {
+            let key = validate_key(key, &schema, &constraints).await?;
+
+            if let Some(prev_key) = &prev {
+                if collator.compare_slice(prev_key, &key) != Ordering::Less {
+                    return Err(TCError::bad_request(
+                        "BTree::load_sorted given an out-of-order key",
+                        Tuple::from(key),
+                    ));
+                }
+            }
+
+            prev = Some(key.clone());
+            all_keys.push(NodeKey::new(key));
+        }
+
+        if all_keys.is_empty() {
+            let root: BlockId = Uuid::new_v4().into();
+            file.clone()
+                .create_block(txn_id, root.clone(), Node::new(true, None))
+                .await?;
+
+            return Ok(BTreeFile::new(file, schema, order, root, collator, constraints));
+        }
+
+        // pack leaves to `2 * order - 1` keys each, consuming one extra key
+        // as the separator promoted between each pair of leaves--a classic
+        // (not B+) BTree has no room to duplicate a key into both a leaf and
+        // its parent, so that key lives in the parent instead
+        let leaf_capacity = (2 * order) - 1;
+        let mut leaves: Vec<(NodeId, Node)> = Vec::new();
+        let mut separators: Vec<NodeKey> = Vec::new();
+        let mut i = 0;
+
+        while i < all_keys.len() {
+            let mut end = std::cmp::min(i + leaf_capacity, all_keys.len());
+            // if exactly one key would be left over after this leaf, hold one
+            // back so the separator still has a non-empty leaf to its right
+            if end + 1 == all_keys.len() {
+                end -= 1;
+            }
+
+            let mut leaf = Node::new(true, None);
+            leaf.keys = all_keys[i..end].to_vec();
+            leaf.reduced = Count::reduce_keys(&leaf.keys);
+            i = end;
+
+            let leaf_id = file.unique_id(&txn_id).await?;
+            leaves.push((leaf_id, leaf));
+
+            if i < all_keys.len() {
+                separators.push(all_keys[i].clone());
+                i += 1;
+            }
+        }
+
+        // `levels[0]` is the leaves, `levels.last()` is the (single-node)
+        // root once the loop below stops growing it
+        let mut levels: Vec<Vec<(NodeId, Node)>> = vec![leaves];
+        let mut level_separators: Vec<Vec<NodeKey>> = vec![separators];
+        let max_children = 2 * order;
+
+        while levels.last().expect("level").len() > 1 {
+            let children = levels.last().expect("level");
+            let seps = level_separators.last().expect("separators");
+
+            let n = children.len();
+            let k = (n + max_children - 1) / max_children;
+            let q = n / k;
+            let r = n % k;
+
+            let mut new_level = Vec::with_capacity(k);
+            let mut new_separators = Vec::new();
+            let mut start = 0;
+
+            for g in 0..k {
+                let size = if g < r { q + 1 } else { q };
+                let end = start + size;
+
+                let own_keys = seps[start..(end - 1)].to_vec();
+                let children_ids = children[start..end]
+                    .iter()
+                    .map(|(id, _)| id.clone())
+                    .collect::<Vec<_>>();
+                let reduced = own_keys.len()
+                    + children[start..end]
+                        .iter()
+                        .map(|(_, node)| node.reduced)
+                        .sum::<usize>();
+
+                let mut node = Node::new(false, None);
+                node.keys = own_keys;
+                node.children = children_ids;
+                node.reduced = reduced;
+
+                let node_id = file.unique_id(&txn_id).await?;
+                new_level.push((node_id, node));
+
+                if end < n {
+                    new_separators.push(seps[end - 1].clone());
+                }
+
+                start = end;
+            }
+
+            levels.push(new_level);
+            level_separators.push(new_separators);
+        }
+
+        // now that the whole tree's shape is known, assign each node's
+        // `parent` from the level above, using the children ID lists
+        // already recorded on each parent (in order) to locate its slice of
+        // the level below--no separate grouping bookkeeping needed
+        for level_idx in (0..levels.len() - 1).rev() {
+            let parents: Vec<(NodeId, usize)> = levels[level_idx + 1]
+                .iter()
+                .map(|(id, node)| (id.clone(), node.children.len()))
+                .collect();
+
+            let mut cursor = 0;
+            for (parent_id, child_count) in parents {
+                for (_, child) in &mut levels[level_idx][cursor..(cursor + child_count)] {
+                    child.parent = Some(parent_id.clone());
+                }
+                cursor += child_count;
+            }
+        }
+
+        let root_id = levels.last().expect("root level")[0].0.clone();
+
+        for level in levels {
+            for (id, node) in level {
+                file.clone().create_block(txn_id, id, node).await?;
+            }
+        }
+
+        Ok(BTreeFile::new(file, schema, order, root_id, collator, constraints))
     }
 
+    /// Marks matching keys deleted and returns the change in `Count` over
+    /// this subtree (always `<= 0`), so the caller can keep its own
+    /// `reduced` in sync without re-scanning anything.
     fn _delete_range<'a>(
         &'a self,
         txn_id: TxnId,
         node_id: NodeId,
         range: &'a Range,
-    ) -> TCBoxTryFuture<'a, ()> {
+    ) -> TCBoxTryFuture<'a, isize> {
         Box::pin(async move {
             let collator = &self.inner.collator;
             let file = &self.inner.file;
@@ -264,31 +613,47 @@ where
 
             if node.leaf {
                 if l == r {
-                    return Ok(());
+                    return Ok(0);
                 }
 
                 let mut node = node.upgrade(file).await?;
+                let mut delta = 0isize;
                 for i in l..r {
+                    if !node.keys[i].deleted {
+                        delta -= 1;
+                    }
                     node.keys[i].deleted = true;
                 }
                 node.rebalance = true;
+                node.reduced = (node.reduced as isize + delta) as usize;
 
-                Ok(())
+                Ok(delta)
             } else if r > l {
                 let mut node = node.upgrade(file).await?;
-                let mut deletes = Vec::with_capacity(r - l);
-
+                let mut own_delta = 0isize;
                 for i in l..r {
+                    if !node.keys[i].deleted {
+                        own_delta -= 1;
+                    }
                     node.keys[i].deleted = true;
-                    deletes.push(self._delete_range(txn_id, node.children[i].clone(), range));
                 }
                 node.rebalance = true;
 
+                let mut deletes = Vec::with_capacity(r - l);
+                for i in l..r {
+                    deletes.push(self._delete_range(txn_id, node.children[i].clone(), range));
+                }
+
                 let child_id = node.children[r].clone();
                 let last_delete = self._delete_range(txn_id, child_id, range);
-                try_join(try_join_all(deletes), last_delete).await?;
+                let (child_deltas, last_delta) =
+                    try_join(try_join_all(deletes), last_delete).await?;
+
+                let delta: isize =
+                    own_delta + child_deltas.into_iter().sum::<isize>() + last_delta;
+                node.reduced = (node.reduced as isize + delta) as usize;
 
-                Ok(())
+                Ok(delta)
             } else {
                 let child_id = node.children[r].clone();
                 self._delete_range(txn_id, child_id, range).await
@@ -298,15 +663,20 @@ where
 
     pub(super) async fn delete_range(&self, txn_id: TxnId, range: &Range) -> TCResult<()> {
         let root_id = self.inner.root.read(&txn_id).await?;
-        self._delete_range(txn_id, (*root_id).clone(), range).await
+        self._delete_range(txn_id, (*root_id).clone(), range)
+            .await?;
+        Ok(())
     }
 
+    /// Inserts (or un-tombstones) `key` and returns the change in `Count`
+    /// over this subtree (`0` or `1`), so the caller can keep its own
+    /// `reduced` in sync.
     fn _insert(
         &self,
         txn_id: TxnId,
         node: <F::Block as Block<Node, F>>::ReadLock,
         key: Key,
-    ) -> TCBoxTryFuture<()> {
+    ) -> TCBoxTryFuture<isize> {
         Box::pin(async move {
             let collator = &self.inner.collator;
             let file = &self.inner.file;
@@ -318,9 +688,11 @@ where
                 if node.keys[i].deleted {
                     let mut node = node.upgrade(file).await?;
                     node.keys[i].deleted = false;
+                    node.reduced += 1;
+                    return Ok(1);
                 }
 
-                return Ok(());
+                return Ok(0);
             }
 
             #[cfg(debug_assertions)]
@@ -329,7 +701,8 @@ where
             if node.leaf {
                 let mut node = node.upgrade(file).await?;
                 node.keys.insert(i, NodeKey::new(key));
-                Ok(())
+                node.reduced += 1;
+                Ok(1)
             } else {
                 let child_id = node.children[i].clone();
                 let child = file.read_block(txn_id, child_id).await?;
@@ -349,9 +722,11 @@ where
                             if node.keys[i].deleted {
                                 let mut node = node.upgrade(file).await?;
                                 node.keys[i].deleted = false;
+                                node.reduced += 1;
+                                return Ok(1);
                             }
 
-                            return Ok(());
+                            return Ok(0);
                         }
                         Ordering::Greater => {
                             let child_id = node.children[i + 1].clone();
@@ -360,12 +735,154 @@ where
                         }
                     }
                 } else {
-                    self._insert(txn_id, child, key).await
+                    let delta = self._insert(txn_id, child, key).await?;
+                    if delta != 0 {
+                        let mut node = node.upgrade(file).await?;
+                        node.reduced = (node.reduced as isize + delta) as usize;
+                    }
+
+                    Ok(delta)
                 }
             }
         })
     }
 
+    /// Atomically apply `op` to the row located by `key` (an exact key or a
+    /// prefix thereof, per [`Collate::compare_slice`]): `op` sees the
+    /// existing row (`None` if there's no live match) and returns the row to
+    /// store in its place, or `None` to delete it. Because the match is
+    /// located with the same bisect `_insert` uses and `op` is applied before
+    /// releasing the lock on the node holding that key (a read lock, upgraded
+    /// to a write lock to store the result), callers get a read-modify-write
+    /// (e.g. a counter keyed by `key`) without a separate round-trip that
+    /// could race with a concurrent writer.
+    pub async fn merge<Op>(&self, txn_id: TxnId, key: Key, op: Op) -> TCResult<()>
+    where
+        Op: Fn(Option<&[Value]>) -> Option<Key> + Send + Sync,
+    {
+        let file = &self.inner.file;
+        let order = self.inner.order;
+
+        // as in `insert`, preemptively split a full root before descending,
+        // since `op` might turn out to insert a new row
+        let mut root_id = self.inner.root.write(txn_id).await?;
+        let root = file.read_block(txn_id, (*root_id).clone()).await?;
+
+        if root.keys.len() == (2 * order) - 1 {
+            let reduced = root.reduced;
+            std::mem::drop(root);
+
+            let old_root_id = (*root_id).clone();
+            (*root_id) = file.unique_id(&txn_id).await?;
+
+            let mut new_root = Node::new(false, None);
+            new_root.children.push(old_root_id.clone());
+            new_root.reduced = reduced;
+
+            let new_root = file
+                .create_block(txn_id, (*root_id).clone(), new_root)
+                .await?;
+
+            let new_root = new_root.write().await;
+            let new_root = self.split_child(txn_id, old_root_id, new_root, 0).await?;
+            self._merge(txn_id, new_root, key, &op).await?;
+        } else {
+            std::mem::drop(root_id);
+            self._merge(txn_id, root, key, &op).await?;
+        }
+
+        Ok(())
+    }
+
+    fn _merge<'a, Op>(
+        &'a self,
+        txn_id: TxnId,
+        node: <F::Block as Block<Node, F>>::ReadLock,
+        key: Key,
+        op: &'a Op,
+    ) -> TCBoxTryFuture<'a, isize>
+    where
+        Op: Fn(Option<&[Value]>) -> Option<Key> + Send + Sync,
+    {
+        Box::pin(async move {
+            let collator = &self.inner.collator;
+            let file = &self.inner.file;
+            let order = self.inner.order;
+
+            let i = collator.bisect_left(&node.keys, &key);
+            let found = i < node.keys.len()
+                && collator.compare_slice(&node.keys[i], &key) == Ordering::Equal;
+
+            if found {
+                let existing = if node.keys[i].deleted {
+                    None
+                } else {
+                    Some(node.keys[i].value.clone())
+                };
+
+                return match op(existing.as_deref()) {
+                    Some(new_row) => {
+                        let mut node = node.upgrade(file).await?;
+                        let was_live = !node.keys[i].deleted;
+                        node.keys[i] = NodeKey::new(new_row);
+                        if was_live {
+                            Ok(0)
+                        } else {
+                            node.reduced += 1;
+                            Ok(1)
+                        }
+                    }
+                    None if existing.is_none() => Ok(0),
+                    None => {
+                        let mut node = node.upgrade(file).await?;
+                        node.keys[i].deleted = true;
+                        node.rebalance = true;
+                        node.reduced -= 1;
+                        Ok(-1)
+                    }
+                };
+            }
+
+            if node.leaf {
+                return match op(None) {
+                    Some(new_row) => {
+                        let mut node = node.upgrade(file).await?;
+                        node.keys.insert(i, NodeKey::new(new_row));
+                        node.reduced += 1;
+                        Ok(1)
+                    }
+                    None => Ok(0),
+                };
+            }
+
+            let child_id = node.children[i].clone();
+            let child = file.read_block(txn_id, child_id).await?;
+
+            if child.keys.len() == (2 * order) - 1 {
+                // split_child needs a write lock on child, so drop the read lock
+                std::mem::drop(child);
+
+                let child_id = node.children[i].clone();
+                let node = self
+                    .split_child(txn_id, child_id, node.upgrade(file).await?, i)
+                    .await?;
+
+                // the split changed which key/child at/around `i` our target
+                // falls under--re-locate it rather than re-deriving the three
+                // `_insert`-style branches here
+                self._merge(txn_id, node, key, op).await
+            } else {
+                let delta = self._merge(txn_id, child, key, op).await?;
+                if delta != 0 {
+                    let mut node = node.upgrade(file).await?;
+                    node.reduced = (node.reduced as isize + delta) as usize;
+                }
+
+                Ok(delta)
+            }
+        })
+    }
+
     fn _slice<'a, B: Deref<Target = Node>>(
         self,
         txn_id: TxnId,
@@ -563,10 +1080,383 @@ where
             new_node.children = child.children.drain(order..).collect();
         }
 
+        // the parent's own `reduced` already accounts for the full,
+        // pre-split child, and splitting doesn't change the subtree's total
+        // key count--only the two halves' own cached values need recomputing
+        child.reduced = self.compute_reduced(txn_id, &child).await?;
+        new_node.reduced = self.compute_reduced(txn_id, &new_node).await?;
+
         file.create_block(txn_id, new_node_id, new_node).await?;
 
         node.downgrade(file).await
     }
+
+    /// Recompute `Count` over `node`'s subtree from scratch: its own
+    /// non-deleted keys, plus (for an interior node) its children's already
+    /// up-to-date cached values.
+    async fn compute_reduced(&self, txn_id: TxnId, node: &Node) -> TCResult<usize> {
+        let mut reduced = Count::reduce_keys(&node.keys);
+
+        if !node.leaf {
+            let file = &self.inner.file;
+            for child_id in &node.children {
+                let child = file.read_block(txn_id, child_id.clone()).await?;
+                reduced = Count::combine(reduced, child.reduced);
+            }
+        }
+
+        Ok(reduced)
+    }
+
+    /// Count the keys in `range` in O(log n) rather than streaming them:
+    /// descend the tree, adding the cached `reduced` value of every child
+    /// fully contained in `range` directly, and only recursing into the two
+    /// boundary children the range actually cuts through.
+    pub async fn reduce_range(&self, txn_id: TxnId, range: Range) -> TCResult<usize> {
+        let root_id = self.inner.root.read(&txn_id).await?;
+        let root = self
+            .inner
+            .file
+            .read_block(txn_id, (*root_id).clone())
+            .await?;
+
+        self._reduce_range(txn_id, &root, range).await
+    }
+
+    fn _reduce_range<'a>(
+        &'a self,
+        txn_id: TxnId,
+        node: &'a Node,
+        range: Range,
+    ) -> TCBoxTryFuture<'a, usize> {
+        Box::pin(async move {
+            let (l, r) = self.inner.collator.bisect(&node.keys, &range);
+
+            if node.leaf {
+                return Ok(Count::reduce_keys(&node.keys[l..r]));
+            }
+
+            let file = &self.inner.file;
+
+            if l == r {
+                // the whole range falls within a single child
+                let child = file.read_block(txn_id, node.children[l].clone()).await?;
+                return self._reduce_range(txn_id, &child, range).await;
+            }
+
+            let mut reduced = Count::reduce_keys(&node.keys[l..r]);
+
+            for i in (l + 1)..r {
+                let child = file.read_block(txn_id, node.children[i].clone()).await?;
+                reduced = Count::combine(reduced, child.reduced);
+            }
+
+            let left = file.read_block(txn_id, node.children[l].clone()).await?;
+            let left_reduced = self._reduce_range(txn_id, &left, range.clone()).await?;
+
+            let right = file.read_block(txn_id, node.children[r].clone()).await?;
+            let right_reduced = self._reduce_range(txn_id, &right, range).await?;
+
+            Ok(Count::combine(Count::combine(reduced, left_reduced), right_reduced))
+        })
+    }
+
+    /// Walk the tree bottom-up and physically remove the tombstones left
+    /// behind by `delete_range`, restoring the B-tree invariant (every
+    /// non-root node holding at least `order - 1` keys) wherever a deletion
+    /// left a node underflowed. Safe to call from `Transact::commit`, or on
+    /// demand--a node not flagged `rebalance` is left untouched.
+    pub async fn compact(&self, txn_id: TxnId) -> TCResult<()> {
+        let root_id = self.inner.root.read(&txn_id).await?;
+        self._compact(txn_id, (*root_id).clone()).await
+    }
+
+    fn _compact<'a>(&'a self, txn_id: TxnId, node_id: NodeId) -> TCBoxTryFuture<'a, ()> {
+        Box::pin(async move {
+            let file = &self.inner.file;
+            let order = self.inner.order;
+
+            let child_ids = file.read_block(txn_id, node_id.clone()).await?.children.clone();
+            try_join_all(
+                child_ids
+                    .into_iter()
+                    .map(|child_id| self._compact(txn_id, child_id)),
+            )
+            .await?;
+
+            if !file.read_block(txn_id, node_id.clone()).await?.rebalance {
+                return Ok(());
+            }
+
+            let mut node = file.write_block(txn_id, node_id.clone()).await?;
+
+            if node.leaf {
+                node.keys.retain(|key| !key.deleted);
+            } else {
+                // a deleted separator key can't simply be dropped, since the
+                // node must always hold one more child than key: merge the
+                // child to its right into the child to its left instead, and
+                // drop the now-redundant separator and right child pointer
+                let mut i = 0;
+                while i < node.keys.len() {
+                    if !node.keys[i].deleted {
+                        i += 1;
+                        continue;
+                    }
+
+                    node.keys.remove(i);
+                    let left_id = node.children[i].clone();
+                    let right_id = node.children.remove(i + 1);
+
+                    let right = file.read_block(txn_id, right_id.clone()).await?;
+                    let right_keys = right.keys.clone();
+                    let right_children = right.children.clone();
+                    let right_reduced = right.reduced;
+                    std::mem::drop(right);
+
+                    let mut left = file.write_block(txn_id, left_id).await?;
+                    left.keys.extend(right_keys);
+                    left.children.extend(right_children);
+                    // the dropped separator was already tombstoned, so it
+                    // contributed nothing to `Count`--just fold in the right
+                    // child's cached value
+                    left.reduced = Count::combine(left.reduced, right_reduced);
+                    std::mem::drop(left);
+
+                    file.delete_block(txn_id, right_id).await?;
+                }
+            }
+
+            node.rebalance = false;
+            let parent_id = node.parent.clone();
+            let underflowed = node.keys.len() < order - 1;
+            std::mem::drop(node);
+
+            match parent_id {
+                Some(parent_id) if underflowed => self.fix_underflow(txn_id, parent_id, node_id).await,
+                None => self.collapse_root(txn_id, node_id).await,
+                Some(_) => Ok(()),
+            }
+        })
+    }
+
+    /// `node_id` (a child of `parent_id`) holds fewer than `order - 1` keys:
+    /// borrow a key from a sibling with keys to spare, or merge with a
+    /// sibling if neither has any.
+    fn fix_underflow<'a>(
+        &'a self,
+        txn_id: TxnId,
+        parent_id: NodeId,
+        node_id: NodeId,
+    ) -> TCBoxTryFuture<'a, ()> {
+        Box::pin(async move {
+            let file = &self.inner.file;
+            let order = self.inner.order;
+
+            let mut parent = file.write_block(txn_id, parent_id.clone()).await?;
+            let i = parent
+                .children
+                .iter()
+                .position(|child_id| child_id == &node_id)
+                .ok_or_else(|| {
+                    TCError::internal("BTree child pointer missing from parent during compaction")
+                })?;
+
+            if i + 1 < parent.children.len() {
+                let right_id = parent.children[i + 1].clone();
+                if file.read_block(txn_id, right_id).await?.keys.len() > order - 1 {
+                    return self.rotate_left(txn_id, &mut parent, i).await;
+                }
+            }
+
+            if i > 0 {
+                let left_id = parent.children[i - 1].clone();
+                if file.read_block(txn_id, left_id).await?.keys.len() > order - 1 {
+                    return self.rotate_right(txn_id, &mut parent, i).await;
+                }
+            }
+
+            let merge_at = if i + 1 < parent.children.len() { i } else { i - 1 };
+            self.merge_children(txn_id, parent, parent_id, merge_at).await
+        })
+    }
+
+    /// Rotate the parent's separator key `i` down into the deficient node
+    /// `i`, and the right sibling's first key up into the parent in its
+    /// place, moving the right sibling's first child along with it for
+    /// interior nodes.
+    async fn rotate_left(
+        &self,
+        txn_id: TxnId,
+        parent: &mut <F::Block as Block<Node, F>>::WriteLock,
+        i: usize,
+    ) -> TCResult<()> {
+        let file = &self.inner.file;
+
+        let node_id = parent.children[i].clone();
+        let right_id = parent.children[i + 1].clone();
+
+        let mut right = file.write_block(txn_id, right_id).await?;
+        let separator = right.keys.remove(0);
+        let moved_child = if right.leaf { None } else { Some(right.children.remove(0)) };
+        let moved_child_reduced = match &moved_child {
+            Some(child_id) => file.read_block(txn_id, child_id.clone()).await?.reduced,
+            None => 0,
+        };
+        // `separator` is right's own first key, already purged of any
+        // tombstones by this compaction pass's recursive descent into
+        // `right` (see `_compact`), so it's safe to assume it's live here
+        right.reduced -= 1 + moved_child_reduced;
+        std::mem::drop(right);
+
+        let old_separator = std::mem::replace(&mut parent.keys[i], separator);
+        // unlike `separator` above, `old_separator` comes from `parent`,
+        // whose own tombstones haven't been purged yet at this point in
+        // the compaction pass--`parent`'s own rebalance/purge step hasn't
+        // run when a descendant's underflow pulls it into `fix_underflow`
+        // (see `merge_children`'s `separator_live`, which handles the same
+        // case)--so it can't be assumed live just because it's migrating.
+        let old_separator_live = !old_separator.deleted as usize;
+
+        let mut node = file.write_block(txn_id, node_id).await?;
+        node.keys.push(old_separator);
+        if let Some(child_id) = moved_child {
+            node.children.push(child_id);
+        }
+        node.reduced += old_separator_live + moved_child_reduced;
+
+        Ok(())
+    }
+
+    /// The mirror image of [`Self::rotate_left`]: borrow from the left
+    /// sibling of the deficient node `i` instead of the right.
+    async fn rotate_right(
+        &self,
+        txn_id: TxnId,
+        parent: &mut <F::Block as Block<Node, F>>::WriteLock,
+        i: usize,
+    ) -> TCResult<()> {
+        let file = &self.inner.file;
+
+        let node_id = parent.children[i].clone();
+        let left_id = parent.children[i - 1].clone();
+
+        let mut left = file.write_block(txn_id, left_id).await?;
+        let separator = left.keys.pop().expect("left sibling has a spare key");
+        let moved_child = if left.leaf { None } else { left.children.pop() };
+        let moved_child_reduced = match &moved_child {
+            Some(child_id) => file.read_block(txn_id, child_id.clone()).await?.reduced,
+            None => 0,
+        };
+        // `separator` is left's own last key, already purged of any
+        // tombstones by this compaction pass's recursive descent into
+        // `left` (see `_compact`), so it's safe to assume it's live here
+        left.reduced -= 1 + moved_child_reduced;
+        std::mem::drop(left);
+
+        let old_separator = std::mem::replace(&mut parent.keys[i - 1], separator);
+        // see the matching comment in `rotate_left`: `old_separator` comes
+        // from `parent`, whose own tombstones haven't been purged yet at
+        // this point in the compaction pass, so it can't be assumed live.
+        let old_separator_live = !old_separator.deleted as usize;
+
+        let mut node = file.write_block(txn_id, node_id).await?;
+        node.keys.insert(0, old_separator);
+        if let Some(child_id) = moved_child {
+            node.children.insert(0, child_id);
+        }
+        node.reduced += old_separator_live + moved_child_reduced;
+
+        Ok(())
+    }
+
+    /// Merge `parent`'s children at `i` and `i + 1`--both at the minimum key
+    /// count--into one node, pulling the separator key between them down
+    /// from `parent`. Deletes the now-empty right child's block, and
+    /// recurses if merging leaves `parent` itself underflowed.
+    fn merge_children<'a>(
+        &'a self,
+        txn_id: TxnId,
+        mut parent: <F::Block as Block<Node, F>>::WriteLock,
+        parent_id: NodeId,
+        i: usize,
+    ) -> TCBoxTryFuture<'a, ()> {
+        Box::pin(async move {
+            let file = &self.inner.file;
+            let order = self.inner.order;
+
+            let left_id = parent.children[i].clone();
+            let right_id = parent.children.remove(i + 1);
+            let separator = parent.keys.remove(i);
+            let separator_live = !separator.deleted as usize;
+
+            let right = file.read_block(txn_id, right_id.clone()).await?;
+            let right_keys = right.keys.clone();
+            let right_children = right.children.clone();
+            let right_reduced = right.reduced;
+            std::mem::drop(right);
+
+            let mut left = file.write_block(txn_id, left_id.clone()).await?;
+            left.keys.push(separator);
+            left.keys.extend(right_keys);
+            left.children.extend(right_children);
+            // this merge pulls a (normally still-live) separator down from
+            // `parent`, unlike the plain tombstone-removal merge in
+            // `_compact`--`parent`'s own cached `reduced` is unaffected
+            // either way, since the separator's contribution just moves from
+            // `parent`'s own-key term into `left`'s subtree
+            left.reduced = Count::combine(Count::combine(left.reduced, right_reduced), separator_live);
+            std::mem::drop(left);
+
+            file.delete_block(txn_id, right_id).await?;
+
+            let grandparent_id = parent.parent.clone();
+            let parent_underflowed = parent.keys.len() < order - 1;
+            std::mem::drop(parent);
+
+            match grandparent_id {
+                Some(grandparent_id) if parent_underflowed => {
+                    self.fix_underflow(txn_id, grandparent_id, parent_id).await
+                }
+                None => self.collapse_root(txn_id, parent_id).await,
+                Some(_) => Ok(()),
+            }
+        })
+    }
+
+    /// If `node_id` is the root and has been left with no keys and exactly
+    /// one child (as happens after merging its last two children), promote
+    /// that child to root and discard `node_id`'s now-redundant block.
+    async fn collapse_root(&self, txn_id: TxnId, node_id: NodeId) -> TCResult<()> {
+        let file = &self.inner.file;
+
+        let (keys_empty, only_child) = {
+            let node = file.read_block(txn_id, node_id.clone()).await?;
+            (node.keys.is_empty(), node.children.first().cloned())
+        };
+
+        if !keys_empty {
+            return Ok(());
+        }
+
+        let only_child = match only_child {
+            Some(child_id) => child_id,
+            None => return Ok(()),
+        };
+
+        let mut root_id = self.inner.root.write(txn_id).await?;
+        if *root_id != node_id {
+            return Ok(());
+        }
+
+        {
+            let mut child = file.write_block(txn_id, only_child.clone()).await?;
+            child.parent = None;
+        }
+
+        *root_id = only_child;
+        file.delete_block(txn_id, node_id).await
+    }
 }
 
 impl<F, D, T> Instance for BTreeFile<F, D, T>
@@ -588,7 +1478,11 @@ where
 {
     type Slice = BTreeSlice<F, D, T>;
 
-    fn collator(&'_ self) -> &'_ ValueCollator {
+    // `BTreeInstance::collator`'s declaration lives outside this crate (no
+    // `mod.rs` ships in this snapshot); assumed compatible with returning
+    // the schema-aware collator now that `Inner::collator` isn't a bare
+    // `ValueCollator` any more.
+    fn collator(&'_ self) -> &'_ SchemaCollator {
         &self.inner.collator
     }
 
@@ -633,7 +1527,7 @@ where
     }
 
     async fn insert(&self, txn_id: TxnId, key: Key) -> TCResult<()> {
-        let key = validate_key(key, &self.inner.schema)?;
+        let key = validate_key(key, &self.inner.schema, &self.inner.constraints).await?;
 
         let file = &self.inner.file;
         let order = self.inner.order;
@@ -666,6 +1560,7 @@ where
 
         if root.keys.len() == (2 * order) - 1 {
             // split_child will need a write lock on root, so release the read lock
+            let reduced = root.reduced;
             std::mem::drop(root);
 
             debug!("split root node");
@@ -676,6 +1571,9 @@ where
 
             let mut new_root = Node::new(false, None);
             new_root.children.push(old_root_id.clone());
+            // splitting doesn't change the subtree's total key count, so the
+            // new root starts out with the same `reduced` the old root had
+            new_root.reduced = reduced;
 
             let new_root = file
                 .create_block(txn_id, (*root_id).clone(), new_root)
@@ -683,11 +1581,13 @@ where
 
             let new_root = new_root.write().await;
             let new_root = self.split_child(txn_id, old_root_id, new_root, 0).await?;
-            self._insert(txn_id, new_root, key).await
+            self._insert(txn_id, new_root, key).await?;
+            Ok(())
         } else {
             // no need to keep this write lock since we're not splitting the root node
             std::mem::drop(root_id);
-            self._insert(txn_id, root, key).await
+            self._insert(txn_id, root, key).await?;
+            Ok(())
         }
     }
 
@@ -699,6 +1599,10 @@ where
 #[async_trait]
 impl<F: File<Node> + Transact, D: Dir, T: Transaction<D>> Transact for BTreeFile<F, D, T> {
     async fn commit(&self, txn_id: &TxnId) {
+        if let Err(cause) = self.compact(txn_id.clone()).await {
+            log::warn!("BTree compaction failed: {}", cause);
+        }
+
         join!(
             self.inner.file.commit(txn_id),
             self.inner.root.commit(txn_id)
@@ -736,7 +1640,20 @@ impl<F: File<Node>, D: Dir, T: Transaction<D>> Persist for BTreeFile<F, D, T> {
 
         let root = root.ok_or_else(|| TCError::internal("BTree corrupted (missing root block)"))?;
 
-        Ok(BTreeFile::new(file, schema, order, root))
+        // `Persist::load`'s signature (defined outside this crate) has
+        // nowhere to carry a per-column direction or constraints, so a loaded
+        // BTree always re-opens ascending and unconstrained; a future change
+        // could persist `collation` and `constraints` alongside the schema
+        // (e.g. in the root block) and read them back here instead of
+        // defaulting them.
+        Ok(BTreeFile::new(
+            file,
+            schema,
+            order,
+            root,
+            SchemaCollator::default(),
+            Vec::new(),
+        ))
     }
 }
 
@@ -796,17 +1713,186 @@ fn value_of(bound: &std::ops::Bound<Value>) -> Value {
     }
 }
 
+/// Ceiling on a key's total *encoded* byte size (summed column-by-column
+/// after dtype coercion), modeled on Deno KV's split read/write limits:
+/// writes get the tighter bound here. The read-side bound (`2049`, one
+/// byte looser since a range scan's exclusive upper bound is compared
+/// against, not stored) belongs in `validate_range`, but that function is
+/// declared outside this crate in this snapshot (see the `use super::{..}`
+/// at the top of this file), so it isn't enforced from here.
+const MAX_WRITE_KEY_SIZE: u64 = 2048;
+
+/// An extra check on a column's coerced value, beyond `dtype`, enforced by
+/// `validate_key`: a maximum length for a string-like column, or inclusive
+/// numeric bounds (either side `None` meaning unbounded) for a numeric one.
+///
+/// This doesn't live on `Column` itself--`Column` has no definition anywhere
+/// in this snapshot (it's referenced only via the `RowSchema` imported in
+/// `use super::{..}` above), so there's no struct here to add a field to.
+/// Instead it's keyed by column position and threaded alongside the schema
+/// wherever one is built (`create`, `load_sorted`), the same way
+/// `SchemaCollator`'s per-column ascending flags are (see that struct's
+/// comment): a position past the end of the list, same as `None`, means "no
+/// extra constraint on this column".
+#[derive(Clone)]
+pub enum ColumnConstraint {
+    MaxLen(usize),
+    Range(Option<Number>, Option<Number>),
+}
+
 #[inline]
-fn validate_key(key: Key, schema: &RowSchema) -> TCResult<Key> {
+async fn validate_key(
+    key: Key,
+    schema: &RowSchema,
+    constraints: &[Option<ColumnConstraint>],
+) -> TCResult<Key> {
     if key.len() != schema.len() {
         return Err(TCError::bad_request("invalid key length", Tuple::from(key)));
     }
 
-    key.into_iter()
+    let key: Vec<Value> = key
+        .into_iter()
         .zip(schema)
         .map(|(val, col)| {
-            val.into_type(col.dtype)
-                .ok_or_else(|| TCError::bad_request("invalid value for column", &col.name))
+            let dtype = col.dtype;
+            val.clone().into_type(dtype).ok_or_else(|| {
+                bad_request!(
+                    "expected {} but found {} for column {}",
+                    dtype,
+                    val,
+                    col.name
+                )
+            })
+        })
+        .collect::<TCResult<Vec<Value>>>()?;
+
+    for (i, (val, col)) in key.iter().zip(schema).enumerate() {
+        let constraint = match constraints.get(i).and_then(Option::as_ref) {
+            Some(constraint) => constraint,
+            None => continue,
+        };
+
+        match (constraint, val) {
+            (ColumnConstraint::MaxLen(max), Value::String(s)) if s.len() > *max => {
+                return Err(TCError::bad_request(
+                    format!(
+                        "column '{}' exceeds the maximum length of {} bytes",
+                        col.name, max
+                    ),
+                    s.len(),
+                ));
+            }
+            (ColumnConstraint::Range(Some(min), _), Value::Number(n)) if n < min => {
+                return Err(TCError::bad_request(
+                    format!("column '{}' is below the minimum allowed value of", col.name),
+                    min.clone(),
+                ));
+            }
+            (ColumnConstraint::Range(_, Some(max)), Value::Number(n)) if n > max => {
+                return Err(TCError::bad_request(
+                    format!("column '{}' exceeds the maximum allowed value of", col.name),
+                    max.clone(),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    let mut size = 0u64;
+    for (val, col) in key.iter().zip(schema) {
+        let encoded = destream_json::en::encode(val.clone())
+            .map_err(|cause| TCError::bad_request("serialization error", cause))?;
+
+        size += encoded
+            .map_err(|cause| TCError::bad_request("serialization error", cause))
+            .try_fold(0u64, |size, chunk| {
+                future::ready(Ok(size + chunk.len() as u64))
+            })
+            .await?;
+
+        if size > MAX_WRITE_KEY_SIZE {
+            return Err(TCError::bad_request(
+                format!(
+                    "key exceeds the maximum write size of {} bytes (column '{}' brought it to {})",
+                    MAX_WRITE_KEY_SIZE, col.name, size
+                ),
+                Tuple::from(key),
+            ));
+        }
+    }
+
+    Ok(key)
+}
+
+/// One column's `dtype` coercion failure, as collected by `validate_key_all`
+/// rather than returned immediately. `expected_dtype` and `column_name` are
+/// captured pre-formatted (`Display`, not the underlying types) since the
+/// only thing this record is ever used for is building one aggregate message
+/// out of several of them.
+#[derive(Clone)]
+struct ColumnCoercionError {
+    column_name: String,
+    index: usize,
+    expected_dtype: String,
+    received: Value,
+}
+
+impl fmt::Display for ColumnCoercionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "column '{}' (index {}) expected {} but received {}",
+            self.column_name, self.index, self.expected_dtype, self.received
+        )
+    }
+}
+
+/// Like `validate_key`, but rather than stopping at the first column whose
+/// value fails `dtype` coercion, coerces every column and, if any failed,
+/// aggregates all of their failures into one `TCError::bad_request` instead
+/// of just the first--so a caller fixing one bad value in a wide composite
+/// key learns about the rest of them in the same round trip instead of
+/// discovering them one at a time over repeated requests. This only covers
+/// `dtype` coercion, not the length/range/size checks below it in
+/// `validate_key`, since those can only be evaluated on an already-coerced
+/// value and reject the whole key anyway once the types line up.
+///
+/// `insert` and `load_sorted` keep using the fail-fast `validate_key`--this
+/// is an alternate entry point for callers (e.g. a bulk key-validation
+/// endpoint) that want every failure up front rather than the first one.
+#[allow(dead_code)]
+async fn validate_key_all(key: Key, schema: &RowSchema) -> TCResult<Key> {
+    if key.len() != schema.len() {
+        return Err(TCError::bad_request("invalid key length", Tuple::from(key)));
+    }
+
+    let results: Vec<Result<Value, ColumnCoercionError>> = key
+        .into_iter()
+        .zip(schema)
+        .enumerate()
+        .map(|(index, (val, col))| {
+            let dtype = col.dtype;
+            val.clone().into_type(dtype).ok_or_else(|| ColumnCoercionError {
+                column_name: col.name.to_string(),
+                index,
+                expected_dtype: dtype.to_string(),
+                received: val,
+            })
         })
-        .collect()
+        .collect();
+
+    let (oks, errs): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+
+    if !errs.is_empty() {
+        let errs: Vec<ColumnCoercionError> = errs.into_iter().map(Result::unwrap_err).collect();
+        let message = errs
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>()
+            .join("; ");
+
+        return Err(TCError::bad_request(format!("invalid key: {}", message), ""));
+    }
+
+    Ok(oks.into_iter().map(Result::unwrap).collect())
 }
@@ -1,10 +1,10 @@
-use std::convert::TryFrom;
 use std::fmt;
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use destream::{de, en};
-use futures::TryFutureExt;
+use futures::TryStreamExt;
+use sha2::{Digest, Sha256};
 
 use error::*;
 use transact::fs::BlockData;
@@ -13,15 +13,113 @@ use transact::TxnId;
 
 use crate::scalar::OpRef;
 
+/// The hash chained into the very first block of a `Chain`, which has no
+/// real predecessor block to reference.
+pub(crate) fn genesis_hash() -> Bytes {
+    Bytes::from(Sha256::digest(&[]).to_vec())
+}
+
+/// `destream_json`-encode `contents`, for hashing. Assumes `OpRef:
+/// en::IntoStream`, the same assumption the pre-existing `en::IntoStream
+/// for ChainBlock` impl below already makes for `Vec<OpRef>`.
+///
+/// Driven through the real async encoder stream rather than
+/// `futures::executor::block_on`--this runs from [`ChainBlock::verify`],
+/// which `chain::load` calls once per block while reading a chain back off
+/// of disk, and blocking the thread driving that on a nested executor is
+/// exactly the anti-pattern `Transaction::resolve_queue` had to be fixed to
+/// avoid for the same reason.
+async fn encode_contents(contents: &[OpRef]) -> TCResult<Vec<u8>> {
+    let mut encoded = destream_json::en::encode(contents.to_vec())
+        .map_err(|e| TCError::internal(format!("unable to serialize ChainBlock contents: {}", e)))?;
+
+    let mut buf = Vec::new();
+    while let Some(chunk) = encoded
+        .try_next()
+        .map_err(|e| TCError::internal(format!("unable to serialize ChainBlock contents: {}", e)))
+        .await?
+    {
+        buf.extend_from_slice(&chunk);
+    }
+
+    Ok(buf)
+}
+
 #[derive(Clone)]
 pub struct ChainBlock {
+    prev_hash: Bytes,
     hash: Bytes,
     contents: Vec<OpRef>,
 }
 
 impl ChainBlock {
-    pub fn append(&mut self, op_ref: OpRef) {
+    /// Construct the first block of a new chain.
+    pub async fn new() -> TCResult<Self> {
+        Self::with_prev_hash(genesis_hash()).await
+    }
+
+    /// Construct a new, empty block which follows the block whose digest is
+    /// `prev_hash` in the chain.
+    pub async fn with_prev_hash(prev_hash: Bytes) -> TCResult<Self> {
+        let mut block = Self {
+            prev_hash,
+            hash: Bytes::new(),
+            contents: Vec::new(),
+        };
+
+        block.rehash().await?;
+        Ok(block)
+    }
+
+    /// This block's own hash, to chain into the block which follows it (see
+    /// [`Self::with_prev_hash`]).
+    pub fn hash(&self) -> Bytes {
+        self.hash.clone()
+    }
+
+    pub async fn append(&mut self, op_ref: OpRef) -> TCResult<()> {
         self.contents.push(op_ref);
+        self.rehash().await
+    }
+
+    async fn rehash(&mut self) -> TCResult<()> {
+        self.hash = Self::digest(&self.prev_hash, &self.contents).await?;
+        Ok(())
+    }
+
+    async fn digest(prev_hash: &Bytes, contents: &[OpRef]) -> TCResult<Bytes> {
+        let encoded = encode_contents(contents).await?;
+
+        let mut hasher = Sha256::default();
+        hasher.update(prev_hash);
+        hasher.update(&encoded);
+        Ok(Bytes::from(hasher.finalize().to_vec()))
+    }
+
+    /// Recompute this block's hash from `prev_hash` and its own contents,
+    /// and check it against the hash stored on this block--so a tampered or
+    /// truncated on-disk chain is rejected rather than silently trusted.
+    /// Called once per block, in order, by [`crate::chain::load`] as it
+    /// reads a chain back off of disk, with the previous block's
+    /// [`Self::hash`] (or [`genesis_hash`] for the first block)--`load`
+    /// refuses to finish loading on the first mismatch.
+    pub async fn verify(&self, prev_hash: &[u8]) -> TCResult<()> {
+        if self.prev_hash.as_ref() != prev_hash {
+            return Err(TCError::bad_request(
+                "ChainBlock does not follow the expected predecessor block",
+                "(hash chain broken)",
+            ));
+        }
+
+        let expected = Self::digest(&self.prev_hash, &self.contents).await?;
+        if expected != self.hash {
+            return Err(TCError::bad_request(
+                "ChainBlock hash does not match its contents--the chain may have been tampered with",
+                "(hash mismatch)",
+            ));
+        }
+
+        Ok(())
     }
 }
 
@@ -46,29 +144,20 @@ impl de::FromStream for ChainBlock {
 
     async fn from_stream<D: de::Decoder>(context: (), decoder: &mut D) -> Result<Self, D::Error> {
         de::FromStream::from_stream(context, decoder)
-            .map_ok(|(hash, contents)| Self { hash, contents })
+            .map_ok(|(prev_hash, hash, contents)| Self {
+                prev_hash,
+                hash,
+                contents,
+            })
             .await
     }
 }
 
 impl<'en> en::IntoStream<'en> for ChainBlock {
     fn into_stream<E: en::Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        let prev_hash = base64::encode(self.prev_hash);
         let hash = base64::encode(self.hash);
-        en::IntoStream::into_stream((hash, self.contents), encoder)
-    }
-}
-
-impl TryFrom<Bytes> for ChainBlock {
-    type Error = TCError;
-
-    fn try_from(_data: Bytes) -> TCResult<Self> {
-        unimplemented!()
-    }
-}
-
-impl From<ChainBlock> for Bytes {
-    fn from(_block: ChainBlock) -> Bytes {
-        unimplemented!()
+        en::IntoStream::into_stream((prev_hash, hash, self.contents), encoder)
     }
 }
 
@@ -76,4 +165,4 @@ impl fmt::Display for ChainBlock {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str("(chain block)")
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,33 @@
+//! A hash-linked sequence of [`ChainBlock`]s persisted in a [`File`].
+
+use error::*;
+use transact::fs::File;
+use transact::TxnId;
+
+mod block;
+
+pub use block::ChainBlock;
+
+/// Read a chain's blocks back out of `file`, from block `"0"` through
+/// `latest_block` in order, verifying each one against the hash of the
+/// block before it (starting from [`block::genesis_hash`]) as it's read--
+/// so that a tampered or truncated on-disk chain is rejected here, at
+/// startup, instead of being silently trusted.
+pub async fn load<F: File<ChainBlock>>(
+    file: &F,
+    txn_id: TxnId,
+    latest_block: u64,
+) -> TCResult<Vec<ChainBlock>> {
+    let mut prev_hash = block::genesis_hash();
+    let mut blocks = Vec::with_capacity(latest_block as usize + 1);
+
+    for block_id in 0..=latest_block {
+        let id = block_id.to_string().parse()?;
+        let block = file.read_block(txn_id.clone(), id).await?;
+        block.verify(&prev_hash).await?;
+        prev_hash = block.hash();
+        blocks.push((*block).clone());
+    }
+
+    Ok(blocks)
+}
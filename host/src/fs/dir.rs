@@ -2,12 +2,13 @@ use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::str::FromStr;
 
 use async_trait::async_trait;
 use futures::Future;
 
 use error::*;
-use generic::{Id, PathSegment};
+use generic::{Id, NativeClass, PathSegment, TCPathBuf};
 use transact::fs;
 use transact::lock::{Mutable, TxnLock};
 use transact::TxnId;
@@ -22,12 +23,113 @@ pub enum FileEntry {
     Chain(File<ChainBlock>),
 }
 
+// The name of the marker file `Dir::load` reads to recover which
+// `StateType` a persisted file entry was created with--see `file_registry`
+// and `persisted_class` below. Block files don't otherwise carry their
+// class on disk (only their raw block contents), so without this marker a
+// reload has no way to tell a B-tree node file from a tensor chunk file
+// from a `Chain`.
+const CLASS_MARKER: &str = ".class";
+
+type FileEntryConstructor = fn(Cache, PathBuf) -> FileEntry;
+type FileEntryLoader =
+    fn(Cache, PathBuf, DirContents) -> Pin<Box<dyn Future<Output = TCResult<FileEntry>>>>;
+
+// One entry per `BlockData` type that can back a `FileEntry`, each pairing
+// a `StateType` predicate with a constructor and a loader. Adding support
+// for a new on-disk structure (a B-tree node file, a tensor chunk file,
+// ...) means adding an entry here--and a matching `TryFrom<FileEntry>` impl
+// for its `File<T>`, as `File<ChainBlock>`'s is below--rather than editing
+// `FileEntry` itself or the match in `Dir::load`.
+struct FileEntryRegistration {
+    matches: fn(&StateType) -> bool,
+    construct: FileEntryConstructor,
+    load: FileEntryLoader,
+}
+
+fn file_registry() -> Vec<FileEntryRegistration> {
+    vec![FileEntryRegistration {
+        matches: |class| matches!(class, StateType::Chain(_)),
+        construct: |cache, path| FileEntry::Chain(File::new(cache, path)),
+        load: |cache, path, contents| {
+            Box::pin(async move {
+                let file = File::load(cache, path, contents).await?;
+                Ok(FileEntry::Chain(file))
+            })
+        },
+    }]
+}
+
+// Read the `StateType` recorded by `persist_class_marker` for the file at
+// `path`. Returns `None` if there's no marker, which is the case for any
+// file written before this marker existed--callers should fall back to the
+// registry's first entry (the historical behavior, when every file was
+// assumed to hold `ChainBlock`s) in that case.
+async fn persisted_class(path: &PathBuf) -> TCResult<Option<StateType>> {
+    let marker_path = path.join(CLASS_MARKER);
+    match tokio::fs::read_to_string(&marker_path).await {
+        Ok(contents) => {
+            let class_path = TCPathBuf::from_str(contents.trim()).map_err(|cause| {
+                TCError::internal(format!(
+                    "invalid class marker at {:?}: {}",
+                    marker_path, cause
+                ))
+            })?;
+
+            StateType::from_path(&class_path)
+                .map(Some)
+                .ok_or_else(|| {
+                    TCError::internal(format!(
+                        "unrecognized class marker at {:?}: {}",
+                        marker_path, class_path
+                    ))
+                })
+        }
+        Err(ref cause) if cause.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(cause) => Err(TCError::internal(format!(
+            "unable to read class marker at {:?}: {}",
+            marker_path, cause
+        ))),
+    }
+}
+
+async fn persist_class_marker(path: &PathBuf, class: &StateType) -> TCResult<()> {
+    tokio::fs::create_dir_all(path).await.map_err(|cause| {
+        TCError::internal(format!("unable to create directory at {:?}: {}", path, cause))
+    })?;
+
+    tokio::fs::write(path.join(CLASS_MARKER), class.path().to_string())
+        .await
+        .map_err(|cause| {
+            TCError::internal(format!(
+                "unable to write class marker at {:?}: {}",
+                path, cause
+            ))
+        })
+}
+
 impl FileEntry {
     fn new(cache: Cache, path: PathBuf, class: StateType) -> TCResult<Self> {
-        match class {
-            StateType::Chain(_) => Ok(Self::Chain(File::new(cache, path))),
-            other => Err(TCError::bad_request("cannot create file for", other)),
-        }
+        file_registry()
+            .into_iter()
+            .find(|registration| (registration.matches)(&class))
+            .map(|registration| (registration.construct)(cache, path))
+            .ok_or_else(|| TCError::bad_request("cannot create file for", class))
+    }
+
+    async fn load(cache: Cache, path: PathBuf, contents: DirContents) -> TCResult<Self> {
+        let registration = match persisted_class(&path).await? {
+            Some(class) => file_registry()
+                .into_iter()
+                .find(|registration| (registration.matches)(&class))
+                .ok_or_else(|| TCError::bad_request("cannot load file of class", class))?,
+            None => file_registry()
+                .into_iter()
+                .next()
+                .expect("at least one registered file type"),
+        };
+
+        (registration.load)(cache, path, contents).await
     }
 }
 
@@ -78,9 +180,8 @@ impl Dir {
                     let path = fs_path(&path, &name);
                     let contents = dir_contents(&path).await?;
                     if contents.iter().all(|(_, meta)| meta.is_file()) {
-                        // TODO: support other file types
-                        let file = File::load(cache.clone(), path, contents).await?;
-                        entries.insert(name, DirEntry::File(FileEntry::Chain(file)));
+                        let file = FileEntry::load(cache.clone(), path, contents).await?;
+                        entries.insert(name, DirEntry::File(file));
                     } else if contents.iter().all(|(_, meta)| meta.is_dir()) {
                         let dir = Dir::load(cache.clone(), path, contents).await?;
                         entries.insert(name, DirEntry::Dir(dir));
@@ -101,6 +202,61 @@ impl Dir {
             }
         })
     }
+
+    /// Walk `path` from this directory, descending through each segment's
+    /// subdirectory in turn, transactionally (each `get_dir` call reads the
+    /// child's `entries` under the same `txn_id`). Returns `Ok(None)` as soon
+    /// as any segment is missing, same as a single-segment `get_dir` would.
+    pub async fn get_dir_path(&self, txn_id: &TxnId, path: &[PathSegment]) -> TCResult<Option<Self>> {
+        let mut dir = self.clone();
+        for name in path {
+            match fs::Dir::get_dir(&dir, txn_id, name).await? {
+                Some(child) => dir = child,
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(dir))
+    }
+
+    /// Like `get_dir_path`, but the last segment of `path` names a file: all
+    /// but the last segment are descended as subdirectories, then the last
+    /// is resolved with `get_file` against the directory that contains it.
+    pub async fn get_file_path(
+        &self,
+        txn_id: &TxnId,
+        path: &[PathSegment],
+    ) -> TCResult<Option<FileEntry>> {
+        let (name, dir_path) = path
+            .split_last()
+            .ok_or_else(|| TCError::bad_request("cannot look up a file at an empty path", "/"))?;
+
+        match self.get_dir_path(txn_id, dir_path).await? {
+            Some(dir) => fs::Dir::get_file(&dir, txn_id, name).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Like `get_file_path`, but downcasts the result to `File<B>` for
+    /// whichever block type `B` the caller is expecting, via `B`'s
+    /// `TryFrom<FileEntry>` impl (see `File<ChainBlock>`'s, above, for the
+    /// pattern a new registered block type should follow). Returns a
+    /// `bad_request` error if the file exists but holds a different block
+    /// type than `B`.
+    pub async fn get_file_as<B>(
+        &self,
+        txn_id: &TxnId,
+        path: &[PathSegment],
+    ) -> TCResult<Option<File<B>>>
+    where
+        B: fs::BlockData,
+        File<B>: TryFrom<FileEntry, Error = TCError>,
+    {
+        match self.get_file_path(txn_id, path).await? {
+            Some(file) => File::<B>::try_from(file).map(Some),
+            None => Ok(None),
+        }
+    }
 }
 
 #[async_trait]
@@ -120,7 +276,8 @@ impl fs::Dir for Dir {
 
     async fn create_file(&self, txn_id: TxnId, name: Id, class: StateType) -> TCResult<Self::File> {
         let path = fs_path(&self.path, &name);
-        let file = FileEntry::new(self.cache.clone(), path, class)?;
+        let file = FileEntry::new(self.cache.clone(), path.clone(), class.clone())?;
+        persist_class_marker(&path, &class).await?;
 
         let mut entries = self.entries.write(txn_id).await?;
         entries.insert(name, DirEntry::File(file.clone()));
@@ -128,12 +285,22 @@ impl fs::Dir for Dir {
         Ok(file)
     }
 
-    async fn get_dir(&self, _txn_id: &TxnId, _name: &PathSegment) -> TCResult<Option<Self>> {
-        unimplemented!()
+    async fn get_dir(&self, txn_id: &TxnId, name: &PathSegment) -> TCResult<Option<Self>> {
+        let entries = self.entries.read(txn_id).await?;
+        match entries.get(name) {
+            Some(DirEntry::Dir(dir)) => Ok(Some(dir.clone())),
+            Some(DirEntry::File(_)) => Err(TCError::bad_request("not a directory", name)),
+            None => Ok(None),
+        }
     }
 
-    async fn get_file(&self, _txn_id: &TxnId, _name: &Id) -> TCResult<Option<Self::File>> {
-        unimplemented!()
+    async fn get_file(&self, txn_id: &TxnId, name: &Id) -> TCResult<Option<Self::File>> {
+        let entries = self.entries.read(txn_id).await?;
+        match entries.get(name) {
+            Some(DirEntry::File(file)) => Ok(Some(file.clone())),
+            Some(DirEntry::Dir(_)) => Err(TCError::bad_request("not a file", name)),
+            None => Ok(None),
+        }
     }
 }
 
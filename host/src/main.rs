@@ -1,5 +1,5 @@
 use std::net::IpAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use bytes::Bytes;
@@ -16,10 +16,34 @@ use tinychain::gateway::Gateway;
 use tinychain::object::InstanceClass;
 use tinychain::*;
 
+mod repl;
+
 type TokioError = Box<dyn std::error::Error + Send + Sync + 'static>;
 
 const MIN_CACHE_SIZE: usize = 5000;
 
+/// Maximum number of attempts to bootstrap a `Cluster` with its peer replicas before giving up.
+const MAX_BOOTSTRAP_ATTEMPTS: u32 = 6;
+
+/// Base delay for cluster peer bootstrap retries; doubles on every attempt up to a cap, plus
+/// up to 50% jitter so that peers restarting together don't all retry in lockstep.
+const BOOTSTRAP_BASE_DELAY: Duration = Duration::from_millis(250);
+const BOOTSTRAP_MAX_DELAY: Duration = Duration::from_secs(30);
+
+fn backoff_with_jitter(attempt: u32) -> Duration {
+    let exp = BOOTSTRAP_BASE_DELAY.saturating_mul(1 << attempt.min(16));
+    let capped = exp.min(BOOTSTRAP_MAX_DELAY);
+
+    let jitter_ms = (capped.as_millis() as u64 / 2).max(1);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    let jitter = Duration::from_millis(nanos % jitter_ms);
+
+    capped + jitter
+}
+
 fn data_size(flag: &str) -> TCResult<u64> {
     const ERR: &str = "unable to parse data size";
 
@@ -69,6 +93,33 @@ struct Config {
     #[structopt(long = "cache_size", default_value = "1G", parse(try_from_str = data_size))]
     pub cache_size: u64,
 
+    #[structopt(
+        long = "cache_eviction_interval",
+        default_value = "1",
+        parse(try_from_str = duration),
+        about = "how often the block cache checks for entries to evict, in seconds"
+    )]
+    pub cache_eviction_interval: Duration,
+
+    #[structopt(
+        long = "cache_ttl",
+        parse(try_from_str = duration),
+        about = "maximum time a cached block may go unused before it is evicted, in seconds"
+    )]
+    pub cache_ttl: Option<Duration>,
+
+    #[structopt(
+        long = "workers",
+        about = "number of worker threads for the Tokio runtime (defaults to the number of CPUs)"
+    )]
+    pub workers: Option<usize>,
+
+    #[structopt(
+        long = "max_blocking_threads",
+        about = "maximum number of threads for blocking (e.g. filesystem) tasks"
+    )]
+    pub max_blocking_threads: Option<usize>,
+
     #[structopt(
         long = "data_dir",
         about = "data directory (required to host a Cluster)"
@@ -88,25 +139,142 @@ struct Config {
 
     #[structopt(long = "http_port", default_value = "8702")]
     pub http_port: u16,
+
+    #[structopt(long = "https_port", default_value = "8703")]
+    pub https_port: u16,
+
+    #[structopt(
+        long = "tls_cert",
+        about = "path to a PEM-encoded TLS certificate; enables HTTPS when set together with --tls_key"
+    )]
+    pub tls_cert: Option<PathBuf>,
+
+    #[structopt(
+        long = "tls_key",
+        about = "path to the PEM-encoded private key for --tls_cert"
+    )]
+    pub tls_key: Option<PathBuf>,
+
+    #[structopt(
+        long = "repl",
+        about = "launch an interactive REPL alongside the HTTP listener"
+    )]
+    pub repl: bool,
 }
 
 impl Config {
-    fn gateway(&self) -> gateway::Config {
-        gateway::Config {
+    fn gateway(&self) -> TCResult<gateway::Config> {
+        let tls = match (&self.tls_cert, &self.tls_key) {
+            (Some(cert_path), Some(key_path)) => Some(gateway::TlsConfig {
+                cert_path: cert_path.clone(),
+                key_path: key_path.clone(),
+                https_port: self.https_port,
+            }),
+            (None, None) => None,
+            _ => {
+                return Err(TCError::bad_request(
+                    "--tls_cert and --tls_key must be given together",
+                    "",
+                ))
+            }
+        };
+
+        Ok(gateway::Config {
             addr: self.address,
             http_port: self.http_port,
             request_ttl: self.request_ttl,
+            tls,
+        })
+    }
+}
+
+/// Decode an `InstanceClass` from a cluster config file, choosing a codec by
+/// file extension: `.prs`/`.preserves` is decoded as
+/// [Preserves](https://preserves.dev) binary data, everything else as JSON.
+async fn decode_cluster_config(path: &Path, bytes: Vec<u8>) -> TCResult<InstanceClass> {
+    let source = stream::once(future::ready(Ok(Bytes::from(bytes))));
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("prs") | Some("preserves") => {
+            let mut decoder = destream_preserves::de::Decoder::from_stream(source);
+            InstanceClass::from_stream((), &mut decoder)
+                .await
+                .map_err(|cause| TCError::bad_request("invalid Preserves cluster config", cause))
+        }
+        _ => {
+            let mut decoder = destream_json::de::Decoder::from_stream(source);
+            InstanceClass::from_stream((), &mut decoder)
+                .await
+                .map_err(|cause| TCError::bad_request("invalid JSON cluster config", cause))
         }
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), TokioError> {
+/// Instantiate a `Cluster` and bootstrap it with its peer replicas, retrying
+/// with exponential backoff and jitter if bootstrapping fails, since peers
+/// restarting at the same time may not be reachable yet.
+async fn instantiate_with_backoff(
+    txn: &txn::Txn,
+    host: LinkHost,
+    class: InstanceClass,
+    data_dir: fs::Dir,
+) -> TCResult<cluster::Cluster> {
+    let mut attempt = 0;
+
+    loop {
+        match cluster::instantiate(txn, host.clone(), class.clone(), data_dir.clone()).await {
+            Ok(cluster) => return Ok(cluster),
+            Err(cause) if attempt + 1 < MAX_BOOTSTRAP_ATTEMPTS => {
+                let delay = backoff_with_jitter(attempt);
+                log::warn!(
+                    "failed to bootstrap cluster (attempt {} of {}): {}; retrying in {:?}",
+                    attempt + 1,
+                    MAX_BOOTSTRAP_ATTEMPTS,
+                    cause,
+                    delay
+                );
+
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(cause) => return Err(cause),
+        }
+    }
+}
+
+fn main() -> Result<(), TokioError> {
     let config = Config::from_args();
-    let gateway_config = config.gateway();
 
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(config.log_level))
-        .init();
+    let workers = config.workers.unwrap_or_else(num_cpus::get);
+
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.enable_all().worker_threads(workers);
+
+    if let Some(max_blocking_threads) = config.max_blocking_threads {
+        builder.max_blocking_threads(max_blocking_threads);
+    }
+
+    let runtime = builder.build()?;
+
+    log::info!(
+        "starting Tokio runtime with {} worker thread(s){}",
+        workers,
+        config
+            .max_blocking_threads
+            .map(|n| format!(" and up to {} blocking thread(s)", n))
+            .unwrap_or_default()
+    );
+
+    runtime.block_on(run(config))
+}
+
+async fn run(config: Config) -> Result<(), TokioError> {
+    let gateway_config = config.gateway()?;
+
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(config.log_level.as_str()),
+    )
+    .init();
 
     if !config.workspace.exists() {
         log::info!(
@@ -122,7 +290,21 @@ async fn main() -> Result<(), TokioError> {
         return Err(TCError::bad_request("the minimum cache size is", MIN_CACHE_SIZE).into());
     }
 
-    let cache = freqfs::Cache::new(config.cache_size as usize, Duration::from_secs(1), None);
+    log::info!(
+        "block cache: size {}, eviction interval {:?}, ttl {}",
+        config.cache_size,
+        config.cache_eviction_interval,
+        config
+            .cache_ttl
+            .map(|ttl| format!("{:?}", ttl))
+            .unwrap_or_else(|| "unbounded".to_string())
+    );
+
+    let cache = freqfs::Cache::new(
+        config.cache_size as usize,
+        config.cache_eviction_interval,
+        config.cache_ttl,
+    );
     let workspace = cache.clone().load(config.workspace).await?;
     let txn_id = TxnId::new(Gateway::time());
 
@@ -158,28 +340,31 @@ async fn main() -> Result<(), TokioError> {
             TCError::internal("the --data_dir option is required to host a Cluster")
         })?;
 
-        let host = LinkHost::from((
-            LinkProtocol::HTTP,
-            config.address.clone(),
-            Some(config.http_port),
-        ));
+        let host = if let Some(tls) = &gateway_config.tls {
+            LinkHost::from((
+                LinkProtocol::HTTPS,
+                config.address.clone(),
+                Some(tls.https_port),
+            ))
+        } else {
+            LinkHost::from((
+                LinkProtocol::HTTP,
+                config.address.clone(),
+                Some(config.http_port),
+            ))
+        };
 
         for path in config.clusters {
-            let config = tokio::fs::read(&path)
+            let bytes = tokio::fs::read(&path)
                 .await
                 .expect(&format!("read from {:?}", &path));
 
-            let mut decoder = destream_json::de::Decoder::from_stream(stream::once(future::ready(
-                Ok(Bytes::from(config)),
-            )));
-
-            let cluster = match InstanceClass::from_stream((), &mut decoder).await {
-                Ok(class) => {
-                    cluster::instantiate(&txn, host.clone(), class, data_dir.clone()).await?
-                }
-                Err(cause) => panic!("error parsing cluster config {:?}: {}", path, cause),
-            };
+            let class = decode_cluster_config(&path, bytes)
+                .await
+                .unwrap_or_else(|cause| panic!("error parsing cluster config {:?}: {}", path, cause));
 
+            let cluster =
+                instantiate_with_backoff(&txn, host.clone(), class, data_dir.clone()).await?;
             clusters.push(cluster);
         }
 
@@ -187,8 +372,60 @@ async fn main() -> Result<(), TokioError> {
     }
 
     let kernel = tinychain::Kernel::new(clusters);
-    let gateway = tinychain::gateway::Gateway::new(gateway_config, kernel, txn_server);
+    let gateway = tinychain::gateway::Gateway::new(gateway_config, kernel, txn_server.clone());
 
     log::info!("starting server, cache size is {}", config.cache_size);
-    gateway.listen().await
+
+    if config.repl {
+        let repl_handle = tokio::task::spawn_blocking(|| {
+            repl::run(|line| {
+                // TODO: dispatch `line` (once parsed into a value/op
+                // expression) through the host's request pipeline and
+                // format the resulting `State`. Left as a stub because the
+                // `Kernel` request-dispatch code this would call into isn't
+                // part of this tree.
+                Ok(format!("(not executed: {})", line))
+            })
+        });
+
+        tokio::select! {
+            result = gateway.listen() => result,
+            result = repl_handle => result.map_err(|cause| TCError::internal(format!("REPL task panicked: {}", cause)))?,
+            _ = shutdown_signal() => {
+                log::info!("received shutdown signal, flushing open transactions...");
+                txn_server.shutdown().await;
+                log::info!("all transactions flushed, shutting down");
+                Ok(())
+            }
+        }
+    } else {
+        tokio::select! {
+            result = gateway.listen() => result,
+            _ = shutdown_signal() => {
+                log::info!("received shutdown signal, flushing open transactions...");
+                txn_server.shutdown().await;
+                log::info!("all transactions flushed, shutting down");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Resolve once either `SIGINT` or (on Unix) `SIGTERM` is received.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("install SIGTERM handler");
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = sigterm.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
 }
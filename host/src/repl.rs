@@ -0,0 +1,186 @@
+//! An interactive line-oriented shell for exploring a running host, entered
+//! with `--repl`. Each accepted line is handed to the caller's `execute`
+//! callback and whatever it returns is printed. The callback the host passes
+//! in (see `main.rs`) echoes the raw line back; it neither parses the line
+//! nor executes it against the host's request pipeline.
+
+use std::borrow::Cow;
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, Helper};
+
+use tc_error::*;
+
+/// Method names already routed under `Number` (see
+/// `route/scalar/value/number.rs`), offered as completions for a partial
+/// path under the cursor.
+const NUMBER_METHODS: &[&str] = &[
+    "abs", "add", "and", "div", "ln", "log", "mod", "mul", "round", "sub", "pow", "gcd", "lcm",
+    "gt", "gte", "lt", "lte", "not", "or", "xor", "atan2", "hypot", "asin", "sin",
+    "asinh", "sinh", "acos", "cos", "acosh", "cosh", "atan", "tan", "atanh", "tanh",
+];
+
+/// Whether `c` opens or closes one of the bracket/paren/brace pairs a
+/// TinyChain value or op expression can nest.
+fn bracket_delta(c: char) -> i32 {
+    match c {
+        '{' | '(' | '[' => 1,
+        '}' | ')' | ']' => -1,
+        _ => 0,
+    }
+}
+
+pub struct TinyChainHelper;
+
+impl Validator for TinyChainHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let mut depth = 0i32;
+        for c in ctx.input().chars() {
+            depth += bracket_delta(c);
+            if depth < 0 {
+                return Ok(ValidationResult::Invalid(Some(
+                    "unbalanced closing bracket".to_string(),
+                )));
+            }
+        }
+
+        if depth > 0 {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Highlighter for TinyChainHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        let mut out = String::with_capacity(line.len());
+        let mut chars = line.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            if c == '/' {
+                let start = i;
+                let mut end = i + c.len_utf8();
+                while let Some(&(j, next)) = chars.peek() {
+                    if next.is_alphanumeric() || next == '/' || next == '_' || next == '-' {
+                        end = j + next.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                out.push_str("\x1b[36m");
+                out.push_str(&line[start..end]);
+                out.push_str("\x1b[0m");
+            } else if c.is_ascii_digit() {
+                let start = i;
+                let mut end = i + c.len_utf8();
+                while let Some(&(j, next)) = chars.peek() {
+                    if next.is_ascii_digit() || next == '.' {
+                        end = j + next.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                out.push_str("\x1b[33m");
+                out.push_str(&line[start..end]);
+                out.push_str("\x1b[0m");
+            } else if "+-*/%<>=".contains(c) {
+                out.push_str("\x1b[35m");
+                out.push(c);
+                out.push_str("\x1b[0m");
+            } else {
+                out.push(c);
+            }
+        }
+
+        Cow::Owned(out)
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        true
+    }
+}
+
+impl Hinter for TinyChainHelper {
+    type Hint = String;
+
+    fn hint(&self, _line: &str, _pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        None
+    }
+}
+
+impl Completer for TinyChainHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let prefix_start = line[..pos].rfind('/').map(|i| i + 1).unwrap_or(pos);
+        let partial = &line[prefix_start..pos];
+
+        let candidates = NUMBER_METHODS
+            .iter()
+            .filter(|method| method.starts_with(partial))
+            .map(|method| Pair {
+                display: method.to_string(),
+                replacement: method.to_string(),
+            })
+            .collect();
+
+        Ok((prefix_start, candidates))
+    }
+}
+
+impl Helper for TinyChainHelper {}
+
+/// Run the REPL to completion (until EOF or an explicit `exit`), printing
+/// the string `execute` returns for each accepted line (or its error).
+/// `execute` is called with the raw, unparsed line text.
+pub fn run<F>(mut execute: F) -> TCResult<()>
+where
+    F: FnMut(&str) -> TCResult<String>,
+{
+    let mut editor: Editor<TinyChainHelper> =
+        Editor::new().map_err(|cause| TCError::internal(format!("failed to start REPL: {}", cause)))?;
+
+    editor.set_helper(Some(TinyChainHelper));
+
+    loop {
+        match editor.readline("tc> ") {
+            Ok(line) => {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+
+                if line == "exit" || line == "quit" {
+                    break;
+                }
+
+                editor.add_history_entry(line);
+
+                match execute(line) {
+                    Ok(result) => println!("{}", result),
+                    Err(cause) => eprintln!("error: {}", cause),
+                }
+            }
+            Err(rustyline::error::ReadlineError::Interrupted)
+            | Err(rustyline::error::ReadlineError::Eof) => break,
+            Err(cause) => {
+                return Err(TCError::internal(format!("REPL read error: {}", cause)));
+            }
+        }
+    }
+
+    Ok(())
+}
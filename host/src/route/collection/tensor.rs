@@ -7,8 +7,9 @@ use safecast::{Match, TryCastFrom, TryCastInto};
 
 use tc_error::*;
 use tc_tensor::{
-    AxisBounds, Bounds, Coord, DenseAccess, DenseTensor, TensorAccess, TensorDualIO, TensorIO, TensorMath,
-    TensorTransform, TensorType,
+    AxisBounds, Bounds, Coord, DenseAccess, DenseTensor, SparseAccess, SparseTensor, TensorAccess,
+    TensorBoolean, TensorCompare, TensorDualIO, TensorIO, TensorMath, TensorTransform, TensorType,
+    TensorUnary,
 };
 use tc_transact::fs::Dir;
 use tc_transact::Transaction;
@@ -23,15 +24,20 @@ use crate::txn::Txn;
 
 use super::{Handler, Route};
 
-struct ConstantHandler;
+struct ConstantHandler {
+    class: TensorType,
+}
 
 impl<'a> Handler<'a> for ConstantHandler {
     fn get(self: Box<Self>) -> Option<GetHandler<'a>> {
-        Some(Box::new(|txn, key| {
+        Some(Box::new(move |txn, key| {
             Box::pin(async move {
                 if key.matches::<(Vec<u64>, Number)>() {
                     let (shape, value): (Vec<u64>, Number) = key.opt_cast_into().unwrap();
-                    constant(&txn, shape, value).await
+                    match self.class {
+                        TensorType::Dense => constant(&txn, shape, value).await,
+                        TensorType::Sparse => sparse_constant(&txn, shape, value).await,
+                    }
                 } else {
                     Err(TCError::bad_request("invalid tensor schema", key))
                 }
@@ -54,6 +60,7 @@ impl<'a> Handler<'a> for CreateHandler {
 
                     match self.class {
                         TensorType::Dense => constant(&txn, shape.into(), dtype.zero()).await,
+                        TensorType::Sparse => sparse_constant(&txn, shape.into(), dtype.zero()).await,
                     }
                 } else {
                     Err(TCError::bad_request(
@@ -66,23 +73,36 @@ impl<'a> Handler<'a> for CreateHandler {
     }
 }
 
-struct RangeHandler;
+struct RangeHandler {
+    class: TensorType,
+}
 
 impl<'a> Handler<'a> for RangeHandler {
     fn get(self: Box<Self>) -> Option<GetHandler<'a>> {
-        Some(Box::new(|txn, key| {
+        Some(Box::new(move |txn, key| {
             Box::pin(async move {
                 if key.matches::<(Vec<u64>, Number, Number)>() {
                     let (shape, start, stop): (Vec<u64>, Number, Number) =
                         key.opt_cast_into().unwrap();
 
-                    let file = create_file(&txn).await?;
-
-                    DenseTensor::range(file, *txn.id(), shape, start, stop)
-                        .map_ok(Tensor::from)
-                        .map_ok(Collection::from)
-                        .map_ok(State::from)
-                        .await
+                    match self.class {
+                        TensorType::Dense => {
+                            let file = create_file(&txn).await?;
+                            DenseTensor::range(file, *txn.id(), shape, start, stop)
+                                .map_ok(Tensor::from)
+                                .map_ok(Collection::from)
+                                .map_ok(State::from)
+                                .await
+                        }
+                        TensorType::Sparse => {
+                            let dir = create_sparse_dir(&txn).await?;
+                            SparseTensor::range(dir, *txn.id(), shape, start, stop)
+                                .map_ok(Tensor::from)
+                                .map_ok(Collection::from)
+                                .map_ok(State::from)
+                                .await
+                        }
+                    }
                 } else {
                     Err(TCError::bad_request("invalid schema for range tensor", key))
                 }
@@ -95,10 +115,10 @@ impl Route for TensorType {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
         if path.is_empty() {
             Some(Box::new(CreateHandler { class: *self }))
-        } else if path.len() == 1 && self == &Self::Dense {
+        } else if path.len() == 1 && (self == &Self::Dense || self == &Self::Sparse) {
             match path[0].as_str() {
-                "constant" => Some(Box::new(ConstantHandler)),
-                "range" => Some(Box::new(RangeHandler)),
+                "constant" => Some(Box::new(ConstantHandler { class: *self })),
+                "range" => Some(Box::new(RangeHandler { class: *self })),
                 _ => None,
             }
         } else {
@@ -107,6 +127,372 @@ impl Route for TensorType {
     }
 }
 
+// One of the axis-wise (or whole-tensor) fold operations exposed at
+// "sum"/"product"/"max"/"min"/"mean". Each is defined by an identity
+// element to seed the accumulator with and a two-argument fold step;
+// `mean` reuses `sum`'s identity/fold and just divides by the count of
+// values folded once the walk is done.
+#[derive(Clone, Copy)]
+enum Reduce {
+    Sum,
+    Product,
+    Max,
+    Min,
+    Mean,
+}
+
+impl Reduce {
+    fn identity(&self, dtype: NumberType) -> Number {
+        match self {
+            Self::Sum | Self::Mean => dtype.zero(),
+            Self::Product => dtype.one(),
+            Self::Max => dtype.min(),
+            Self::Min => dtype.max(),
+        }
+    }
+
+    fn fold(&self, acc: Number, value: Number) -> Number {
+        match self {
+            Self::Sum | Self::Mean => acc + value,
+            Self::Product => acc * value,
+            Self::Max => {
+                if value > acc {
+                    value
+                } else {
+                    acc
+                }
+            }
+            Self::Min => {
+                if value < acc {
+                    value
+                } else {
+                    acc
+                }
+            }
+        }
+    }
+}
+
+// The Cartesian product of every axis of `shape` except `axis` (pass
+// `shape.len()` for `axis` to get every coordinate of the whole tensor),
+// in the same axis order as `shape` itself. `reduce_axis` walks `axis`
+// within each of these to fold one output coordinate; `reduce_all` folds
+// all of them together into a single scalar.
+fn orthogonal_coords(shape: &[u64], axis: usize) -> Vec<Vec<u64>> {
+    let mut coords = vec![Vec::new()];
+
+    for (i, &dim) in shape.iter().enumerate() {
+        if i == axis {
+            continue;
+        }
+
+        let mut next = Vec::with_capacity(coords.len() * dim as usize);
+        for coord in &coords {
+            for x in 0..dim {
+                let mut coord = coord.clone();
+                coord.push(x);
+                next.push(coord);
+            }
+        }
+
+        coords = next;
+    }
+
+    coords
+}
+
+fn insert_axis(orthogonal: &[u64], axis: usize, x: u64) -> Coord {
+    let mut coord = Vec::with_capacity(orthogonal.len() + 1);
+    coord.extend_from_slice(&orthogonal[..axis]);
+    coord.push(x);
+    coord.extend_from_slice(&orthogonal[axis..]);
+    coord
+}
+
+async fn reduce_axis<T>(tensor: &T, txn: &Txn, axis: usize, op: Reduce) -> TCResult<Tensor>
+where
+    T: TensorIO<fs::Dir, Txn = Txn> + TensorAccess,
+{
+    let shape = tensor.shape();
+    if axis >= shape.len() {
+        return Err(TCError::bad_request(
+            "tensor has no such axis to reduce over",
+            axis,
+        ));
+    }
+
+    let axis_len = shape[axis];
+    let mut out_shape = shape.to_vec();
+    out_shape.remove(axis);
+
+    let file = create_file(txn).await?;
+    let result =
+        DenseTensor::constant(file, *txn.id(), out_shape, op.identity(tensor.dtype())).await?;
+
+    for orthogonal in orthogonal_coords(shape, axis) {
+        let mut acc = op.identity(tensor.dtype());
+        for x in 0..axis_len {
+            let value = tensor.read_value(txn, insert_axis(&orthogonal, axis, x)).await?;
+            acc = op.fold(acc, value);
+        }
+
+        if let Reduce::Mean = op {
+            acc = acc / Number::from(axis_len as f64);
+        }
+
+        result.write_value_at(*txn.id(), orthogonal, acc).await?;
+    }
+
+    Ok(Tensor::from(result))
+}
+
+async fn reduce_all<T>(tensor: &T, txn: &Txn, op: Reduce) -> TCResult<Value>
+where
+    T: TensorIO<fs::Dir, Txn = Txn> + TensorAccess,
+{
+    let shape = tensor.shape();
+    let mut acc = op.identity(tensor.dtype());
+    let mut count = 0u64;
+
+    for coord in orthogonal_coords(shape, shape.len()) {
+        let value = tensor.read_value(txn, coord).await?;
+        acc = op.fold(acc, value);
+        count += 1;
+    }
+
+    if let Reduce::Mean = op {
+        acc = acc / Number::from(count as f64);
+    }
+
+    Ok(Value::from(acc))
+}
+
+struct ReduceHandler<'a, T> {
+    tensor: &'a T,
+    op: Reduce,
+}
+
+impl<'a, T> Handler<'a> for ReduceHandler<'a, T>
+where
+    T: TensorIO<fs::Dir, Txn = Txn> + TensorAccess + Send + Sync,
+{
+    fn post(self: Box<Self>) -> Option<PostHandler<'a>> {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let axis: Option<u64> = params.or_default(&label("axis").into())?;
+                params.expect_empty()?;
+
+                match axis {
+                    Some(axis) => {
+                        reduce_axis(self.tensor, &txn, axis as usize, self.op)
+                            .await
+                            .map(Collection::from)
+                            .map(State::from)
+                    }
+                    None => reduce_all(self.tensor, &txn, self.op)
+                        .await
+                        .map(State::from),
+                }
+            })
+        }))
+    }
+}
+
+// The Cartesian product of the given axis lengths, e.g. `cartesian(&[2,
+// 3])` is every `[i, j]` with `i < 2, j < 3`--used below to walk either a
+// tensordot's free output coordinates or its contracted coordinates.
+fn cartesian(dims: &[u64]) -> Vec<Vec<u64>> {
+    let mut coords = vec![Vec::new()];
+
+    for &dim in dims {
+        let mut next = Vec::with_capacity(coords.len() * dim as usize);
+        for coord in &coords {
+            for x in 0..dim {
+                let mut coord = coord.clone();
+                coord.push(x);
+                next.push(coord);
+            }
+        }
+
+        coords = next;
+    }
+
+    coords
+}
+
+// The axes of a tensor of the given `rank` that aren't contracted--in
+// ascending order, which is also the order they keep in the output shape.
+fn free_axes(rank: usize, contracted: &[usize]) -> Vec<usize> {
+    (0..rank).filter(|axis| !contracted.contains(axis)).collect()
+}
+
+// Reassemble a full-rank coordinate from a contracted-axes assignment
+// (`axes`/`axis_values`, one value per contracted axis) and a free-axes
+// assignment (`free_positions`/`free_values`, one value per remaining
+// axis, in `free_axes` order)--the two together cover every axis exactly
+// once.
+fn assemble_coord(
+    rank: usize,
+    axes: &[usize],
+    axis_values: &[u64],
+    free_positions: &[usize],
+    free_values: &[u64],
+) -> Coord {
+    let mut coord = vec![0u64; rank];
+
+    for (&position, &value) in axes.iter().zip(axis_values) {
+        coord[position] = value;
+    }
+
+    for (&position, &value) in free_positions.iter().zip(free_values) {
+        coord[position] = value;
+    }
+
+    coord
+}
+
+// The contracted generalization behind both `matmul` and `tensordot`:
+// contract `l`'s axes named in `axes_l` against `r`'s axes named in
+// `axes_r` (pairwise, so `axes_l[i]` is contracted with `axes_r[i]`),
+// summing the product of matching coordinate elements over every
+// contracted-axis combination, and arranging `l`'s remaining (free) axes
+// followed by `r`'s remaining axes into the output shape.
+async fn tensordot<L, R>(
+    l: &L,
+    r: &R,
+    txn: &Txn,
+    axes_l: Vec<usize>,
+    axes_r: Vec<usize>,
+) -> TCResult<Tensor>
+where
+    L: TensorIO<fs::Dir, Txn = Txn> + TensorAccess,
+    R: TensorIO<fs::Dir, Txn = Txn> + TensorAccess,
+{
+    if axes_l.len() != axes_r.len() {
+        return Err(TCError::bad_request(
+            "tensordot requires the same number of contracted axes on each side, found",
+            format!("{} and {}", axes_l.len(), axes_r.len()),
+        ));
+    }
+
+    let l_shape = l.shape();
+    let r_shape = r.shape();
+
+    let mut contracted_dims = Vec::with_capacity(axes_l.len());
+    for (&axis_l, &axis_r) in axes_l.iter().zip(&axes_r) {
+        let (dim_l, dim_r) = (l_shape[axis_l], r_shape[axis_r]);
+        if dim_l != dim_r {
+            return Err(TCError::bad_request(
+                "cannot contract axes of different lengths",
+                format!("{} (axis {}) != {} (axis {})", dim_l, axis_l, dim_r, axis_r),
+            ));
+        }
+
+        contracted_dims.push(dim_l);
+    }
+
+    let free_l = free_axes(l_shape.len(), &axes_l);
+    let free_r = free_axes(r_shape.len(), &axes_r);
+    let free_l_dims: Vec<u64> = free_l.iter().map(|&axis| l_shape[axis]).collect();
+    let free_r_dims: Vec<u64> = free_r.iter().map(|&axis| r_shape[axis]).collect();
+
+    let mut out_shape = free_l_dims.clone();
+    out_shape.extend(free_r_dims.iter().copied());
+
+    let file = create_file(txn).await?;
+    let result = DenseTensor::constant(file, *txn.id(), out_shape, l.dtype().zero()).await?;
+
+    for free_l_values in cartesian(&free_l_dims) {
+        for free_r_values in cartesian(&free_r_dims) {
+            let mut acc = l.dtype().zero();
+
+            for contracted_values in cartesian(&contracted_dims) {
+                let l_coord =
+                    assemble_coord(l_shape.len(), &axes_l, &contracted_values, &free_l, &free_l_values);
+                let r_coord =
+                    assemble_coord(r_shape.len(), &axes_r, &contracted_values, &free_r, &free_r_values);
+
+                let l_value = l.read_value(txn, l_coord).await?;
+                let r_value = r.read_value(txn, r_coord).await?;
+                acc = acc + (l_value * r_value);
+            }
+
+            let mut out_coord = free_l_values.clone();
+            out_coord.extend(free_r_values);
+            result.write_value_at(*txn.id(), out_coord, acc).await?;
+        }
+    }
+
+    Ok(Tensor::from(result))
+}
+
+struct MatMulHandler<'a, T> {
+    tensor: &'a T,
+}
+
+impl<'a, T> Handler<'a> for MatMulHandler<'a, T>
+where
+    T: TensorIO<fs::Dir, Txn = Txn> + TensorAccess + Send + Sync,
+{
+    fn post(self: Box<Self>) -> Option<PostHandler<'a>> {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let r: Tensor = params.require(&label("r").into())?;
+                params.expect_empty()?;
+
+                let l_shape = self.tensor.shape();
+                let r_shape = r.shape();
+                if l_shape.len() != 2 || r_shape.len() != 2 {
+                    return Err(TCError::bad_request(
+                        "matmul requires two 2-D tensors, found shapes",
+                        format!("{:?} and {:?}", l_shape, r_shape),
+                    ));
+                }
+
+                if l_shape[1] != r_shape[0] {
+                    return Err(TCError::bad_request(
+                        "cannot multiply matrices of shapes",
+                        format!("{:?} and {:?}", l_shape, r_shape),
+                    ));
+                }
+
+                tensordot(self.tensor, &r, &txn, vec![1], vec![0])
+                    .await
+                    .map(Collection::from)
+                    .map(State::from)
+            })
+        }))
+    }
+}
+
+struct TensorDotHandler<'a, T> {
+    tensor: &'a T,
+}
+
+impl<'a, T> Handler<'a> for TensorDotHandler<'a, T>
+where
+    T: TensorIO<fs::Dir, Txn = Txn> + TensorAccess + Send + Sync,
+{
+    fn post(self: Box<Self>) -> Option<PostHandler<'a>> {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let r: Tensor = params.require(&label("r").into())?;
+                let axes: (Vec<u64>, Vec<u64>) = params.require(&label("axes").into())?;
+                params.expect_empty()?;
+
+                let (axes_l, axes_r) = axes;
+                let axes_l: Vec<usize> = axes_l.into_iter().map(|x| x as usize).collect();
+                let axes_r: Vec<usize> = axes_r.into_iter().map(|x| x as usize).collect();
+
+                tensordot(self.tensor, &r, &txn, axes_l, axes_r)
+                    .await
+                    .map(Collection::from)
+                    .map(State::from)
+            })
+        }))
+    }
+}
+
 struct MathHandler<'a, T> {
     tensor: &'a T,
     op: fn(&'a T, &Tensor) -> TCResult<Tensor>,
@@ -140,6 +526,61 @@ where
     }
 }
 
+// Shares `MathHandler`'s broadcast-then-combine shape, but the combiner
+// produces a `Bool`-dtype `Tensor` (a mask of 1s and 0s) instead of an
+// arithmetic result--used for both the comparison ops (`eq`/`ne`/`gt`/
+// `gte`/`lt`/`lte`) and the boolean ops (`and`/`or`/`xor`).
+struct CompareHandler<'a, T> {
+    tensor: &'a T,
+    op: fn(&'a T, &Tensor) -> TCResult<Tensor>,
+}
+
+impl<'a, T> Handler<'a> for CompareHandler<'a, T>
+where
+    T: TensorAccess + Send + Sync + 'a,
+{
+    fn post(self: Box<Self>) -> Option<PostHandler<'a>> {
+        Some(Box::new(|_txn, mut params| {
+            Box::pin(async move {
+                let r = params.require::<Tensor>(&label("r").into())?;
+                let r = if r.shape() == self.tensor.shape() {
+                    r
+                } else {
+                    r.broadcast(self.tensor.shape().clone())?
+                };
+
+                (self.op)(self.tensor, &r)
+                    .map(Collection::from)
+                    .map(State::from)
+            })
+        }))
+    }
+}
+
+// `not` takes no right-hand operand, so it's a no-argument `PostHandler`
+// rather than reusing `CompareHandler`'s broadcast machinery.
+struct NotHandler<'a, T> {
+    tensor: &'a T,
+}
+
+impl<'a, T> Handler<'a> for NotHandler<'a, T>
+where
+    T: TensorUnary<fs::Dir, Unary = Tensor> + Send + Sync + 'a,
+{
+    fn post(self: Box<Self>) -> Option<PostHandler<'a>> {
+        Some(Box::new(|_txn, params| {
+            Box::pin(async move {
+                params.expect_empty()?;
+
+                self.tensor
+                    .not()
+                    .map(Collection::from)
+                    .map(State::from)
+            })
+        }))
+    }
+}
+
 struct TensorHandler<'a, T> {
     tensor: &'a T,
 }
@@ -206,6 +647,89 @@ impl<'a, T> From<&'a T> for TensorHandler<'a, T> {
     }
 }
 
+// The on-the-wire shape of a tensor's full dense contents: a small header
+// (shape + dtype) followed by the flattened row-major elements, so a
+// whole tensor round-trips as one CBOR document instead of one request per
+// coordinate.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CborTensor {
+    shape: Vec<u64>,
+    dtype: NumberType,
+    data: Vec<Number>,
+}
+
+struct CborHandler<'a, T> {
+    tensor: &'a T,
+}
+
+impl<'a, T> Handler<'a> for CborHandler<'a, T>
+where
+    T: TensorIO<fs::Dir, Txn = Txn> + TensorAccess + Send + Sync,
+{
+    fn get(self: Box<Self>) -> Option<GetHandler<'a>> {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                if !key.is_none() {
+                    return Err(TCError::bad_request(
+                        "the cbor endpoint takes no key, found",
+                        key,
+                    ));
+                }
+
+                let shape = self.tensor.shape().to_vec();
+                let dtype = self.tensor.dtype();
+
+                let mut data = Vec::with_capacity(shape.iter().product::<u64>() as usize);
+                for coord in cartesian(&shape) {
+                    data.push(self.tensor.read_value(&txn, coord).await?);
+                }
+
+                let doc = CborTensor { shape, dtype, data };
+                let bytes = serde_cbor::to_vec(&doc).map_err(|e| {
+                    TCError::internal(format!("failed to encode tensor as CBOR: {}", e))
+                })?;
+
+                Ok(State::from(Value::from(bytes)))
+            })
+        }))
+    }
+
+    fn put(self: Box<Self>) -> Option<PutHandler<'a>> {
+        Some(Box::new(|txn, key, value| {
+            Box::pin(async move {
+                if !key.is_none() {
+                    return Err(TCError::bad_request(
+                        "the cbor endpoint takes no key, found",
+                        key,
+                    ));
+                }
+
+                let value =
+                    Value::try_cast_from(value, |v| TCError::bad_request("expected CBOR bytes, found", v))?;
+                let bytes: Vec<u8> =
+                    value.try_cast_into(|v| TCError::bad_request("expected CBOR bytes, found", v))?;
+
+                let doc: CborTensor = serde_cbor::from_slice(&bytes).map_err(|e| {
+                    TCError::bad_request("invalid CBOR tensor document", e)
+                })?;
+
+                if doc.shape != self.tensor.shape().to_vec() {
+                    return Err(TCError::bad_request(
+                        "CBOR tensor shape does not match the destination tensor's shape",
+                        format!("{:?}", doc.shape),
+                    ));
+                }
+
+                for (coord, value) in cartesian(&doc.shape).into_iter().zip(doc.data) {
+                    self.tensor.write_value_at(*txn.id(), coord, value).await?;
+                }
+
+                Ok(())
+            })
+        }))
+    }
+}
+
 impl<B: DenseAccess<fs::File<Array>, fs::Dir, Txn>> Route
     for DenseTensor<fs::File<Array>, fs::Dir, Txn, B>
 {
@@ -214,6 +738,14 @@ impl<B: DenseAccess<fs::File<Array>, fs::Dir, Txn>> Route
     }
 }
 
+impl<A: SparseAccess<fs::File<Array>, fs::Dir, Txn>> Route
+    for SparseTensor<fs::File<Array>, fs::Dir, Txn, A>
+{
+    fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
+        route(self, path)
+    }
+}
+
 impl Route for Tensor {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
         route(self, path)
@@ -225,6 +757,9 @@ where
     T: TensorIO<fs::Dir, Txn = Txn>
         + TensorDualIO<fs::Dir, Tensor>
         + TensorMath<fs::Dir, Tensor, Combine = Tensor>
+        + TensorCompare<fs::Dir, Tensor, Compare = Tensor>
+        + TensorBoolean<fs::Dir, Tensor, Combine = Tensor>
+        + TensorUnary<fs::Dir, Unary = Tensor>
         + TensorTransform<fs::Dir>
         + Clone
         + Send
@@ -240,6 +775,66 @@ where
             "div" => Some(Box::new(MathHandler::new(tensor, TensorMath::div))),
             "mul" => Some(Box::new(MathHandler::new(tensor, TensorMath::mul))),
             "sub" => Some(Box::new(MathHandler::new(tensor, TensorMath::sub))),
+            "sum" => Some(Box::new(ReduceHandler {
+                tensor,
+                op: Reduce::Sum,
+            })),
+            "product" => Some(Box::new(ReduceHandler {
+                tensor,
+                op: Reduce::Product,
+            })),
+            "max" => Some(Box::new(ReduceHandler {
+                tensor,
+                op: Reduce::Max,
+            })),
+            "min" => Some(Box::new(ReduceHandler {
+                tensor,
+                op: Reduce::Min,
+            })),
+            "mean" => Some(Box::new(ReduceHandler {
+                tensor,
+                op: Reduce::Mean,
+            })),
+            "matmul" => Some(Box::new(MatMulHandler { tensor })),
+            "tensordot" => Some(Box::new(TensorDotHandler { tensor })),
+            "eq" => Some(Box::new(CompareHandler {
+                tensor,
+                op: TensorCompare::eq,
+            })),
+            "ne" => Some(Box::new(CompareHandler {
+                tensor,
+                op: TensorCompare::ne,
+            })),
+            "gt" => Some(Box::new(CompareHandler {
+                tensor,
+                op: TensorCompare::gt,
+            })),
+            "gte" => Some(Box::new(CompareHandler {
+                tensor,
+                op: TensorCompare::gte,
+            })),
+            "lt" => Some(Box::new(CompareHandler {
+                tensor,
+                op: TensorCompare::lt,
+            })),
+            "lte" => Some(Box::new(CompareHandler {
+                tensor,
+                op: TensorCompare::lte,
+            })),
+            "and" => Some(Box::new(CompareHandler {
+                tensor,
+                op: TensorBoolean::and,
+            })),
+            "or" => Some(Box::new(CompareHandler {
+                tensor,
+                op: TensorBoolean::or,
+            })),
+            "xor" => Some(Box::new(CompareHandler {
+                tensor,
+                op: TensorBoolean::xor,
+            })),
+            "not" => Some(Box::new(NotHandler { tensor })),
+            "cbor" => Some(Box::new(CborHandler { tensor })),
             _ => None,
         }
     } else {
@@ -257,6 +852,23 @@ async fn constant(txn: &Txn, shape: Vec<u64>, value: Number) -> TCResult<State>
         .await
 }
 
+// Unlike `constant`'s dense block, a sparse tensor only ever materializes
+// the coordinates it's explicitly given a value for--`read_value` returns
+// the implicit zero everywhere else. A non-zero `constant` still has to
+// write every coordinate out (there's no way to represent "every cell is
+// 5" sparsely), but `dtype.zero()` (the only caller that matters for
+// `CreateHandler`) writes nothing at all, which is exactly the case this
+// tensor type exists for.
+async fn sparse_constant(txn: &Txn, shape: Vec<u64>, value: Number) -> TCResult<State> {
+    let dir = create_sparse_dir(txn).await?;
+
+    SparseTensor::constant(dir, *txn.id(), shape, value)
+        .map_ok(Tensor::from)
+        .map_ok(Collection::from)
+        .map_ok(State::from)
+        .await
+}
+
 async fn write<T: TensorIO<fs::Dir, Txn = Txn> + TensorDualIO<fs::Dir, Tensor>>(
     tensor: &T,
     txn: Txn,
@@ -301,6 +913,13 @@ async fn create_file(txn: &Txn) -> TCResult<fs::File<afarray::Array>> {
         .await
 }
 
+// A sparse tensor's backing is a directory (holding the table of explicit
+// coordinate/value entries), not a single dense block file--so this goes
+// through `create_dir_tmp` rather than `create_file_tmp`.
+async fn create_sparse_dir(txn: &Txn) -> TCResult<fs::Dir> {
+    txn.context().create_dir_tmp(*txn.id()).await
+}
+
 fn cast_bound(dim: u64, bound: Value) -> TCResult<u64> {
     let bound = i64::try_cast_from(bound, |v| TCError::bad_request("invalid bound", v))?;
     if bound.abs() as u64 > dim {
@@ -317,6 +936,59 @@ fn cast_bound(dim: u64, bound: Value) -> TCResult<u64> {
     }
 }
 
+// A `[start:stop:step]` axis spec, given as the three elements of a
+// `Scalar::Tuple`. `AxisBounds` has no native stride variant, so a step
+// (including a negative one, for reversal) is always expanded into the
+// explicit coordinate list `AxisBounds::Of` rather than `AxisBounds::In`.
+fn strided_axis(dim: u64, axis: usize, parts: Vec<Scalar>) -> TCResult<AxisBounds> {
+    let mut parts = parts.into_iter();
+    let start = Value::try_cast_from(parts.next().unwrap(), |s| {
+        TCError::bad_request(format!("invalid start index for axis {}", axis), s)
+    })?;
+    let stop = Value::try_cast_from(parts.next().unwrap(), |s| {
+        TCError::bad_request(format!("invalid stop index for axis {}", axis), s)
+    })?;
+    let step = Value::try_cast_from(parts.next().unwrap(), |s| {
+        TCError::bad_request(format!("invalid step for axis {}", axis), s)
+    })?;
+
+    let start = cast_bound(dim, start)?;
+    let stop = cast_bound(dim, stop)?;
+    let step: i64 = step.try_cast_into(|v| {
+        TCError::bad_request(format!("invalid step for axis {}", axis), v)
+    })?;
+
+    if step == 0 {
+        return Err(TCError::bad_request(
+            format!("step cannot be zero for axis {}", axis),
+            step,
+        ));
+    } else if (step > 0 && start > stop) || (step < 0 && start < stop) {
+        return Err(TCError::bad_request(
+            format!("step direction does not match range for axis {}", axis),
+            format!("{}..{} step {}", start, stop, step),
+        ));
+    }
+
+    let mut indices = Vec::new();
+    if step > 0 {
+        let mut i = start;
+        while i < stop {
+            indices.push(i);
+            i += step as u64;
+        }
+    } else {
+        let mut i = start as i64;
+        let stop = stop as i64;
+        while i > stop {
+            indices.push(i as u64);
+            i += step;
+        }
+    }
+
+    Ok(AxisBounds::Of(indices))
+}
+
 pub fn cast_bounds(shape: &[u64], scalar: Scalar) -> TCResult<Bounds> {
     debug!("tensor bounds from {}", scalar);
 
@@ -325,18 +997,31 @@ pub fn cast_bounds(shape: &[u64], scalar: Scalar) -> TCResult<Bounds> {
             let mut axes = Vec::with_capacity(shape.len());
 
             for (axis, bound) in bounds.into_inner().into_iter().enumerate() {
-                let bound = if bound.matches::<Range>() {
+                let bound = if let Scalar::Tuple(parts) = &bound {
+                    let parts = parts.clone().into_inner();
+                    if parts.len() == 3 {
+                        Some(strided_axis(shape[axis], axis, parts)?)
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                };
+
+                let bound = if let Some(bound) = bound {
+                    bound
+                } else if bound.matches::<Range>() {
                     let range = Range::opt_cast_from(bound).unwrap();
                     let start = match range.start {
                         Bound::Un => 0,
                         Bound::In(start) => cast_bound(shape[axis], start)?,
-                        Bound::Ex(start) => cast_bound(shape[1], start)? + 1,
+                        Bound::Ex(start) => cast_bound(shape[axis], start)? + 1,
                     };
 
                     let end = match range.end {
                         Bound::Un => shape[axis],
                         Bound::In(end) => cast_bound(shape[axis], end)?,
-                        Bound::Ex(end) => cast_bound(shape[1], end)?,
+                        Bound::Ex(end) => cast_bound(shape[axis], end)?,
                     };
 
                     AxisBounds::In(start..end)
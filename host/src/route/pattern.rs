@@ -0,0 +1,106 @@
+use tc_error::*;
+use tc_value::Value;
+use tcgeneric::{Map, PathSegment, TCPathBuf};
+
+/// A single matcher within a compiled [`PathPattern`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PatternSegment {
+    /// Matches a single path segment with the given literal value.
+    Static(PathSegment),
+    /// Matches a single path segment and captures it under the given name.
+    Dynamic(PathSegment),
+    /// Matches all remaining path segments and captures them as a `TCPathBuf`.
+    /// May only appear as the final segment of a pattern.
+    Tail(PathSegment),
+}
+
+/// A compiled route pattern, e.g. `"render/{name}"` or `"items/{id}/tail*"`.
+///
+/// Call [`PathPattern::match_path`] to test an incoming path and, on success,
+/// collect the captured [`Value`]s keyed by capture name.
+#[derive(Clone, Debug)]
+pub struct PathPattern {
+    segments: Vec<PatternSegment>,
+}
+
+impl PathPattern {
+    /// Compile a pattern string into a [`PathPattern`].
+    ///
+    /// `{name}` denotes a dynamic segment captured under `name`; a trailing
+    /// `name*` denotes a tail capture that consumes all remaining segments.
+    /// Any other segment is matched literally.
+    pub fn compile(pattern: &str) -> TCResult<Self> {
+        let parts: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+
+        let mut segments = Vec::with_capacity(parts.len());
+        for (i, part) in parts.iter().enumerate() {
+            let is_last = i == parts.len() - 1;
+
+            if let Some(name) = part.strip_suffix('*') {
+                if !is_last {
+                    return Err(TCError::bad_request(
+                        "a tail capture may only appear at the end of a pattern",
+                        pattern,
+                    ));
+                }
+
+                segments.push(PatternSegment::Tail(name.parse()?));
+            } else if let Some(name) = part.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                segments.push(PatternSegment::Dynamic(name.parse()?));
+            } else {
+                segments.push(PatternSegment::Static(part.parse()?));
+            }
+        }
+
+        if let Some(tail) = segments
+            .iter()
+            .position(|s| matches!(s, PatternSegment::Tail(_)))
+        {
+            if tail != segments.len() - 1 {
+                return Err(TCError::bad_request(
+                    "a tail capture may only appear at the end of a pattern",
+                    pattern,
+                ));
+            }
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// `true` if this pattern ends in a tail capture, meaning it can match
+    /// paths longer than its own segment count.
+    pub fn has_tail(&self) -> bool {
+        matches!(self.segments.last(), Some(PatternSegment::Tail(_)))
+    }
+
+    /// Match `path` against this pattern, left-to-right and on full segment
+    /// boundaries. On success, return the captured values.
+    pub fn match_path(&self, path: &[PathSegment]) -> Option<Map<Value>> {
+        let mut captures = Map::new();
+
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                PatternSegment::Static(expected) => {
+                    if path.get(i) != Some(expected) {
+                        return None;
+                    }
+                }
+                PatternSegment::Dynamic(name) => {
+                    let actual = path.get(i)?;
+                    captures.insert(name.clone(), Value::from(actual.clone()));
+                }
+                PatternSegment::Tail(name) => {
+                    let tail: TCPathBuf = path[i..].iter().cloned().collect();
+                    captures.insert(name.clone(), Value::from(tail));
+                    return Some(captures);
+                }
+            }
+        }
+
+        if path.len() != self.segments.len() {
+            return None;
+        }
+
+        Some(captures)
+    }
+}
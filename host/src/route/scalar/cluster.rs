@@ -1,17 +1,91 @@
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+
+use tc_error::*;
+use tc_value::{Link, Value};
 use tcgeneric::{PathSegment, TCPathBuf};
 
 use crate::route::{DeleteHandler, GetHandler, Handler, PostHandler, PutHandler, Route};
 use crate::scalar::ClusterRef;
+use crate::state::State;
+use crate::txn::Txn;
 
 struct ClusterHandler {
     path: TCPathBuf,
+    replicas: Vec<Link>,
+    quorum: usize,
 }
 
 impl ClusterHandler {
     fn new(cluster: &ClusterRef, path: &[PathSegment]) -> Self {
         let mut cluster_path = cluster.path().clone();
         cluster_path.extend_from_slice(path);
-        Self { path: cluster_path }
+
+        let replicas = cluster.replicas().to_vec();
+        let quorum = if replicas.is_empty() {
+            0
+        } else {
+            replicas.len() / 2 + 1
+        };
+
+        Self {
+            path: cluster_path,
+            replicas,
+            quorum,
+        }
+    }
+
+    fn replica_links(&self) -> Vec<Link> {
+        self.replicas
+            .iter()
+            .map(|replica| replica.clone().append(self.path.clone()))
+            .collect()
+    }
+}
+
+/// Fan `request` out to every replica in `links` concurrently, and resolve
+/// once all of them have completed. Returns the per-replica results in
+/// completion order; whether a quorum succeeded is decided by the caller
+/// (see [`require_quorum`]).
+async fn fan_out<F, Fut, T>(links: Vec<Link>, request: F) -> Vec<TCResult<T>>
+where
+    F: Fn(Link) -> Fut,
+    Fut: std::future::Future<Output = TCResult<T>>,
+{
+    let mut futures: FuturesUnordered<_> = links.into_iter().map(request).collect();
+
+    let mut results = Vec::with_capacity(futures.len());
+    while let Some(result) = futures.next().await {
+        results.push(result);
+    }
+
+    results
+}
+
+/// Require that at least `quorum` of `results` succeeded, distinguishing a
+/// partial failure ("no quorum") from a total failure ("all failed").
+fn require_quorum<T>(results: Vec<TCResult<T>>, quorum: usize) -> TCResult<Vec<T>> {
+    let total = results.len();
+    let (ok, err): (Vec<_>, Vec<_>) = results.into_iter().partition(|r| r.is_ok());
+
+    if ok.len() >= quorum {
+        Ok(ok.into_iter().map(|r| r.unwrap()).collect())
+    } else if ok.is_empty() {
+        Err(TCError::internal(format!(
+            "all {} replicas failed: {}",
+            total,
+            err.into_iter()
+                .map(|r| r.unwrap_err().to_string())
+                .collect::<Vec<String>>()
+                .join("; ")
+        )))
+    } else {
+        Err(TCError::internal(format!(
+            "failed to reach quorum of {} replicas ({} of {} succeeded)",
+            quorum,
+            ok.len(),
+            total
+        )))
     }
 }
 
@@ -21,7 +95,32 @@ impl<'a> Handler<'a> for ClusterHandler {
         'b: 'a,
     {
         Some(Box::new(|txn, key| {
-            Box::pin(txn.get(self.path.into(), key))
+            Box::pin(async move {
+                if self.replicas.is_empty() {
+                    return txn.get(self.path.into(), key).await;
+                }
+
+                let links = self.replica_links();
+                let mut attempts: FuturesUnordered<_> = links
+                    .into_iter()
+                    .map(|link| {
+                        let txn = &txn;
+                        let key = key.clone();
+                        async move { txn.get(link, key).await }
+                    })
+                    .collect();
+
+                let mut last_err = None;
+                while let Some(result) = attempts.next().await {
+                    match result {
+                        Ok(state) => return Ok(state),
+                        Err(cause) => last_err = Some(cause),
+                    }
+                }
+
+                Err(last_err
+                    .unwrap_or_else(|| TCError::internal("no replicas available to read from")))
+            })
         }))
     }
 
@@ -30,7 +129,24 @@ impl<'a> Handler<'a> for ClusterHandler {
         'b: 'a,
     {
         Some(Box::new(|txn, key, value| {
-            Box::pin(txn.put(self.path.into(), key, value))
+            Box::pin(async move {
+                if self.replicas.is_empty() {
+                    return txn.put(self.path.into(), key, value).await;
+                }
+
+                let links = self.replica_links();
+                let quorum = self.quorum;
+                let results = fan_out(links, |link| {
+                    let txn = &txn;
+                    let key = key.clone();
+                    let value = value.clone();
+                    async move { txn.put(link, key, value).await }
+                })
+                .await;
+
+                require_quorum(results, quorum)?;
+                Ok(())
+            })
         }))
     }
 
@@ -39,7 +155,25 @@ impl<'a> Handler<'a> for ClusterHandler {
         'b: 'a,
     {
         Some(Box::new(|txn, params| {
-            Box::pin(txn.post(self.path.into(), params.into()))
+            Box::pin(async move {
+                let params = State::from(params);
+
+                if self.replicas.is_empty() {
+                    return txn.post(self.path.into(), params).await;
+                }
+
+                let links = self.replica_links();
+                let quorum = self.quorum;
+                let results = fan_out(links, |link| {
+                    let txn = &txn;
+                    let params = params.clone();
+                    async move { txn.post(link, params).await }
+                })
+                .await;
+
+                let mut acks = require_quorum(results, quorum)?;
+                Ok(acks.pop().unwrap_or_else(|| State::from(Value::None)))
+            })
         }))
     }
 
@@ -48,7 +182,23 @@ impl<'a> Handler<'a> for ClusterHandler {
         'b: 'a,
     {
         Some(Box::new(|txn, key| {
-            Box::pin(txn.delete(self.path.into(), key))
+            Box::pin(async move {
+                if self.replicas.is_empty() {
+                    return txn.delete(self.path.into(), key).await;
+                }
+
+                let links = self.replica_links();
+                let quorum = self.quorum;
+                let results = fan_out(links, |link| {
+                    let txn = &txn;
+                    let key = key.clone();
+                    async move { txn.delete(link, key).await }
+                })
+                .await;
+
+                require_quorum(results, quorum)?;
+                Ok(())
+            })
         }))
     }
 }
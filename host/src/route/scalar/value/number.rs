@@ -1,3 +1,4 @@
+use num::Integer;
 use safecast::{CastFrom, TryCastInto};
 
 use tc_error::*;
@@ -7,6 +8,19 @@ use tcgeneric::{label, PathSegment};
 use crate::route::{GetHandler, Handler, PostHandler, Route};
 use crate::state::State;
 
+// `gcd`/`lcm` are only defined over integers--reject `Float`/`Complex`
+// (and `Bool`, which isn't meaningful here either) rather than silently
+// truncating.
+fn require_int(n: Number, op: &'static str) -> TCResult<i64> {
+    match n {
+        Number::Int(_) | Number::UInt(_) => Ok(i64::cast_from(n)),
+        other => Err(TCError::bad_request(
+            format!("{} requires an integer operand, found", op),
+            other,
+        )),
+    }
+}
+
 struct Dual<F> {
     op: F,
 }
@@ -178,6 +192,64 @@ impl Route for Number {
             "round" => Box::new(Unary::new("round", move || self.round())),
             "sub" => Box::new(Dual::new(move |other| Ok(*self - other))),
             "pow" => Box::new(Dual::new(move |other| Ok(self.pow(other)))),
+            "atan2" => Box::new(Dual::new(move |other: Number| {
+                let y = *self;
+                let x = other;
+
+                if y.class().is_complex() || x.class().is_complex() {
+                    return Err(TCError::bad_request(
+                        "atan2 does not support a complex operand",
+                        x,
+                    ));
+                }
+
+                let zero = x.class().zero();
+                if x == zero && y == zero {
+                    return Err(TCError::unsupported("atan2 is undefined at (0, 0)"));
+                }
+
+                if x == zero {
+                    let half_pi = Number::from(std::f64::consts::FRAC_PI_2);
+                    let neg_half_pi = Number::from(-std::f64::consts::FRAC_PI_2);
+                    return Ok(if y > zero { half_pi } else { neg_half_pi });
+                }
+
+                let angle = (y / x).atan();
+                if x < zero {
+                    let pi = Number::from(std::f64::consts::PI);
+                    let neg_pi = Number::from(-std::f64::consts::PI);
+                    Ok(if y >= zero { angle + pi } else { angle + neg_pi })
+                } else {
+                    Ok(angle)
+                }
+            })),
+            "gcd" => Box::new(Dual::new(move |other: Number| {
+                let a = require_int(*self, "gcd")?;
+                let b = require_int(other, "gcd")?;
+                Ok(Number::from(a.gcd(&b)))
+            })),
+            "lcm" => Box::new(Dual::new(move |other: Number| {
+                let a = require_int(*self, "lcm")?;
+                let b = require_int(other, "lcm")?;
+                if a == 0 || b == 0 {
+                    Ok(Number::from(0))
+                } else {
+                    Ok(Number::from(a.lcm(&b)))
+                }
+            })),
+            "hypot" => Box::new(Dual::new(move |other: Number| {
+                let a = self.abs();
+                let b = other.abs();
+                let (hi, lo) = if a > b { (a, b) } else { (b, a) };
+
+                if hi == hi.class().zero() {
+                    return Ok(hi);
+                }
+
+                let ratio = lo / hi;
+                let one = Number::from(1);
+                Ok(hi * (one + ratio * ratio).pow(Number::from(0.5)))
+            })),
 
             // comparison
             "gt" => Box::new(Dual::new(move |other| Ok((*self > other).into()))),
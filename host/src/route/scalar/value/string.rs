@@ -1,14 +1,76 @@
+use std::pin::Pin;
+
+use futures::Future;
 use safecast::{Match, TryCastFrom, TryCastInto};
 
 use tc_error::*;
 use tc_value::{TCString, Value};
-use tcgeneric::{Map, PathSegment};
+use tcgeneric::{Id, Map, PathSegment, Tuple};
 
 use crate::route::{GetHandler, Handler, PostHandler, Route};
 use crate::state::State;
+use crate::txn::Txn;
+
+/// A template may only include another template up to this many levels deep,
+/// to guard against include cycles.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Selects how interpolated [`Value`]s are encoded before being spliced into
+/// a rendered template, based on the content type the template targets.
+#[derive(Copy, Clone)]
+enum Escaper {
+    /// HTML entity-escape interpolated values. The default when no mode is given.
+    Html,
+    /// JSON string-escape interpolated values.
+    Json,
+    /// Percent-encode interpolated values for use in a URL.
+    Url,
+    /// Splice values in verbatim, for trusted template fragments.
+    Raw,
+}
+
+impl Escaper {
+    fn from_path_segment(segment: &str) -> TCResult<Self> {
+        match segment {
+            "html" => Ok(Self::Html),
+            "json" => Ok(Self::Json),
+            "url" => Ok(Self::Url),
+            "raw" => Ok(Self::Raw),
+            other => Err(TCError::bad_request("unknown template escape mode", other)),
+        }
+    }
+
+    fn escape(&self, unescaped: &str) -> String {
+        match self {
+            Self::Html => unescaped
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+                .replace('\'', "&#39;"),
+            Self::Json => unescaped
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r")
+                .replace('\t', "\\t"),
+            Self::Url => unescaped
+                .bytes()
+                .map(|b| match b {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                        (b as char).to_string()
+                    }
+                    _ => format!("%{:02X}", b),
+                })
+                .collect(),
+            Self::Raw => unescaped.to_string(),
+        }
+    }
+}
 
 struct RenderHandler<'a> {
     template: &'a TCString,
+    escaper: Escaper,
 }
 
 impl<'a> Handler<'a> for RenderHandler<'a> {
@@ -16,16 +78,20 @@ impl<'a> Handler<'a> for RenderHandler<'a> {
     where
         'b: 'a,
     {
-        Some(Box::new(|_txn, value| {
+        Some(Box::new(|txn, value| {
             Box::pin(async move {
-                let result = if value.matches::<Map<Value>>() {
-                    let data: Map<Value> = value.opt_cast_into().unwrap();
-                    self.template.render(data)
+                let scope = if value.matches::<Map<Value>>() {
+                    value.opt_cast_into().unwrap()
                 } else {
-                    self.template.render(value)
+                    let mut scope = Map::new();
+                    scope.insert(Id::from("self"), value);
+                    scope
                 };
 
-                result.map(Value::String).map(State::from)
+                render(self.template.as_str(), &scope, txn, self.escaper, MAX_INCLUDE_DEPTH)
+                    .await
+                    .map(Value::String)
+                    .map(State::from)
             })
         }))
     }
@@ -34,9 +100,9 @@ impl<'a> Handler<'a> for RenderHandler<'a> {
     where
         'b: 'a,
     {
-        Some(Box::new(|_txn, params| {
+        Some(Box::new(|txn, params| {
             Box::pin(async move {
-                let params = params
+                let scope = params
                     .into_iter()
                     .map(|(id, state)| {
                         Value::try_cast_from(state, |s| {
@@ -46,8 +112,8 @@ impl<'a> Handler<'a> for RenderHandler<'a> {
                     })
                     .collect::<TCResult<Map<Value>>>()?;
 
-                self.template
-                    .render(params)
+                render(self.template.as_str(), &scope, txn, self.escaper, MAX_INCLUDE_DEPTH)
+                    .await
                     .map(Value::String)
                     .map(State::from)
             })
@@ -55,15 +121,174 @@ impl<'a> Handler<'a> for RenderHandler<'a> {
     }
 }
 
+/// Resolve a (possibly dotted) path like `a.b.c` against `scope`, descending
+/// into nested `Map<Value>`s for each additional segment.
+fn resolve(scope: &Map<Value>, path: &str) -> TCResult<Value> {
+    let mut segments = path.split('.');
+
+    let first: Id = segments
+        .next()
+        .ok_or_else(|| TCError::bad_request("empty template variable", path))?
+        .parse()?;
+
+    let mut value = scope
+        .get(&first)
+        .cloned()
+        .ok_or_else(|| TCError::not_found(format!("template variable {}", first)))?;
+
+    for segment in segments {
+        let id: Id = segment.parse()?;
+        let nested: Map<Value> = value
+            .opt_cast_into()
+            .ok_or_else(|| TCError::bad_request("not a nested value", path))?;
+
+        value = nested
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| TCError::not_found(format!("template variable {}", path)))?;
+    }
+
+    Ok(value)
+}
+
+/// `true` if `value` should be treated as "truthy" by `{{#if ...}}`.
+fn truthy(value: &Value) -> bool {
+    if value.is_none() {
+        return false;
+    }
+
+    if let Some(tuple) = value.opt_cast_into_ref::<&Tuple<Value>>() {
+        return !tuple.is_empty();
+    }
+
+    true
+}
+
+/// Find the matching `{{/tag}}` for a `{{#tag ...}}` opened at `body[start..]`,
+/// accounting for nested blocks of the same tag. Returns the inner body and
+/// the index just past the closing tag.
+fn find_block_end<'a>(body: &'a str, tag: &str, start: usize) -> TCResult<(&'a str, usize)> {
+    let open = format!("{{{{#{} ", tag);
+    let open_bare = format!("{{{{#{}", tag);
+    let close = format!("{{{{/{}}}}}", tag);
+
+    let mut depth = 1;
+    let mut cursor = start;
+
+    while depth > 0 {
+        let next_open = body[cursor..]
+            .find(open.as_str())
+            .or_else(|| body[cursor..].find(open_bare.as_str()));
+        let next_close = body[cursor..].find(close.as_str());
+
+        match (next_open, next_close) {
+            (_, None) => {
+                return Err(TCError::bad_request("unterminated template block", tag));
+            }
+            (Some(o), Some(c)) if o < c => {
+                depth += 1;
+                cursor += o + open_bare.len();
+            }
+            (_, Some(c)) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok((&body[start..cursor + c], cursor + c + close.len()));
+                }
+                cursor += c + close.len();
+            }
+        }
+    }
+
+    Err(TCError::bad_request("unterminated template block", tag))
+}
+
+/// Render `template` against `scope`, resolving `{{#if}}`, `{{#each}}`,
+/// `{{> include}}` and plain `{{variable}}` directives, encoding each
+/// interpolated value with `escaper`. `depth` bounds the number of nested
+/// includes still allowed, to prevent include cycles.
+fn render<'a>(
+    template: &'a str,
+    scope: &'a Map<Value>,
+    txn: &'a Txn,
+    escaper: Escaper,
+    depth: usize,
+) -> Pin<Box<dyn Future<Output = TCResult<String>> + Send + 'a>> {
+    Box::pin(async move {
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find("{{") {
+            output.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| TCError::bad_request("unterminated template tag", rest))?
+                + start
+                + 2;
+
+            let tag = &rest[start + 2..end - 2];
+
+            if let Some(field) = tag.strip_prefix("#if ") {
+                let (body, consumed) = find_block_end(rest, "if", end)?;
+                if truthy(&resolve(scope, field.trim())?) {
+                    output.push_str(&render(body, scope, txn, escaper, depth).await?);
+                }
+                rest = &rest[consumed..];
+            } else if let Some(rest_tag) = tag.strip_prefix("#each ") {
+                let (body, consumed) = find_block_end(rest, "each", end)?;
+                let collection: Tuple<Value> = resolve(scope, rest_tag.trim())?
+                    .try_cast_into(|v| TCError::bad_request("not iterable", v))?;
+
+                for (index, item) in collection.into_iter().enumerate() {
+                    let mut item_scope = scope.clone();
+                    item_scope.insert(Id::from("this"), item);
+                    item_scope.insert(Id::from("index"), Value::from(index as u64));
+                    output.push_str(&render(body, &item_scope, txn, escaper, depth).await?);
+                }
+
+                rest = &rest[consumed..];
+            } else if let Some(path) = tag.strip_prefix("> ") {
+                if depth == 0 {
+                    return Err(TCError::bad_request(
+                        "template include cycle detected at",
+                        path,
+                    ));
+                }
+
+                let include_path = path.trim().parse()?;
+                let include: TCString = txn
+                    .get(include_path, Value::None)
+                    .await?
+                    .try_cast_into(|s| TCError::bad_request("not a template", s))?;
+
+                output.push_str(&render(include.as_str(), scope, txn, escaper, depth - 1).await?);
+                rest = &rest[end..];
+            } else {
+                let value = resolve(scope, tag.trim())?.to_string();
+                output.push_str(&escaper.escape(&value));
+                rest = &rest[end..];
+            }
+        }
+
+        output.push_str(rest);
+        Ok(output)
+    })
+}
+
 impl Route for TCString {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
-        if path.len() != 1 {
+        if path.is_empty() || path.len() > 2 || path[0].as_str() != "render" {
             return None;
         }
 
-        match path[0].as_str() {
-            "render" => Some(Box::new(RenderHandler { template: self })),
-            _ => None,
-        }
+        let escaper = if let Some(mode) = path.get(1) {
+            Escaper::from_path_segment(mode.as_str()).ok()?
+        } else {
+            Escaper::Html
+        };
+
+        Some(Box::new(RenderHandler {
+            template: self,
+            escaper,
+        }))
     }
 }
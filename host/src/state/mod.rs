@@ -4,15 +4,19 @@ use std::collections::{BTreeMap, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
 use std::iter::FromIterator;
+use std::pin::Pin;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use destream::de;
 use futures::future::try_join_all;
-use futures::TryFutureExt;
+use futures::{TryFutureExt, TryStreamExt};
 use log::debug;
 use safecast::{TryCastFrom};
+use tokio::sync::{mpsc, Mutex as AsyncMutex};
 
 use tc_error::*;
 use tc_transact::Transaction;
@@ -34,9 +38,11 @@ pub use view::StateView;
 pub enum StateType {
     Collection(CollectionType),
     Chain(ChainType),
+    Closure,
     Map,
     Object(ObjectType),
     Scalar(ScalarType),
+    Stream,
     Tuple,
 }
 
@@ -51,7 +57,9 @@ impl NativeClass for StateType {
         } else if &path[0] == "state" {
             if path.len() == 2 {
                 match path[1].as_str() {
+                    "closure" => Some(Self::Closure),
                     "map" => Some(Self::Map),
+                    "stream" => Some(Self::Stream),
                     "tuple" => Some(Self::Tuple),
                     _ => None,
                 }
@@ -74,9 +82,11 @@ impl NativeClass for StateType {
         match self {
             Self::Collection(ct) => ct.path(),
             Self::Chain(ct) => ct.path(),
+            Self::Closure => path_label(&["state", "closure"]).into(),
             Self::Map => path_label(&["state", "map"]).into(),
             Self::Object(ot) => ot.path(),
             Self::Scalar(st) => st.path(),
+            Self::Stream => path_label(&["state", "stream"]).into(),
             Self::Tuple => path_label(&["state", "tuple"]).into(),
         }
     }
@@ -124,22 +134,68 @@ impl fmt::Display for StateType {
         match self {
             Self::Collection(ct) => fmt::Display::fmt(ct, f),
             Self::Chain(ct) => fmt::Display::fmt(ct, f),
+            Self::Closure => f.write_str("Closure"),
             Self::Map => f.write_str("Map<Id, State>"),
             Self::Object(ot) => fmt::Display::fmt(ot, f),
             Self::Scalar(st) => fmt::Display::fmt(st, f),
+            Self::Stream => f.write_str("Stream<State>"),
             Self::Tuple => f.write_str("Tuple<State>"),
         }
     }
 }
 
+/// An [`OpDef`] closed over a snapshot of part of the [`Scope`] it was
+/// defined in, as produced by the `With` flow-control operator.
+#[derive(Clone)]
+pub struct Closure {
+    capture: Tuple<Id>,
+    captured: Map<Id, State>,
+    op: OpDef,
+}
+
+impl Closure {
+    /// Construct a new, not-yet-resolved `Closure` which will capture the
+    /// value of each `Id` in `capture` from the enclosing scope once resolved.
+    pub fn new(capture: Tuple<Id>, op: OpDef) -> Self {
+        Self {
+            capture,
+            captured: Map::default(),
+            op,
+        }
+    }
+
+    /// The `Id`s captured from the enclosing scope.
+    pub fn capture(&self) -> &Tuple<Id> {
+        &self.capture
+    }
+
+    /// The values captured from the enclosing scope, frozen at resolution time.
+    pub fn captured(&self) -> &Map<Id, State> {
+        &self.captured
+    }
+
+    /// The body of this closure.
+    pub fn op(&self) -> &OpDef {
+        &self.op
+    }
+}
+
+impl fmt::Display for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "closure over {} of {}", self.capture, self.op)
+    }
+}
+
 /// An addressable state with a discrete value per-transaction.
 #[derive(Clone)]
 pub enum State {
     Collection(Collection),
     Chain(Chain),
+    Closure(Closure),
     Map(Map<Self>),
     Object(Object),
     Scalar(Scalar),
+    Stream(StateStream),
     Tuple(Tuple<Self>),
 }
 
@@ -170,19 +226,47 @@ impl State {
     }
 
     /// Cast this `State` into the given [`StateType`], if possible.
+    ///
+    /// In addition to casting between [`ScalarType`]s, this supports casting
+    /// between the composite types [`State::Map`] and [`State::Tuple`], and
+    /// flattening any [`Collection`] with a `Tuple<State>` representation
+    /// (e.g. a `BTree` or `Table`) into a `State::Tuple` of its rows.
     pub fn into_type(self, class: StateType) -> Option<Self> {
         if self.class() == class {
             return Some(self);
         }
 
-        match class {
-            StateType::Scalar(class) => {
-                debug!("cast into {} from {}", class, self);
-                Scalar::opt_cast_from(self)
+        match (class, self) {
+            (StateType::Scalar(class), state) => {
+                debug!("cast into {} from {}", class, state);
+                Scalar::opt_cast_from(state)
                     .and_then(|scalar| scalar.into_type(class))
                     .map(Self::Scalar)
             }
-            _ => None,
+            (StateType::Tuple, Self::Map(map)) => Some(Self::Tuple(map.into_values().collect())),
+            (StateType::Tuple, state @ Self::Collection(_)) => {
+                Tuple::<State>::opt_cast_from(state).map(Self::Tuple)
+            }
+            (StateType::Map, Self::Tuple(tuple)) => {
+                let map = tuple
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, state)| (Id::from(i), state))
+                    .collect::<BTreeMap<Id, State>>();
+
+                Some(Self::Map(map.into()))
+            }
+            (StateType::Map, state @ Self::Collection(_)) => {
+                let tuple = Tuple::<State>::opt_cast_from(state)?;
+                let map = tuple
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, state)| (Id::from(i), state))
+                    .collect::<BTreeMap<Id, State>>();
+
+                Some(Self::Map(map.into()))
+            }
+            (_, _) => None,
         }
     }
 }
@@ -191,6 +275,7 @@ impl State {
 impl Refer for State {
     fn requires(&self, deps: &mut HashSet<Id>) {
         match self {
+            Self::Closure(closure) => deps.extend(closure.capture().iter().cloned()),
             Self::Map(map) => {
                 for state in map.values() {
                     state.requires(deps);
@@ -214,16 +299,27 @@ impl Refer for State {
         debug!("State::resolve {}", self);
 
         match self {
-            Self::Map(map) => {
-                let resolved = try_join_all(
-                    map.into_iter()
-                        .map(|(id, state)| state.resolve(context, txn).map_ok(|s| (id, s))),
-                )
-                .await?;
+            Self::Closure(closure) => {
+                let mut captured = BTreeMap::new();
+                for id in closure.capture().iter() {
+                    let state = context
+                        .resolve_id(id)
+                        .cloned()
+                        .ok_or_else(|| TCError::not_found(id))?;
+
+                    captured.insert(id.clone(), state);
+                }
 
-                let map = BTreeMap::from_iter(resolved);
-                Ok(State::Map(map.into()))
+                Ok(State::Closure(Closure {
+                    capture: closure.capture().clone(),
+                    captured: captured.into(),
+                    op: closure.op().clone(),
+                }))
             }
+            Self::Map(map) => resolve_map(map, context, txn).await.map(State::Map),
+            // NOTE: a `While` reference that re-runs an `OpDef` body over a reused child
+            // `Scope` belongs here as a new `tc_scalar::reference` variant; that crate is not
+            // part of this source tree, so `scalar.resolve` is the full extent of dispatch.
             Self::Scalar(scalar) => scalar.resolve(context, txn).await,
             Self::Tuple(tuple) => {
                 let resolved =
@@ -237,6 +333,51 @@ impl Refer for State {
     }
 }
 
+/// Resolve every entry of `map` in dependency order: an entry that refers to
+/// a sibling key of the same map (e.g. `b` in `{"b": $a}`) is resolved only
+/// after that sibling is. Every entry is resolved against `context`; a
+/// sibling's resolved value is not added to it, so `$a` in `b` is looked up
+/// in `context`, not in `map`. Entries with no unresolved dependencies left
+/// in `map` are resolved concurrently in each round.
+async fn resolve_map<'a, T: Instance + Public>(
+    map: Map<State>,
+    context: &'a Scope<'a, T>,
+    txn: &'a Txn,
+) -> TCResult<Map<State>> {
+    let mut pending: BTreeMap<Id, State> = map.into_iter().collect();
+    let mut resolved: BTreeMap<Id, State> = BTreeMap::new();
+
+    while !pending.is_empty() {
+        let ready: Vec<Id> = pending
+            .iter()
+            .filter(|(_, state)| {
+                let mut deps = HashSet::new();
+                state.requires(&mut deps);
+                deps.iter()
+                    .all(|dep| !pending.contains_key(dep) || resolved.contains_key(dep))
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        if ready.is_empty() {
+            return Err(TCError::bad_request(
+                "cyclic dependency among map entries",
+                Tuple::<Id>::from_iter(pending.into_keys()),
+            ));
+        }
+
+        let newly_resolved = try_join_all(ready.into_iter().map(|id| {
+            let state = pending.remove(&id).expect("pending map entry");
+            state.resolve(context, txn).map_ok(move |state| (id, state))
+        }))
+        .await?;
+
+        resolved.extend(newly_resolved);
+    }
+
+    Ok(resolved.into())
+}
+
 impl Default for State {
     fn default() -> Self {
         Self::Scalar(Scalar::default())
@@ -250,9 +391,11 @@ impl Instance for State {
         match self {
             Self::Collection(collection) => StateType::Collection(collection.class()),
             Self::Chain(chain) => StateType::Chain(chain.class()),
+            Self::Closure(_) => StateType::Closure,
             Self::Map(_) => StateType::Map,
             Self::Object(object) => StateType::Object(object.class()),
             Self::Scalar(scalar) => StateType::Scalar(scalar.class()),
+            Self::Stream(_) => StateType::Stream,
             Self::Tuple(_) => StateType::Tuple,
         }
     }
@@ -585,6 +728,25 @@ impl TryCastFrom<State> for OpDef {
     }
 }
 
+impl TryCastFrom<State> for Closure {
+    fn can_cast_from(state: &State) -> bool {
+        matches!(state, State::Closure(_))
+    }
+
+    fn opt_cast_from(state: State) -> Option<Self> {
+        match state {
+            State::Closure(closure) => Some(closure),
+            _ => None,
+        }
+    }
+}
+
+impl From<Closure> for State {
+    fn from(closure: Closure) -> Self {
+        Self::Closure(closure)
+    }
+}
+
 impl TryCastFrom<State> for OpRef {
     fn can_cast_from(state: &State) -> bool {
         match state {
@@ -704,20 +866,71 @@ impl fmt::Display for State {
         match self {
             Self::Collection(collection) => fmt::Display::fmt(collection, f),
             Self::Chain(chain) => fmt::Display::fmt(chain, f),
+            Self::Closure(closure) => fmt::Display::fmt(closure, f),
             Self::Map(map) => fmt::Display::fmt(map, f),
             Self::Object(object) => fmt::Display::fmt(object, f),
             Self::Scalar(scalar) => fmt::Display::fmt(scalar, f),
+            Self::Stream(_) => f.write_str("Stream<State>"),
             Self::Tuple(tuple) => fmt::Display::fmt(tuple, f),
         }
     }
 }
 
+/// A subject that a decoder can publish each element of a `State::Tuple` or `State::Map` to
+/// as soon as it finishes decoding, so subscribers can observe a large upload incrementally
+/// instead of waiting for the whole `State` to arrive.
+pub type StateSubject = mpsc::UnboundedSender<State>;
+
+/// Adapts the receiving half of a bounded channel into a [`TCBoxTryStream`], so a
+/// producer can feed it one decoded `State` (or decode error) at a time without pulling in
+/// a separate stream-wrapper dependency for it.
+struct ChannelStream(mpsc::Receiver<TCResult<State>>);
+
+impl futures::Stream for ChannelStream {
+    type Item = TCResult<State>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+/// A sequence of `State`s decoded lazily, one element at a time, rather than collected into
+/// memory up front--returned by [`StateVisitor::visit_seq`] in place of a fully-materialized
+/// `State::Tuple` so that decoding a very large sequence costs constant memory regardless of
+/// its length. Wrapped in `Arc<Mutex<_>>` rather than held directly so `State` can stay
+/// `Clone`: every clone shares the same underlying stream and its progress, since there's no
+/// way to "fork" a `Stream` mid-iteration without buffering it back into memory--exactly what
+/// this type exists to avoid.
+#[derive(Clone)]
+pub struct StateStream(Arc<AsyncMutex<TCBoxTryStream<'static, State>>>);
+
+impl StateStream {
+    fn new(stream: TCBoxTryStream<'static, State>) -> Self {
+        Self(Arc::new(AsyncMutex::new(stream)))
+    }
+
+    /// Pull the next element, if any. This advances the stream for every clone of this
+    /// handle, since they all share the same underlying decode in progress.
+    pub async fn try_next(&self) -> TCResult<Option<State>> {
+        self.0.lock().await.try_next().await
+    }
+}
+
 struct StateVisitor {
     txn: Txn,
     scalar: ScalarVisitor,
+    subject: Option<StateSubject>,
 }
 
 impl StateVisitor {
+    /// Publish `state` to this visitor's [`StateSubject`], if any. A subscriber that has
+    /// stopped listening (a dropped receiver) is not treated as an error.
+    fn publish(&self, state: &State) {
+        if let Some(subject) = &self.subject {
+            let _ = subject.send(state.clone());
+        }
+    }
+
     async fn visit_map_value<A: de::MapAccess>(
         &self,
         class: StateType,
@@ -736,6 +949,10 @@ impl StateVisitor {
                     .map_ok(State::Chain)
                     .await
             }
+            StateType::Closure => {
+                let (capture, op): (Tuple<Id>, OpDef) = access.next_value(()).await?;
+                Ok(State::Closure(Closure::new(capture, op)))
+            }
             StateType::Map => access.next_value(self.txn.clone()).await,
             StateType::Object(ot) => match ot {
                 ObjectType::Class => {
@@ -758,6 +975,45 @@ impl StateVisitor {
             StateType::Tuple => access.next_value(self.txn.clone()).await,
         }
     }
+
+    /// Decode a single `id: state` entry of a `State::Map`, recording any failure against
+    /// `key` in `errors` instead of aborting the whole decode, so that every entry gets a
+    /// chance to report its own problem in one pass.
+    async fn visit_map_entry<A: de::MapAccess>(
+        &self,
+        key: String,
+        access: &mut A,
+        map: &mut BTreeMap<Id, State>,
+        errors: &mut Vec<String>,
+    ) -> Result<(), A::Error> {
+        let id = match Id::from_str(&key) {
+            Ok(id) => id,
+            Err(cause) => {
+                access.next_value::<de::IgnoredAny>(()).await?;
+                errors.push(format!("invalid Id {}: {}", key, cause));
+                return Ok(());
+            }
+        };
+
+        let txn = match self.txn.subcontext(id.clone()).await {
+            Ok(txn) => txn,
+            Err(cause) => {
+                access.next_value::<de::IgnoredAny>(()).await?;
+                errors.push(format!("{}: {}", key, cause));
+                return Ok(());
+            }
+        };
+
+        match access.next_value(txn).await {
+            Ok(state) => {
+                self.publish(&state);
+                map.insert(id, state);
+            }
+            Err(cause) => errors.push(format!("{}: {}", key, cause)),
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -846,64 +1102,98 @@ impl<'a> de::Visitor for StateVisitor {
             }
 
             debug!("deserialize Op with subject {}", key);
+            // NOTE: a `Case` reference generalizing `IfRef` to a multi-arm match belongs in
+            // `tc_scalar::reference`, which is not part of this source tree; `ScalarVisitor`
+            // here only forwards to whatever `Subject`/`Op` variants that crate defines.
             if let Ok(subject) = reference::Subject::from_str(&key) {
                 let params = access.next_value(()).await?;
                 return ScalarVisitor::visit_subject(subject, params).map(State::Scalar);
             }
 
             let mut map = BTreeMap::new();
+            let mut errors = Vec::new();
 
-            let id = Id::from_str(&key).map_err(de::Error::custom)?;
-            let txn = self
-                .txn
-                .subcontext(id.clone())
-                .map_err(de::Error::custom)
+            self.visit_map_entry(key, &mut access, &mut map, &mut errors)
                 .await?;
 
-            let value = access.next_value(txn).await?;
-            map.insert(id, value);
-
-            while let Some(id) = access.next_key::<Id>(()).await? {
-                let txn = self
-                    .txn
-                    .subcontext(id.clone())
-                    .map_err(de::Error::custom)
+            while let Some(key) = access.next_key::<String>(()).await? {
+                self.visit_map_entry(key, &mut access, &mut map, &mut errors)
                     .await?;
-
-                let state = access.next_value(txn).await?;
-                map.insert(id, state);
             }
 
-            Ok(State::Map(map.into()))
+            if errors.is_empty() {
+                Ok(State::Map(map.into()))
+            } else {
+                Err(de::Error::custom(format!(
+                    "{} error(s) decoding Map: {}",
+                    errors.len(),
+                    errors.join("; ")
+                )))
+            }
         } else {
             Ok(State::Map(Map::default()))
         }
     }
 
     async fn visit_seq<A: de::SeqAccess>(self, mut access: A) -> Result<Self::Value, A::Error> {
-        let mut seq = if let Some(len) = access.size_hint() {
-            Vec::with_capacity(len)
-        } else {
-            Vec::new()
-        };
+        // Decode lazily instead of collecting every element into a `Vec` before this method
+        // can return: a task owns `access` and forwards one decoded `State` at a time over a
+        // channel with room for a single element, waiting for the caller to take each one
+        // before decoding the next, and the channel's receiving half is handed back
+        // immediately as a `State::Stream`, so a caller pulls elements on demand and a very
+        // large sequence costs constant memory rather than however much it takes to hold the
+        // whole thing.
+        //
+        // This assumes `A: Send + 'static`, which `de::SeqAccess` doesn't spell out in this
+        // tree (`destream` isn't part of this source snapshot to check against)--but an
+        // async_trait `Visitor` like this one already needs its futures to be `Send` to run
+        // on a multi-threaded executor, and every decoder this crate actually uses
+        // (`destream_json`) owns its underlying byte stream outright, so neither bound costs
+        // anything in practice.
+        let (tx, rx) = mpsc::channel::<TCResult<State>>(1);
+        let txn = self.txn.clone();
+        let subject = self.subject.clone();
+
+        tokio::spawn(async move {
+            let mut i = 0usize;
+            loop {
+                let element_txn = match txn.subcontext(i.into()).await {
+                    Ok(element_txn) => element_txn,
+                    Err(cause) => {
+                        let _ = tx.send(Err(cause)).await;
+                        return;
+                    }
+                };
 
-        let mut i = 0usize;
-        loop {
-            let txn = self
-                .txn
-                .subcontext(i.into())
-                .map_err(de::Error::custom)
-                .await?;
+                match access.next_element::<State>(element_txn).await {
+                    Ok(Some(next)) => {
+                        if let Some(subject) = &subject {
+                            let _ = subject.send(next.clone());
+                        }
 
-            if let Some(next) = access.next_element(txn).await? {
-                seq.push(next);
-                i += 1;
-            } else {
-                break;
+                        if tx.send(Ok(next)).await.is_err() {
+                            return;
+                        }
+
+                        i += 1;
+                    }
+                    Ok(None) => return,
+                    Err(cause) => {
+                        let _ = tx.send(Err(TCError::bad_request(
+                            "error decoding Tuple element",
+                            cause,
+                        )))
+                        .await;
+
+                        return;
+                    }
+                }
             }
-        }
+        });
 
-        Ok(State::Tuple(seq.into()))
+        let stream: TCBoxTryStream<'static, State> = Box::pin(ChannelStream(rx));
+
+        Ok(State::Stream(StateStream::new(stream)))
     }
 }
 
@@ -913,6 +1203,31 @@ impl de::FromStream for State {
 
     async fn from_stream<D: de::Decoder>(txn: Txn, decoder: &mut D) -> Result<Self, D::Error> {
         let scalar = ScalarVisitor::default();
-        decoder.decode_any(StateVisitor { txn, scalar }).await
+        decoder
+            .decode_any(StateVisitor {
+                txn,
+                scalar,
+                subject: None,
+            })
+            .await
+    }
+}
+
+impl State {
+    /// Decode a `State` from `decoder`, publishing each element of a top-level
+    /// `State::Tuple` or `State::Map` to `subject` as soon as it is decoded.
+    pub async fn from_stream_with_subject<D: de::Decoder>(
+        txn: Txn,
+        subject: StateSubject,
+        decoder: &mut D,
+    ) -> Result<Self, D::Error> {
+        let scalar = ScalarVisitor::default();
+        decoder
+            .decode_any(StateVisitor {
+                txn,
+                scalar,
+                subject: Some(subject),
+            })
+            .await
     }
 }
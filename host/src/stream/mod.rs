@@ -1,18 +1,21 @@
 //! A stream generator such as a `Collection` or a mapping or aggregation of its items
 
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::convert::TryInto;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use async_trait::async_trait;
-use destream::en;
+use destream::{de, en};
 use futures::future::{self, TryFutureExt};
-use futures::stream::{Stream, StreamExt, TryStreamExt};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use log::debug;
 use safecast::{CastFrom, CastInto, TryCastFrom};
 
 use tc_error::*;
-use tc_transact::IntoView;
+use tc_transact::fs::{BlockData, BlockId, Dir as _, File as _};
+use tc_transact::{IntoView, Transaction};
 use tc_value::{Number, UInt};
 use tcgeneric::{Id, Map, TCBoxTryFuture, TCBoxTryStream};
 
@@ -28,6 +31,104 @@ use source::*;
 mod group;
 mod source;
 
+/// The number of `(key, value)` rows read from the source stream and sorted
+/// in memory before spilling a run to a temporary file. Bounds
+/// `TCStream::sorted`'s peak memory use to roughly this many rows at once,
+/// independent of how many rows the source stream actually produces.
+const SORT_CHUNK_SIZE: usize = 10_000;
+
+/// One `(key, value)` row of an externally-sorted run, persisted as its own
+/// block (per the merge-sort algorithm in `TCStream::execute_sorted`) so the
+/// k-way merge phase only ever has to hold one row per run in memory at a
+/// time, not a whole run. `key`/`value` are `Value`s rather than bare
+/// `State`s because `BlockData` requires a `Context = ()` `de::FromStream`
+/// impl, which `State` (decoded against a `Txn`) doesn't have--the same
+/// constraint `execute_aggregate`, below, already works around by requiring
+/// its items be `Value`-castable.
+#[derive(Clone)]
+struct SortedRow {
+    key: Value,
+    value: Value,
+}
+
+#[async_trait]
+impl de::FromStream for SortedRow {
+    type Context = ();
+
+    async fn from_stream<D: de::Decoder>(cxt: (), decoder: &mut D) -> Result<Self, D::Error> {
+        de::FromStream::from_stream(cxt, decoder)
+            .map_ok(|(key, value)| Self { key, value })
+            .await
+    }
+}
+
+impl<'en> en::ToStream<'en> for SortedRow {
+    fn to_stream<E: en::Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        en::IntoStream::into_stream((&self.key, &self.value), encoder)
+    }
+}
+
+impl<'en> en::IntoStream<'en> for SortedRow {
+    fn into_stream<E: en::Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        en::IntoStream::into_stream((self.key, self.value), encoder)
+    }
+}
+
+impl BlockData for SortedRow {
+    fn ext() -> &'static str {
+        "sorted_row"
+    }
+
+    fn max_size() -> u64 {
+        4096
+    }
+}
+
+/// The file class passed to [`tc_transact::fs::Dir::create_file_tmp`] to
+/// create a run's backing file. `crate::fs::Dir`'s concrete file-class
+/// registry (`FileEntryRegistration`, in `fs::dir`) is keyed by `StateType`,
+/// which has no slot for an internal query-execution block kind like this
+/// one--registering one is follow-up plumbing outside this module, so this
+/// type exists only to name the call below.
+struct SortedRunClass;
+
+/// One run of an external merge sort: a temporary file of [`SortedRow`]
+/// blocks, numbered `0, 1, 2, ...` in ascending sorted order, plus a cursor
+/// onto the next block the k-way merge hasn't read yet.
+struct SortedRun {
+    file: fs::File<SortedRow>,
+    next: usize,
+}
+
+/// One run's current head, as tracked by the merge phase's min-heap: `key`
+/// determines heap order, `run` identifies which run to refill from once
+/// this entry is popped.
+struct HeapEntry {
+    key: Value,
+    run: usize,
+    value: Value,
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.partial_cmp(other).unwrap_or(std::cmp::Ordering::Equal)
+    }
+}
+
 /// A stream generator such as a `Collection` or a mapping or aggregation of its items
 #[derive(Clone)]
 pub enum TCStream {
@@ -36,7 +137,9 @@ pub enum TCStream {
     Filter(Box<TCStream>, Closure),
     Flatten(Box<TCStream>),
     Map(Box<TCStream>, Closure),
+    GroupBy(Box<TCStream>, Closure, Closure),
     Range(Number, Number, Number),
+    Sorted(Box<TCStream>, Closure),
 }
 
 impl TCStream {
@@ -101,6 +204,32 @@ impl TCStream {
         Self::Map(Box::new(self), op)
     }
 
+    /// Group this stream's items by the `Value` that `key` extracts from
+    /// each one, reducing each group to a single `State` with `reduce`--SQL
+    /// `GROUP BY`, in other words.
+    ///
+    /// `reduce` has the same `(Map<State>, item) -> State` shape as the
+    /// `op` passed to [`Self::fold`]: it's called once per item with the
+    /// group's running accumulator (the item bound under `"item"`) and its
+    /// result becomes the new accumulator. Unlike [`Self::aggregate`],
+    /// equal keys don't need to already be adjacent in the stream, so this
+    /// works on data in any order--at the cost of keeping one accumulator
+    /// in memory per distinct key for the life of the stream.
+    pub fn group_by(self, key: Closure, reduce: Closure) -> Self {
+        Self::GroupBy(Box::new(self), key, reduce)
+    }
+
+    /// Return a new stream with the same items as this stream, in ascending
+    /// order of the key that calling `key` on each item produces.
+    ///
+    /// Sorting is external: the source is consumed in bounded chunks which
+    /// are sorted in memory and spilled to temporary files, then merged back
+    /// together one item at a time, so this works even on streams too large
+    /// to fit in memory. See `execute_sorted`, below, for the algorithm.
+    pub fn sorted(self, key: Closure) -> Self {
+        Self::Sorted(Box::new(self), key)
+    }
+
     /// Return a Rust `Stream` of the items in this `TCStream`.
     pub fn into_stream<'a>(self, txn: Txn) -> TCBoxTryFuture<'a, TCBoxTryStream<'static, State>> {
         Box::pin(async move {
@@ -112,6 +241,10 @@ impl TCStream {
                         .await
                 }
                 Self::Collection(collection) => Source::into_stream(collection, txn).await,
+                Self::GroupBy(source, key, reduce) => {
+                    let source = source.into_stream(txn.clone()).await?;
+                    Self::execute_group_by(source, txn, key, reduce).await
+                }
                 Self::Filter(source, op) => {
                     source
                         .into_stream(txn.clone())
@@ -138,6 +271,10 @@ impl TCStream {
                     let range: TCBoxTryStream<_> = Box::pin(range);
                     Ok(range)
                 }
+                Self::Sorted(source, key) => {
+                    let source = source.into_stream(txn.clone()).await?;
+                    Self::execute_sorted(source, txn, key).await
+                }
             }
         })
     }
@@ -157,6 +294,49 @@ impl TCStream {
         aggregate
     }
 
+    /// Drive `group_by`: read `source` to completion, reducing each item
+    /// into the running accumulator for the `Value` that `key` extracts
+    /// from it, then emit the finished `(key, accumulator)` pairs in the
+    /// order each key was first seen.
+    async fn execute_group_by(
+        mut source: TCBoxTryStream<'static, State>,
+        txn: Txn,
+        key: Closure,
+        reduce: Closure,
+    ) -> TCResult<TCBoxTryStream<'static, State>> {
+        let item_name = Id::from("item");
+        let mut groups: Vec<(Value, Map<State>)> = Vec::new();
+
+        while let Some(item) = source.try_next().await? {
+            let key_state = key.clone().call_owned(txn.clone(), item.clone()).await?;
+            let item_key = Value::try_cast_from(key_state, |s| {
+                TCError::bad_request("Stream::group_by key must be a Value, not", s)
+            })?;
+
+            let group = match groups.iter().position(|(k, _)| k == &item_key) {
+                Some(i) => i,
+                None => {
+                    groups.push((item_key, Map::new()));
+                    groups.len() - 1
+                }
+            };
+
+            let acc = &mut groups[group].1;
+            let mut args = acc.clone();
+            args.insert(item_name.clone(), item);
+            let result = reduce.clone().call(&txn, args.into()).await?;
+            *acc = result.try_into()?;
+        }
+
+        let grouped = groups.into_iter().map(|(key, acc)| {
+            let pair = vec![State::from(key), State::Map(acc)];
+            Ok(State::Tuple(pair.into()))
+        });
+
+        let grouped: TCBoxTryStream<'static, State> = Box::pin(stream::iter(grouped));
+        Ok(grouped)
+    }
+
     fn execute_filter(
         source: TCBoxTryStream<'static, State>,
         txn: Txn,
@@ -220,6 +400,129 @@ impl TCStream {
 
         Box::pin(map)
     }
+
+    /// External merge sort: read `source` in chunks of at most
+    /// `SORT_CHUNK_SIZE` rows, sort each chunk in memory by the key that
+    /// `key` extracts, and spill it to a temporary file (one block per
+    /// row) as a sorted run. Once `source` is exhausted, merge the runs
+    /// back together with a binary min-heap, which only ever needs to hold
+    /// one row per run in memory at a time. If `source` holds fewer than
+    /// `SORT_CHUNK_SIZE` rows, its one sorted chunk is returned directly,
+    /// skipping the spill-to-disk round trip entirely.
+    async fn execute_sorted(
+        source: TCBoxTryStream<'static, State>,
+        txn: Txn,
+        key: Closure,
+    ) -> TCResult<TCBoxTryStream<'static, State>> {
+        let mut rows = source
+            .map_ok(move |state| {
+                let key = key.clone();
+                let txn = txn.clone();
+                async move {
+                    let key_state = key.call_owned(txn, state.clone()).await?;
+                    let key = Value::try_cast_from(key_state, |s| {
+                        TCError::bad_request("Stream::sorted key must be a Value, not", s)
+                    })?;
+
+                    let value = Value::try_cast_from(state, |s| {
+                        TCError::bad_request("Stream::sorted requires a Value, not", s)
+                    })?;
+
+                    TCResult::Ok(SortedRow { key, value })
+                }
+            })
+            .try_buffered(num_cpus::get());
+
+        let mut runs: Vec<SortedRun> = Vec::new();
+        let mut chunk = Vec::with_capacity(SORT_CHUNK_SIZE);
+
+        loop {
+            chunk.clear();
+            while chunk.len() < SORT_CHUNK_SIZE {
+                match rows.try_next().await? {
+                    Some(row) => chunk.push(row),
+                    None => break,
+                }
+            }
+
+            if chunk.is_empty() {
+                break;
+            }
+
+            chunk.sort_by(|a, b| a.key.partial_cmp(&b.key).unwrap_or(std::cmp::Ordering::Equal));
+
+            if runs.is_empty() && chunk.len() < SORT_CHUNK_SIZE {
+                // The whole stream fit in one chunk--return it directly
+                // without spilling a run to disk and merging it back.
+                let sorted = chunk.drain(..).map(|row| Ok(State::from(row.value)));
+                let sorted: TCBoxTryStream<'static, State> = Box::pin(stream::iter(sorted));
+                return Ok(sorted);
+            }
+
+            let file: fs::File<SortedRow> = txn
+                .context()
+                .create_file_tmp(*txn.id(), SortedRunClass)
+                .await?;
+
+            for (i, row) in chunk.drain(..).enumerate() {
+                let block_id = BlockId::from(i.to_string());
+                file.create_block(*txn.id(), block_id, row).await?;
+            }
+
+            runs.push(SortedRun { file, next: 0 });
+        }
+
+        Self::merge_sorted_runs(runs, txn).await
+    }
+
+    /// Drive the k-way merge phase of `execute_sorted`: open each run's
+    /// first row, then repeatedly pop the smallest head off of the heap,
+    /// emit it, and refill from the run it came from. A run that has no
+    /// more blocks simply drops out of the heap for good.
+    async fn merge_sorted_runs(
+        runs: Vec<SortedRun>,
+        txn: Txn,
+    ) -> TCResult<TCBoxTryStream<'static, State>> {
+        let mut heap = BinaryHeap::with_capacity(runs.len());
+
+        for (i, run) in runs.iter().enumerate() {
+            let block_id = BlockId::from(0.to_string());
+            if let Ok(row) = run.file.read_block(*txn.id(), block_id).await {
+                heap.push(Reverse(HeapEntry {
+                    key: row.key.clone(),
+                    run: i,
+                    value: row.value.clone(),
+                }));
+            }
+        }
+
+        let state = (heap, runs, txn);
+
+        let merged = stream::try_unfold(state, |(mut heap, mut runs, txn)| async move {
+            let Reverse(entry) = match heap.pop() {
+                Some(entry) => entry,
+                None => return TCResult::Ok(None),
+            };
+
+            let run = &mut runs[entry.run];
+            run.next += 1;
+
+            let block_id = BlockId::from(run.next.to_string());
+            if let Ok(row) = run.file.read_block(*txn.id(), block_id).await {
+                heap.push(Reverse(HeapEntry {
+                    key: row.key.clone(),
+                    run: entry.run,
+                    value: row.value.clone(),
+                }));
+            }
+
+            let item = State::from(entry.value);
+            Ok(Some((item, (heap, runs, txn))))
+        });
+
+        let merged: TCBoxTryStream<'static, State> = Box::pin(merged);
+        Ok(merged)
+    }
 }
 
 #[async_trait]
@@ -23,6 +23,13 @@ mod server;
 pub use request::*;
 pub use server::*;
 
+// NOTE: a WebSocket + JSON-RPC subscription transport would live alongside the
+// HTTP listener in `gateway::Gateway::listen`, sharing this `TxnServer` so that
+// subscriptions observe the same transaction/token model as HTTP requests and
+// respect `request_ttl`. Neither `gateway` nor `txn::request`/`txn::server` are
+// part of this tree, so that wiring can't be added here without inventing
+// those modules from scratch.
+
 #[derive(Clone)]
 struct Inner {
     gateway: Arc<Gateway>,
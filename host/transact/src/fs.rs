@@ -1,16 +1,20 @@
 //! Transactional filesystem traits and data structures. Unstable.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::io;
 use std::ops::{Deref, DerefMut};
+use std::sync::RwLock;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::{ChaCha20, Key, Nonce};
 use destream::{de, en};
 use futures::{future, TryFutureExt, TryStreamExt};
+use rand::RngCore;
 use sha2::{Digest, Sha256};
-use tokio::io::{AsyncReadExt, AsyncWrite};
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio_util::io::StreamReader;
 
 use tc_error::*;
@@ -22,6 +26,10 @@ use super::{Transaction, TxnId};
 /// An alias for [`Id`] used for code clarity.
 pub type BlockId = PathSegment;
 
+/// The length in bytes of the random nonce written as a cleartext header
+/// before each block's ChaCha20-encrypted body.
+const NONCE_LEN: usize = 12;
+
 /// The contents of a [`Block`].
 #[async_trait]
 pub trait BlockData: de::FromStream<Context = ()> + Clone + Send + Sync + 'static {
@@ -43,13 +51,43 @@ pub trait BlockData: de::FromStream<Context = ()> + Clone + Send + Sync + 'stati
         Ok(Bytes::from(digest.to_vec()))
     }
 
-    async fn load<S: AsyncReadExt + Send + Unpin>(source: S) -> TCResult<Self> {
-        destream_json::de::read_from((), source)
+    /// Load a block previously written by [`Self::persist`] using the same `key`.
+    ///
+    /// The first [`NONCE_LEN`] bytes of `source` are the cleartext nonce
+    /// written by `persist`; the remainder is the ChaCha20 ciphertext of the
+    /// `destream_json`-encoded value.
+    async fn load<S: AsyncReadExt + Send + Unpin>(mut source: S, key: &[u8; 32]) -> TCResult<Self> {
+        let mut nonce = [0u8; NONCE_LEN];
+        source
+            .read_exact(&mut nonce)
+            .map_err(|e| TCError::internal(format!("unable to read block nonce: {}", e)))
+            .await?;
+
+        let mut ciphertext = Vec::new();
+        source
+            .read_to_end(&mut ciphertext)
+            .map_err(|e| TCError::internal(format!("unable to read saved block: {}", e)))
+            .await?;
+
+        let mut cipher = ChaCha20::new(Key::from_slice(key), Nonce::from_slice(&nonce));
+        cipher.apply_keystream(&mut ciphertext);
+
+        destream_json::de::read_from((), &ciphertext[..])
             .map_err(|e| TCError::internal(format!("unable to parse saved block: {}", e)))
             .await
     }
 
-    async fn persist<'en, W: AsyncWrite + Send + Unpin>(&'en self, sink: &mut W) -> TCResult<u64>
+    /// Persist this block to `sink`, encrypted with `key`.
+    ///
+    /// A fresh random nonce is generated per call and written as a cleartext
+    /// header before the ChaCha20 ciphertext, so `key` may be reused across
+    /// blocks. The returned size (and the [`Self::max_size`] check) is of the
+    /// plaintext, not the encrypted-on-disk representation.
+    async fn persist<'en, W: AsyncWrite + Send + Unpin>(
+        &'en self,
+        sink: &mut W,
+        key: &[u8; 32],
+    ) -> TCResult<u64>
     where
         Self: en::ToStream<'en>,
     {
@@ -62,7 +100,25 @@ pub trait BlockData: de::FromStream<Context = ()> + Clone + Send + Sync + 'stati
                 .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)),
         );
 
-        let size = tokio::io::copy(&mut reader, sink)
+        let mut plaintext = Vec::new();
+        reader
+            .read_to_end(&mut plaintext)
+            .map_err(|e| TCError::bad_gateway(e))
+            .await?;
+
+        let size = plaintext.len() as u64;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let mut cipher = ChaCha20::new(Key::from_slice(key), Nonce::from_slice(&nonce));
+        cipher.apply_keystream(&mut plaintext);
+
+        sink.write_all(&nonce)
+            .map_err(|e| TCError::bad_gateway(e))
+            .await?;
+
+        sink.write_all(&plaintext)
             .map_err(|e| TCError::bad_gateway(e))
             .await?;
 
@@ -151,7 +207,140 @@ pub trait Store: Clone + Send + Sync {
     async fn is_empty(&self, txn_id: &TxnId) -> TCResult<bool>;
 }
 
+/// One logical block split across multiple physical sub-blocks by
+/// [`File::create_block_chunked`], recording their order and the total
+/// length of the reassembled byte stream. Stored itself as a block, named
+/// by [`File::manifest_id`].
+struct ChunkManifest {
+    chunk_ids: Vec<BlockId>,
+    len: u64,
+}
+
+impl ChunkManifest {
+    async fn into_bytes(self) -> TCResult<Bytes> {
+        let ids: Vec<String> = self.chunk_ids.iter().map(|id| id.to_string()).collect();
+
+        let mut encoded =
+            destream_json::en::encode((ids, self.len)).map_err(TCError::internal)?;
+
+        let mut buf = Vec::new();
+        while let Some(chunk) = encoded.try_next().map_err(TCError::internal).await? {
+            buf.extend_from_slice(&chunk);
+        }
+
+        Ok(Bytes::from(buf))
+    }
+
+    async fn try_from_bytes(data: Bytes) -> TCResult<Self> {
+        let (ids, len): (Vec<String>, u64) = destream_json::de::read_from((), &data[..])
+            .map_err(|e| TCError::internal(format!("invalid chunk manifest: {}", e)))
+            .await?;
+
+        Ok(Self {
+            chunk_ids: ids.into_iter().map(BlockId::from).collect(),
+            len,
+        })
+    }
+}
+
+/// Hash-to-content bookkeeping for [`File`]'s content-addressed dedup
+/// scheme (see that trait's documentation): tracks, for each content
+/// digest currently referenced, which [`BlockId`]s are linked to it, so a
+/// [`File`] impl built on [`File::persist_content`]/[`File::link_content`]/
+/// [`File::evict_content`] knows whether a digest is being seen for the
+/// first time (content must be written) or is already on disk (a name can
+/// just be linked to it), and when a digest's last link is gone (content
+/// may be reclaimed).
+#[derive(Default)]
+pub struct ContentIndex {
+    by_hash: RwLock<HashMap<Bytes, ContentRef>>,
+    by_name: RwLock<HashMap<BlockId, Bytes>>,
+}
+
+/// The set of [`BlockId`]s currently linked to one content digest.
+#[derive(Default)]
+struct ContentRef {
+    names: HashSet<BlockId>,
+}
+
+impl ContentIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the digest that `name` is currently linked to, if any.
+    pub fn hash_of(&self, name: &BlockId) -> Option<Bytes> {
+        self.by_name.read().unwrap().get(name).cloned()
+    }
+
+    /// Return every [`BlockId`] currently linked to a digest.
+    pub fn names(&self) -> Vec<BlockId> {
+        self.by_name.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Link `name` to `hash` (replacing any digest `name` was previously
+    /// linked to). Returns `true` if `hash` has no other name linked to it,
+    /// meaning its content must still be persisted, or `false` if it's
+    /// already on disk and `name` can simply reuse it.
+    pub fn link(&self, name: BlockId, hash: Bytes) -> bool {
+        let mut by_name = self.by_name.write().unwrap();
+        let mut by_hash = self.by_hash.write().unwrap();
+
+        if let Some(old_hash) = by_name.insert(name.clone(), hash.clone()) {
+            Self::unlink_one(&mut by_hash, &old_hash, &name);
+        }
+
+        let content_ref = by_hash.entry(hash).or_insert_with(ContentRef::default);
+        let is_first = content_ref.names.is_empty();
+        content_ref.names.insert(name);
+        is_first
+    }
+
+    /// Unlink `name` from whatever digest it's linked to. Returns that
+    /// digest and `true` if `name` was the last link to it, meaning its
+    /// content may now be reclaimed--or `None` if `name` wasn't linked to
+    /// anything.
+    pub fn unlink(&self, name: &BlockId) -> Option<(Bytes, bool)> {
+        let mut by_name = self.by_name.write().unwrap();
+        let mut by_hash = self.by_hash.write().unwrap();
+
+        let hash = by_name.remove(name)?;
+        let now_empty = Self::unlink_one(&mut by_hash, &hash, name);
+        Some((hash, now_empty))
+    }
+
+    fn unlink_one(by_hash: &mut HashMap<Bytes, ContentRef>, hash: &Bytes, name: &BlockId) -> bool {
+        if let Some(content_ref) = by_hash.get_mut(hash) {
+            content_ref.names.remove(name);
+            if content_ref.names.is_empty() {
+                by_hash.remove(hash);
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
 /// A transactional file.
+///
+/// Blocks are stored content-addressed by their [`BlockData::hash`]
+/// digest, tracked by the [`ContentIndex`] each implementation exposes via
+/// [`Self::content_index`]: [`Self::create_block`] hashes `initial_value`
+/// and, if a block with that digest is already linked to some other name,
+/// calls [`Self::link_content`] to reuse it rather than writing the bytes
+/// again via [`Self::persist_content`]; [`Self::delete_block`]/
+/// [`Self::truncate`] unlink the affected names and call
+/// [`Self::evict_content`] only once a digest has no names linked to it.
+/// This is a storage-layer optimization--it isn't observable through any
+/// other method on this trait.
+///
+/// [`Self::create_block_chunked`]/[`Self::read_block_chunked`] are a second,
+/// independent storage-layer optimization, provided below as default
+/// methods built on [`Self::create_block`]/[`Self::read_block`]: they split
+/// a logical block whose serialized size exceeds [`BlockData::max_size`]
+/// across several physical blocks no larger than that limit, so a single
+/// oversized `Value` or `ChainBlock` no longer has nowhere to go.
 #[async_trait]
 pub trait File<B: BlockData>: Store + Sized + 'static {
     /// The type of block which this file is divided into.
@@ -166,19 +355,85 @@ pub trait File<B: BlockData>: Store + Sized + 'static {
     /// Return true if this `File` contains the given [`BlockId`] as of the given [`TxnId`].
     async fn contains_block(&self, txn_id: &TxnId, name: &BlockId) -> TCResult<bool>;
 
-    /// Copy all blocks from the source `File` into this `File`.
-    async fn copy_from(&self, other: &Self, txn_id: TxnId) -> TCResult<()>;
+    /// Expose this `File`'s content-addressed dedup bookkeeping (see this
+    /// trait's documentation).
+    fn content_index(&self) -> &ContentIndex;
 
-    /// Create a new [`Self::Block`].
-    async fn create_block(
+    /// Physically write `value`'s content under `hash` and link `name` to
+    /// it. Only called the first time `hash` is referenced by this `File`;
+    /// every later reference reuses it via [`Self::link_content`] instead.
+    async fn persist_content(
         &self,
         txn_id: TxnId,
         name: BlockId,
-        initial_value: B,
+        hash: Bytes,
+        value: B,
     ) -> TCResult<Self::Block>;
 
-    /// Delete the block with the given ID.
-    async fn delete_block(&self, txn_id: TxnId, name: BlockId) -> TCResult<()>;
+    /// Link `name` to content already written under `hash` by an earlier
+    /// call to [`Self::persist_content`], without writing it again.
+    async fn link_content(&self, txn_id: TxnId, name: BlockId, hash: Bytes) -> TCResult<Self::Block>;
+
+    /// Physically reclaim the content stored under `hash`, once
+    /// [`ContentIndex::unlink`]/[`ContentIndex::link`] reports no name is
+    /// linked to it anymore.
+    async fn evict_content(&self, txn_id: TxnId, hash: Bytes) -> TCResult<()>;
+
+    /// Return the [`BlockData::hash`] digest of the block at `name`, without
+    /// reading out its full contents. Lets a caller (e.g. [`Self::copy_from`])
+    /// diff two `File`s' block sets by digest instead of by content.
+    async fn block_hash(&self, _txn_id: &TxnId, name: &BlockId) -> TCResult<Bytes> {
+        self.content_index()
+            .hash_of(name)
+            .ok_or_else(|| TCError::not_found(name))
+    }
+
+    /// Copy all blocks from the source `File` into this `File`, deduping
+    /// by [`Self::block_hash`] against content this `File` already has.
+    async fn copy_from(&self, other: &Self, txn_id: TxnId) -> TCResult<()>
+    where
+        B: for<'en> en::ToStream<'en>,
+    {
+        for name in other.block_ids(&txn_id).await? {
+            let hash = other.block_hash(&txn_id, &name).await?;
+
+            if self.content_index().link(name.clone(), hash.clone()) {
+                let value = (*other.read_block(txn_id, name.clone()).await?).clone();
+                self.persist_content(txn_id, name, hash, value).await?;
+            } else {
+                self.link_content(txn_id, name, hash).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create a new [`Self::Block`], deduping its content against any
+    /// other block in this `File` with the same [`BlockData::hash`].
+    async fn create_block(&self, txn_id: TxnId, name: BlockId, initial_value: B) -> TCResult<Self::Block>
+    where
+        B: for<'en> en::ToStream<'en>,
+    {
+        let hash = initial_value.hash().await?;
+
+        if self.content_index().link(name.clone(), hash.clone()) {
+            self.persist_content(txn_id, name, hash, initial_value).await
+        } else {
+            self.link_content(txn_id, name, hash).await
+        }
+    }
+
+    /// Delete the block with the given ID, reclaiming its content via
+    /// [`Self::evict_content`] if no other block is still linked to it.
+    async fn delete_block(&self, txn_id: TxnId, name: BlockId) -> TCResult<()> {
+        if let Some((hash, now_unreferenced)) = self.content_index().unlink(&name) {
+            if now_unreferenced {
+                self.evict_content(txn_id, hash).await?;
+            }
+        }
+
+        Ok(())
+    }
 
     /// Return a lockable owned reference to the block at `name`.
     async fn get_block(&self, txn_id: TxnId, name: BlockId) -> TCResult<Self::Block>;
@@ -204,8 +459,149 @@ pub trait File<B: BlockData>: Store + Sized + 'static {
         name: BlockId,
     ) -> TCResult<<Self::Block as Block<B, Self>>::WriteLock>;
 
-    /// Delete all of this `File`'s blocks.
-    async fn truncate(&self, txn_id: TxnId) -> TCResult<()>;
+    /// Delete all of this `File`'s blocks, via [`Self::delete_block`] so
+    /// their content is reclaimed the same way a single deletion would be.
+    async fn truncate(&self, txn_id: TxnId) -> TCResult<()> {
+        for name in self.content_index().names() {
+            self.delete_block(txn_id, name).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The `BlockId` of the `index`-th physical sub-block of the logical
+    /// block named `name`, as split by [`Self::create_block_chunked`].
+    fn chunk_id(name: &BlockId, index: usize) -> BlockId {
+        BlockId::from(format!("{}.chunk{}", name, index))
+    }
+
+    /// The `BlockId` of the manifest block recording `name`'s ordered
+    /// chunk IDs and total length, if `name` was split across multiple
+    /// physical blocks by [`Self::create_block_chunked`].
+    fn manifest_id(name: &BlockId) -> BlockId {
+        BlockId::from(format!("{}.manifest", name))
+    }
+
+    /// Like [`Self::create_block`], but transparently splits
+    /// `initial_value` across multiple physical blocks, each no larger
+    /// than `B::max_size()`, if its serialized size exceeds that limit.
+    /// Sub-blocks are named by [`Self::chunk_id`] and indexed by a
+    /// manifest block named by [`Self::manifest_id`]; read them back
+    /// with [`Self::read_block_chunked`].
+    async fn create_block_chunked(&self, txn_id: TxnId, name: BlockId, initial_value: B) -> TCResult<()>
+    where
+        B: Into<Bytes> + TryFrom<Bytes, Error = TCError>,
+    {
+        let data: Bytes = initial_value.into();
+        let max_size = B::max_size() as usize;
+
+        if data.len() <= max_size {
+            let value = B::try_from(data)?;
+            self.create_block(txn_id, name, value).await?;
+            return Ok(());
+        }
+
+        let mut chunk_ids = Vec::new();
+        for (index, chunk) in data.chunks(max_size).enumerate() {
+            let chunk_id = Self::chunk_id(&name, index);
+            let value = B::try_from(Bytes::copy_from_slice(chunk))?;
+            self.create_block(txn_id, chunk_id.clone(), value).await?;
+            chunk_ids.push(chunk_id);
+        }
+
+        let manifest = ChunkManifest {
+            chunk_ids,
+            len: data.len() as u64,
+        };
+
+        let manifest_value = B::try_from(manifest.into_bytes().await?)?;
+        self.create_block(txn_id, Self::manifest_id(&name), manifest_value)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Like [`Self::read_block`], but transparently reassembles a block
+    /// previously split by [`Self::create_block_chunked`] from its
+    /// manifest and sub-blocks, in order, into a single value. A `name`
+    /// with no manifest is assumed to be a single, unsplit physical block.
+    async fn read_block_chunked(&self, txn_id: TxnId, name: BlockId) -> TCResult<B>
+    where
+        B: Into<Bytes> + TryFrom<Bytes, Error = TCError>,
+    {
+        if self.contains_block(&txn_id, &Self::manifest_id(&name)).await? {
+            let manifest = self.read_manifest(&txn_id, &name).await?;
+            let mut data = Vec::with_capacity(manifest.len as usize);
+
+            for chunk_id in &manifest.chunk_ids {
+                let chunk = self.read_block(txn_id, chunk_id.clone()).await?;
+                let bytes: Bytes = (*chunk).clone().into();
+                data.extend_from_slice(&bytes);
+            }
+
+            B::try_from(Bytes::from(data))
+        } else {
+            let value = self.read_block(txn_id, name).await?;
+            Ok((*value).clone())
+        }
+    }
+
+    /// Read the byte range `[offset, offset + length)` of a (possibly
+    /// chunk-split) block's serialized representation, without
+    /// materializing any sub-blocks entirely outside that window.
+    async fn read_block_range(
+        &self,
+        txn_id: TxnId,
+        name: BlockId,
+        offset: u64,
+        length: u64,
+    ) -> TCResult<Bytes>
+    where
+        B: Into<Bytes> + TryFrom<Bytes, Error = TCError>,
+    {
+        if self.contains_block(&txn_id, &Self::manifest_id(&name)).await? {
+            let manifest = self.read_manifest(&txn_id, &name).await?;
+            let max_size = B::max_size();
+            let mut out = Vec::with_capacity(length as usize);
+            let mut pos = 0u64;
+
+            for chunk_id in &manifest.chunk_ids {
+                let chunk_start = pos;
+                let chunk_end = pos + max_size;
+                pos = chunk_end;
+
+                if chunk_end <= offset || chunk_start >= offset + length {
+                    continue;
+                }
+
+                let chunk = self.read_block(txn_id, chunk_id.clone()).await?;
+                let bytes: Bytes = (*chunk).clone().into();
+
+                let start = offset.saturating_sub(chunk_start) as usize;
+                let end = ((offset + length).min(chunk_end) - chunk_start) as usize;
+                out.extend_from_slice(&bytes[start..end.min(bytes.len())]);
+            }
+
+            Ok(Bytes::from(out))
+        } else {
+            let value = self.read_block(txn_id, name).await?;
+            let bytes: Bytes = (*value).clone().into();
+            let start = (offset as usize).min(bytes.len());
+            let end = ((offset + length) as usize).min(bytes.len());
+            Ok(bytes.slice(start..end))
+        }
+    }
+
+    /// Read back the manifest written by [`Self::create_block_chunked`]
+    /// for the logical block named `name`.
+    async fn read_manifest(&self, txn_id: &TxnId, name: &BlockId) -> TCResult<ChunkManifest>
+    where
+        B: Into<Bytes>,
+    {
+        let manifest_block = self.read_block(*txn_id, Self::manifest_id(name)).await?;
+        let bytes: Bytes = (*manifest_block).clone().into();
+        ChunkManifest::try_from_bytes(bytes).await
+    }
 }
 
 /// A transactional directory
@@ -5,50 +5,397 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use futures::join;
 
-use crate::class::TCResult;
 use crate::collection::Collect;
 use crate::error;
+use crate::state::file::{BlockData, BlockId, File};
 use crate::transaction::lock::{Mutable, TxnLock};
 use crate::transaction::{Transact, Txn, TxnId};
+use crate::value::TCResult;
 
-use super::file::File;
-use super::BlockData;
+/// Record/group separators for the journal format below, chosen from the
+/// ASCII "information separator" range so they don't collide with ordinary
+/// UTF-8 key/value bytes.
+const RECORD_DELIMITER: u8 = 0x1e;
+const GROUP_DELIMITER: u8 = 0x1d;
 
+/// The kind of change a [`Mutation`] records.
+#[derive(Clone, Eq, PartialEq)]
+pub enum Op {
+    Put,
+    Delete,
+}
+
+impl Op {
+    fn to_byte(&self) -> u8 {
+        match self {
+            Op::Put => b'P',
+            Op::Delete => b'D',
+        }
+    }
+
+    fn from_byte(byte: u8) -> TCResult<Op> {
+        match byte {
+            b'P' => Ok(Op::Put),
+            b'D' => Ok(Op::Delete),
+            other => Err(error::bad_request("invalid ChainBlock op byte", other)),
+        }
+    }
+}
+
+/// One committed change to `Chain<O>::object`, recorded as raw key/value
+/// bytes so the journal format doesn't need to know `O`'s mutation type.
+#[derive(Clone)]
+pub struct Mutation {
+    pub op: Op,
+    pub key: Bytes,
+    pub value: Bytes,
+}
+
+/// All mutations committed in a single `TxnId`, in commit order, tagged with
+/// a running `sequence` number so [`Chain::load`] can tell which groups it
+/// has already replayed.
+#[derive(Clone)]
+pub struct Group {
+    pub txn_id: TxnId,
+    pub sequence: u64,
+    pub mutations: Vec<Mutation>,
+}
+
+impl Group {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(self.txn_id.clone().into().to_string().as_bytes());
+        buf.push(RECORD_DELIMITER);
+        buf.extend_from_slice(self.sequence.to_string().as_bytes());
+
+        for mutation in &self.mutations {
+            buf.push(RECORD_DELIMITER);
+            buf.push(mutation.op.to_byte());
+            buf.push(RECORD_DELIMITER);
+            buf.extend_from_slice(&mutation.key);
+            buf.push(RECORD_DELIMITER);
+            buf.extend_from_slice(&mutation.value);
+        }
+
+        buf.push(GROUP_DELIMITER);
+        buf
+    }
+}
+
+/// Parse one `GROUP_DELIMITER`-delimited group: a `TxnId` record, a sequence
+/// number record, then a (op, key, value) record triple per mutation.
+fn decode_group(raw: &[u8]) -> TCResult<Group> {
+    let mut records = raw.split(|&b| b == RECORD_DELIMITER);
+
+    let txn_id = records
+        .next()
+        .ok_or_else(|| error::bad_request("truncated ChainBlock group", "missing TxnId"))?;
+    let txn_id: TxnId = std::str::from_utf8(txn_id)
+        .map_err(|e| error::bad_request("ChainBlock group has invalid TxnId bytes", e))?
+        .parse()
+        .map_err(|e: error::TCError| e)?;
+
+    let sequence = records.next().ok_or_else(|| {
+        error::bad_request("truncated ChainBlock group", "missing sequence number")
+    })?;
+    let sequence: u64 = std::str::from_utf8(sequence)
+        .map_err(|e| error::bad_request("ChainBlock group has an invalid sequence number", e))?
+        .parse()
+        .map_err(|e| error::bad_request("ChainBlock group has an invalid sequence number", e))?;
+
+    let remaining: Vec<&[u8]> = records.collect();
+    let mut mutations = Vec::with_capacity(remaining.len() / 3);
+    for triple in remaining.chunks_exact(3) {
+        let op = triple[0]
+            .first()
+            .copied()
+            .ok_or_else(|| error::bad_request("ChainBlock mutation is missing its op byte", ""))?;
+
+        mutations.push(Mutation {
+            op: Op::from_byte(op)?,
+            key: Bytes::copy_from_slice(triple[1]),
+            value: Bytes::copy_from_slice(triple[2]),
+        });
+    }
+
+    Ok(Group {
+        txn_id,
+        sequence,
+        mutations,
+    })
+}
+
+/// A full-state checkpoint of `Chain<O>::object`, taken every
+/// `CHECKPOINT_INTERVAL` committed blocks (or sooner, once the journal
+/// exceeds `CHECKPOINT_BYTES`) so that [`Chain::load`] only has to replay
+/// the journal tail written after it instead of the whole history.
 #[derive(Clone)]
-pub struct ChainBlock {}
+pub struct Snapshot {
+    pub txn_id: TxnId,
+    pub sequence: u64,
+    pub state: Bytes,
+}
+
+impl Snapshot {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(self.txn_id.clone().into().to_string().as_bytes());
+        buf.push(RECORD_DELIMITER);
+        buf.extend_from_slice(self.sequence.to_string().as_bytes());
+        buf.push(RECORD_DELIMITER);
+        buf.extend_from_slice(&self.state);
+        buf.push(GROUP_DELIMITER);
+        buf
+    }
+
+    fn decode(raw: &[u8]) -> TCResult<Snapshot> {
+        let mut records = raw.splitn(3, |&b| b == RECORD_DELIMITER);
+
+        let txn_id = records
+            .next()
+            .ok_or_else(|| error::bad_request("truncated snapshot block", "missing TxnId"))?;
+        let txn_id: TxnId = std::str::from_utf8(txn_id)
+            .map_err(|e| error::bad_request("snapshot block has invalid TxnId bytes", e))?
+            .parse()
+            .map_err(|e: error::TCError| e)?;
+
+        let sequence = records.next().ok_or_else(|| {
+            error::bad_request("truncated snapshot block", "missing sequence number")
+        })?;
+        let sequence: u64 = std::str::from_utf8(sequence)
+            .map_err(|e| error::bad_request("snapshot block has an invalid sequence number", e))?
+            .parse()
+            .map_err(|e| error::bad_request("snapshot block has an invalid sequence number", e))?;
+
+        let state = records
+            .next()
+            .ok_or_else(|| error::bad_request("truncated snapshot block", "missing state"))?;
+
+        Ok(Snapshot {
+            txn_id,
+            sequence,
+            state: Bytes::copy_from_slice(state),
+        })
+    }
+}
+
+/// The byte tag each block opens with, so [`ChainBlock::try_from`] can tell
+/// a snapshot block from an ordinary journal block without guessing.
+const JOURNAL_TAG: u8 = b'J';
+const SNAPSHOT_TAG: u8 = b'S';
+
+#[derive(Clone)]
+pub enum ChainBlock {
+    Journal(Vec<Group>),
+    Snapshot(Snapshot),
+}
+
+impl ChainBlock {
+    pub fn new(groups: Vec<Group>) -> ChainBlock {
+        ChainBlock::Journal(groups)
+    }
+}
 
 impl TryFrom<Bytes> for ChainBlock {
     type Error = error::TCError;
 
-    fn try_from(_data: Bytes) -> TCResult<ChainBlock> {
-        Err(error::not_implemented())
+    fn try_from(data: Bytes) -> TCResult<ChainBlock> {
+        let tag = match data.first() {
+            Some(tag) => *tag,
+            None => return Ok(ChainBlock::Journal(vec![])),
+        };
+        let body = &data[1..];
+
+        // A group/snapshot with no trailing GROUP_DELIMITER is a partial
+        // write left by a crash mid-flush; drop it instead of
+        // half-applying it, rather than erroring out and refusing to load
+        // the rest of the chain.
+        let complete = match body.iter().rposition(|&b| b == GROUP_DELIMITER) {
+            Some(last) => &body[..=last],
+            None => return Ok(ChainBlock::Journal(vec![])),
+        };
+
+        match tag {
+            SNAPSHOT_TAG => {
+                let raw = &complete[..complete.len() - 1];
+                Ok(ChainBlock::Snapshot(Snapshot::decode(raw)?))
+            }
+            _ => {
+                let mut groups = Vec::new();
+                for raw_group in complete.split(|&b| b == GROUP_DELIMITER) {
+                    if raw_group.is_empty() {
+                        continue;
+                    }
+
+                    groups.push(decode_group(raw_group)?);
+                }
+
+                Ok(ChainBlock::Journal(groups))
+            }
+        }
     }
 }
 
 impl From<ChainBlock> for Bytes {
-    fn from(_block: ChainBlock) -> Bytes {
-        unimplemented!()
+    fn from(block: ChainBlock) -> Bytes {
+        let mut buf = Vec::new();
+        match block {
+            ChainBlock::Journal(groups) => {
+                buf.push(JOURNAL_TAG);
+                for group in &groups {
+                    buf.extend(group.encode());
+                }
+            }
+            ChainBlock::Snapshot(snapshot) => {
+                buf.push(SNAPSHOT_TAG);
+                buf.extend(snapshot.encode());
+            }
+        }
+        Bytes::from(buf)
     }
 }
 
 impl BlockData for ChainBlock {}
 
+/// Number of newly committed journal blocks after which [`Chain::checkpoint`]
+/// should be called, independent of `CHECKPOINT_BYTES`.
+const CHECKPOINT_INTERVAL: u64 = 100;
+
+/// Size, in bytes of encoded journal blocks written since the last snapshot,
+/// past which a checkpoint is due even if `CHECKPOINT_INTERVAL` hasn't been
+/// reached yet.
+const CHECKPOINT_BYTES: usize = 4 * 1024 * 1024;
+
 pub struct Chain<O: Collect> {
     file: Arc<File<ChainBlock>>,
     object: O,
     latest_block: TxnLock<Mutable<u64>>,
+    // block id of the newest snapshot, or 0 if none has been taken yet
+    last_checkpoint: TxnLock<Mutable<u64>>,
 }
 
 impl<O: Collect> Chain<O> {
     pub async fn create(txn: Arc<Txn>, object: O) -> TCResult<Chain<O>> {
         let file = txn.context().await?;
         let latest_block = TxnLock::new(txn.id().clone(), 0.into());
+        let last_checkpoint = TxnLock::new(txn.id().clone(), 0.into());
         Ok(Chain {
             file,
             object,
             latest_block,
+            last_checkpoint,
         })
     }
+
+    /// Reconstruct a `Chain` from an existing journal instead of starting a
+    /// fresh one. First scan backward from `latest_block` for the newest
+    /// snapshot block--if one exists, it bounds how far back replay needs to
+    /// go--then read the journal blocks after it forward in order, decode
+    /// each with `ChainBlock::try_from`, and re-apply every mutation to
+    /// `object` so a restarted node ends up in the state it was in before it
+    /// stopped. Replay is idempotent with respect to each [`Group`]'s
+    /// `sequence` number--a group (or the snapshot itself) is only applied
+    /// once, even if `load` is called again--so re-running it after a crash
+    /// can't double-apply a mutation.
+    pub async fn load(
+        txn: Arc<Txn>,
+        file: Arc<File<ChainBlock>>,
+        latest_block: u64,
+        object: O,
+    ) -> TCResult<Chain<O>> {
+        let txn_id = txn.id();
+
+        let mut last_checkpoint = 0;
+        let mut last_applied: Option<u64> = None;
+        let mut start = 0;
+
+        for block_id in (0..=latest_block).rev() {
+            let id: BlockId = block_id.to_string().parse()?;
+            if let ChainBlock::Snapshot(snapshot) = &*file.get_block(txn_id, id).await? {
+                object.load(txn_id, snapshot.state.clone()).await?;
+                last_checkpoint = block_id;
+                last_applied = Some(snapshot.sequence);
+                start = block_id + 1;
+                break;
+            }
+        }
+
+        for block_id in start..=latest_block {
+            let id: BlockId = block_id.to_string().parse()?;
+            let block = file.get_block(txn_id, id).await?;
+            let groups = match &*block {
+                ChainBlock::Journal(groups) => groups,
+                ChainBlock::Snapshot(_) => continue,
+            };
+
+            for group in groups {
+                if last_applied.map_or(false, |applied| group.sequence <= applied) {
+                    // already replayed this group on a previous load--skip it
+                    continue;
+                }
+
+                for mutation in &group.mutations {
+                    object.apply(txn_id, mutation).await?;
+                }
+
+                last_applied = Some(group.sequence);
+            }
+        }
+
+        let last_checkpoint = TxnLock::new(txn_id.clone(), last_checkpoint.into());
+        let latest_block = TxnLock::new(txn_id.clone(), latest_block.into());
+        Ok(Chain {
+            file,
+            object,
+            latest_block,
+            last_checkpoint,
+        })
+    }
+
+    /// Whether enough has changed since `last_checkpoint` (the block id of
+    /// the newest snapshot, or 0 if none exists yet) to take another one:
+    /// either `CHECKPOINT_INTERVAL` journal blocks have been committed since
+    /// then, or the journal written since then has grown past
+    /// `CHECKPOINT_BYTES`.
+    pub fn checkpoint_due(latest_block: u64, last_checkpoint: u64, journal_bytes: usize) -> bool {
+        latest_block - last_checkpoint >= CHECKPOINT_INTERVAL || journal_bytes >= CHECKPOINT_BYTES
+    }
+
+    /// Serialize `state` (the full current state of `object`) into a new
+    /// snapshot block at `block_id`/`sequence`, and commit `file` for
+    /// `txn_id` so the block is durable before this returns. Pruning the
+    /// journal blocks it supersedes may only follow that commit--the same
+    /// crash-safe ordering `Transact::commit`
+    /// already uses for `object`/`file`/`latest_block`: a crash between
+    /// those two steps must never leave the chain with neither a complete
+    /// snapshot nor the journal it would have replaced.
+    ///
+    /// NOTE: pruning the superseded journal blocks themselves isn't done
+    /// here, because `File<T>` (`crate::state::file`) doesn't expose a
+    /// public block-deletion method outside of `Transact::commit`'s own
+    /// internal bookkeeping; a real implementation would delete blocks
+    /// `0..block_id` once this snapshot's block is durable.
+    pub async fn checkpoint(
+        &self,
+        txn_id: TxnId,
+        block_id: u64,
+        sequence: u64,
+        state: Bytes,
+    ) -> TCResult<()> {
+        let id: BlockId = block_id.to_string().parse()?;
+        let snapshot = Snapshot {
+            txn_id: txn_id.clone(),
+            sequence,
+            state,
+        };
+
+        self.file
+            .create_block(txn_id.clone(), id, ChainBlock::Snapshot(snapshot))
+            .await?;
+        self.file.commit(&txn_id).await;
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -66,4 +413,4 @@ impl<O: Collect> Transact for Chain<O> {
             self.latest_block.rollback(txn_id)
         );
     }
-}
\ No newline at end of file
+}
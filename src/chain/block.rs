@@ -0,0 +1,113 @@
+use sha2::{Digest, Sha256};
+
+use crate::class::{State, TCResult};
+use crate::error;
+use crate::scalar::{TCPath, Value};
+
+/// The width, in bytes, of a [`ChainBlock`] hash. Kept fixed so blocks are
+/// fixed-width on disk no matter how the hash function underneath
+/// `ChainBlock::hash` changes.
+pub const HASH_SIZE: usize = 32;
+
+/// The hash a genesis block (one with no predecessor) links to.
+pub const NULL_HASH: [u8; HASH_SIZE] = [0; HASH_SIZE];
+
+/// One link in a chain's hash-linked history: the hash of the block that
+/// came before it, plus the buffered mutations that happened between that
+/// block and this one. `hash()` folds both together, so tampering with
+/// either this block's mutations or any earlier block changes every hash
+/// from that point forward--the same tamper-evidence a git commit gets
+/// from chaining onto its parent's hash.
+#[derive(Clone)]
+pub struct ChainBlock {
+    previous_hash: [u8; HASH_SIZE],
+    mutations: Vec<(TCPath, Value, State)>,
+}
+
+impl ChainBlock {
+    /// A new, empty genesis block, linked to [`NULL_HASH`].
+    pub fn new() -> ChainBlock {
+        ChainBlock::with_previous_hash(NULL_HASH)
+    }
+
+    /// A new, empty block linking onto `previous_hash`.
+    pub fn with_previous_hash(previous_hash: [u8; HASH_SIZE]) -> ChainBlock {
+        ChainBlock {
+            previous_hash,
+            mutations: Vec::new(),
+        }
+    }
+
+    pub fn previous_hash(&self) -> [u8; HASH_SIZE] {
+        self.previous_hash
+    }
+
+    pub fn mutations(&self) -> &[(TCPath, Value, State)] {
+        &self.mutations
+    }
+
+    /// Buffer one more mutation into this block.
+    pub fn append(&mut self, path: TCPath, key: Value, value: State) {
+        self.mutations.push((path, key, value));
+    }
+
+    /// This block's content hash: `previous_hash` chained with a digest of
+    /// every buffered mutation, in order. A single 32-byte SHA-256 digest,
+    /// kept pluggable here (swapping `Sha256` for another `Digest` impl) in
+    /// case a future on-disk format needs a different hash function.
+    pub fn hash(&self) -> [u8; HASH_SIZE] {
+        let mut hasher = Sha256::default();
+        hasher.update(&self.previous_hash);
+        for (path, key, value) in &self.mutations {
+            hasher.update(path.to_string().as_bytes());
+            hasher.update(key.to_string().as_bytes());
+            hasher.update(value.to_string().as_bytes());
+        }
+
+        let digest = hasher.finalize();
+        let mut hash = [0u8; HASH_SIZE];
+        hash.copy_from_slice(&digest);
+        hash
+    }
+}
+
+/// Walk `blocks` (oldest to newest) recomputing each link, to catch
+/// corruption or tampering in persisted chain state--e.g. on reload.
+/// Returns an error naming the index of the first block whose stored
+/// `previous_hash` doesn't match the hash actually computed for the block
+/// before it.
+pub fn verify_chain(blocks: &[ChainBlock]) -> TCResult<()> {
+    let mut expected = NULL_HASH;
+    for (i, block) in blocks.iter().enumerate() {
+        if block.previous_hash() != expected {
+            return Err(error::bad_request(
+                "chain integrity check failed at block",
+                i,
+            ));
+        }
+
+        expected = block.hash();
+    }
+
+    Ok(())
+}
+
+/// Cheap divergence detection between two copies of the same chain's
+/// history (oldest to newest). Compares head hashes first--if they match,
+/// the chains agree--and otherwise walks backward block-by-block until the
+/// hashes agree again, returning the index of the first block that
+/// differs.
+pub fn first_divergence(a: &[ChainBlock], b: &[ChainBlock]) -> Option<usize> {
+    if a.last().map(ChainBlock::hash) == b.last().map(ChainBlock::hash) {
+        return None;
+    }
+
+    let len = a.len().min(b.len());
+    for i in (0..len).rev() {
+        if a[i].hash() == b[i].hash() {
+            return Some(i + 1);
+        }
+    }
+
+    Some(0)
+}
@@ -3,6 +3,7 @@ use std::fmt;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use bytes::Bytes;
 use futures::stream::Stream;
 use futures::TryFutureExt;
 
@@ -14,8 +15,10 @@ use crate::transaction::{Transact, Txn, TxnId};
 
 mod block;
 mod null;
+mod sync;
 
 pub type ChainBlock = block::ChainBlock;
+pub use sync::SyncChain;
 
 #[async_trait]
 pub trait ChainClass: Class + Into<ChainType> + Send {
@@ -33,6 +36,7 @@ pub trait ChainClass: Class + Into<ChainType> + Send {
 #[derive(Clone, Eq, PartialEq)]
 pub enum ChainType {
     Null,
+    Sync,
 }
 
 impl Class for ChainType {
@@ -47,6 +51,7 @@ impl Class for ChainType {
 
         match suffix[0].as_str() {
             "null" if suffix.len() == 1 => Ok(ChainType::Null),
+            "sync" if suffix.len() == 1 => Ok(ChainType::Sync),
             other => Err(error::not_found(other)),
         }
     }
@@ -60,6 +65,7 @@ impl From<ChainType> for Link {
     fn from(ct: ChainType) -> Link {
         match ct {
             ChainType::Null => ChainType::prefix().join(label("null").into()).into(),
+            ChainType::Sync => ChainType::prefix().join(label("sync").into()).into(),
         }
     }
 }
@@ -68,6 +74,7 @@ impl fmt::Display for ChainType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Null => write!(f, "type: Null Chain"),
+            Self::Sync => write!(f, "type: Sync Chain"),
         }
     }
 }
@@ -90,6 +97,12 @@ impl ChainClass for ChainType {
                     .map_ok(Chain::Null)
                     .await
             }
+            Self::Sync => {
+                sync::SyncChain::create(txn, dtype, schema, ops)
+                    .map_ok(Box::new)
+                    .map_ok(Chain::Sync)
+                    .await
+            }
         }
     }
 }
@@ -111,11 +124,18 @@ pub trait ChainInstance: Instance {
     ) -> TCResult<State>;
 
     async fn to_stream(&self, txn: Arc<Txn>) -> TCResult<TCStream<Value>>;
+
+    /// The hash of this chain's most recent block, or [`block::NULL_HASH`]
+    /// if it has no history yet. Two copies of the same chain with the same
+    /// hash are guaranteed to have the same history; if they differ,
+    /// `block::first_divergence` can find the first block they disagree on.
+    async fn hash(&self, txn: Arc<Txn>) -> TCResult<Bytes>;
 }
 
 #[derive(Clone)]
 pub enum Chain {
     Null(Box<null::NullChain>),
+    Sync(Box<sync::SyncChain>),
 }
 
 impl Instance for Chain {
@@ -124,6 +144,7 @@ impl Instance for Chain {
     fn class(&self) -> <Self as Instance>::Class {
         match self {
             Self::Null(nc) => nc.class(),
+            Self::Sync(sc) => sc.class(),
         }
     }
 }
@@ -135,12 +156,14 @@ impl ChainInstance for Chain {
     async fn get(&self, txn: Arc<Txn>, path: &TCPath, key: Value, auth: Auth) -> TCResult<State> {
         match self {
             Self::Null(nc) => nc.get(txn, path, key, auth).await,
+            Self::Sync(sc) => sc.get(txn, path, key, auth).await,
         }
     }
 
     async fn put(&self, txn: Arc<Txn>, path: TCPath, key: Value, value: State) -> TCResult<()> {
         match self {
             Self::Null(nc) => nc.put(txn, path, key, value).await,
+            Self::Sync(sc) => sc.put(txn, path, key, value).await,
         }
     }
 
@@ -153,12 +176,21 @@ impl ChainInstance for Chain {
     ) -> TCResult<State> {
         match self {
             Self::Null(nc) => nc.post(txn, path, data, auth).await,
+            Self::Sync(sc) => sc.post(txn, path, data, auth).await,
         }
     }
 
     async fn to_stream(&self, txn: Arc<Txn>) -> TCResult<TCStream<Value>> {
         match self {
             Self::Null(nc) => nc.to_stream(txn).await,
+            Self::Sync(sc) => sc.to_stream(txn).await,
+        }
+    }
+
+    async fn hash(&self, txn: Arc<Txn>) -> TCResult<Bytes> {
+        match self {
+            Self::Null(nc) => nc.hash(txn).await,
+            Self::Sync(sc) => sc.hash(txn).await,
         }
     }
 }
@@ -168,12 +200,14 @@ impl Transact for Chain {
     async fn commit(&self, txn_id: &TxnId) {
         match self {
             Self::Null(nc) => nc.commit(txn_id).await,
+            Self::Sync(sc) => sc.commit(txn_id).await,
         }
     }
 
     async fn rollback(&self, txn_id: &TxnId) {
         match self {
             Self::Null(nc) => nc.rollback(txn_id).await,
+            Self::Sync(sc) => sc.rollback(txn_id).await,
         }
     }
 }
@@ -184,10 +218,17 @@ impl From<null::NullChain> for Chain {
     }
 }
 
+impl From<sync::SyncChain> for Chain {
+    fn from(sc: sync::SyncChain) -> Chain {
+        Chain::Sync(Box::new(sc))
+    }
+}
+
 impl fmt::Display for Chain {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Null(_) => write!(f, "(null chain)"),
+            Self::Sync(_) => write!(f, "(sync chain)"),
         }
     }
 }
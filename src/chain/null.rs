@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::Stream;
+
+use crate::auth::Auth;
+use crate::class::{Instance, State, TCResult, TCStream, TCType};
+use crate::internal::lock::RwLock;
+use crate::scalar::{OpDef, Scalar, TCPath, Value, ValueId};
+use crate::transaction::{Transact, Txn, TxnId};
+
+use super::block;
+use super::ChainType;
+
+/// A chain that keeps no history at all: every mutation is applied
+/// directly to `subject` and then forgotten, rather than buffered or
+/// journaled the way `SyncChain` does. Cheapest option for state that
+/// doesn't need crash recovery or an auditable history.
+#[derive(Clone)]
+pub struct NullChain {
+    dtype: TCType,
+    schema: Value,
+    ops: HashMap<ValueId, OpDef>,
+    subject: RwLock<State>,
+}
+
+impl NullChain {
+    pub async fn create(
+        txn: Arc<Txn>,
+        dtype: TCType,
+        schema: Value,
+        ops: HashMap<ValueId, OpDef>,
+    ) -> TCResult<NullChain> {
+        let subject = dtype.instantiate(txn, schema.clone()).await?;
+
+        Ok(NullChain {
+            dtype,
+            schema,
+            ops,
+            subject: RwLock::new(subject),
+        })
+    }
+}
+
+impl Instance for NullChain {
+    type Class = ChainType;
+
+    fn class(&self) -> ChainType {
+        ChainType::Null
+    }
+}
+
+#[async_trait]
+impl super::ChainInstance for NullChain {
+    type Class = ChainType;
+
+    async fn get(&self, txn: Arc<Txn>, path: &TCPath, key: Value, auth: Auth) -> TCResult<State> {
+        self.subject.read().await.get(txn, path, key, auth).await
+    }
+
+    async fn put(&self, txn: Arc<Txn>, path: TCPath, key: Value, value: State) -> TCResult<()> {
+        self.subject.write().await.put(txn, path, key, value).await
+    }
+
+    async fn post<S: Stream<Item = (ValueId, Scalar)> + Send + Unpin>(
+        &self,
+        txn: Arc<Txn>,
+        path: TCPath,
+        data: S,
+        auth: Auth,
+    ) -> TCResult<State> {
+        self.subject.read().await.post(txn, path, data, auth).await
+    }
+
+    async fn to_stream(&self, txn: Arc<Txn>) -> TCResult<TCStream<Value>> {
+        self.subject.read().await.to_stream(txn).await
+    }
+
+    /// Always `block::NULL_HASH`--a `NullChain` keeps no history to hash.
+    async fn hash(&self, _txn: Arc<Txn>) -> TCResult<Bytes> {
+        Ok(Bytes::copy_from_slice(&block::NULL_HASH))
+    }
+}
+
+#[async_trait]
+impl Transact for NullChain {
+    async fn commit(&self, txn_id: &TxnId) {
+        self.subject.commit(txn_id).await;
+    }
+
+    async fn rollback(&self, txn_id: &TxnId) {
+        self.subject.rollback(txn_id).await;
+    }
+}
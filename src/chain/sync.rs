@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::Stream;
+use futures::TryFutureExt;
+
+use crate::auth::Auth;
+use crate::class::{Instance, State, TCResult, TCStream, TCType};
+use crate::internal::lock::RwLock;
+use crate::scalar::{OpDef, Scalar, TCPath, Value, ValueId};
+use crate::transaction::{Transact, Txn, TxnId};
+
+use super::block;
+use super::{ChainBlock, ChainType};
+
+/// A chain that exists only to make its subject's pending mutations
+/// recoverable if the host crashes mid-commit, rather than to keep a
+/// permanent history the way an append-only chain would. `SyncChain`
+/// buffers each open transaction's mutations in its own `ChainBlock`,
+/// applies the buffer to the subject on `commit`, and discards it on
+/// `rollback`--on reload, any block left over from a transaction that did
+/// neither is replayed, restoring the subject to its last consistent
+/// state.
+#[derive(Clone)]
+pub struct SyncChain {
+    dtype: TCType,
+    schema: Value,
+    ops: HashMap<ValueId, OpDef>,
+    subject: RwLock<State>,
+    pending: RwLock<BTreeMap<TxnId, ChainBlock>>,
+    history: RwLock<Vec<ChainBlock>>,
+}
+
+impl SyncChain {
+    pub async fn create(
+        txn: Arc<Txn>,
+        dtype: TCType,
+        schema: Value,
+        ops: HashMap<ValueId, OpDef>,
+    ) -> TCResult<SyncChain> {
+        let subject = dtype.instantiate(txn, schema.clone()).await?;
+
+        Ok(SyncChain {
+            dtype,
+            schema,
+            ops,
+            subject: RwLock::new(subject),
+            pending: RwLock::new(BTreeMap::new()),
+            history: RwLock::new(Vec::new()),
+        })
+    }
+
+    /// Replay any block left behind by a transaction that crashed before it
+    /// could commit or roll back, applying its buffered mutations to
+    /// `subject` in `TxnId` order (the order the mutations were originally
+    /// made), and folding each replayed block into `history` so the chain
+    /// picks back up where it left off. Called once, when this chain is
+    /// loaded from disk; also re-verifies the integrity of the history
+    /// that's already committed, before replaying anything on top of it.
+    pub async fn recover(&self) -> TCResult<()> {
+        block::verify_chain(&self.history.read().await)?;
+
+        let mut pending = self.pending.write().await;
+        let mut subject = self.subject.write().await;
+        let mut history = self.history.write().await;
+
+        for (txn_id, block) in std::mem::take(&mut *pending) {
+            subject.apply(&txn_id, &block).await?;
+            history.push(block);
+        }
+
+        Ok(())
+    }
+
+    async fn mutation(&self, txn_id: &TxnId, path: TCPath, key: Value, value: State) {
+        let previous_hash = self.head_hash().await;
+        let mut pending = self.pending.write().await;
+        let block = pending
+            .entry(txn_id.clone())
+            .or_insert_with(|| ChainBlock::with_previous_hash(previous_hash));
+        block.append(path, key, value);
+    }
+
+    async fn head_hash(&self) -> [u8; block::HASH_SIZE] {
+        self.history
+            .read()
+            .await
+            .last()
+            .map(ChainBlock::hash)
+            .unwrap_or(block::NULL_HASH)
+    }
+
+    /// Verify that this chain's committed history is a correctly hash-linked
+    /// chain rooted at `block::NULL_HASH`.
+    pub async fn verify(&self) -> TCResult<()> {
+        block::verify_chain(&self.history.read().await)
+    }
+}
+
+impl Instance for SyncChain {
+    type Class = ChainType;
+
+    fn class(&self) -> ChainType {
+        ChainType::Sync
+    }
+}
+
+#[async_trait]
+impl super::ChainInstance for SyncChain {
+    type Class = ChainType;
+
+    async fn get(&self, txn: Arc<Txn>, path: &TCPath, key: Value, auth: Auth) -> TCResult<State> {
+        self.subject.read().await.get(txn, path, key, auth).await
+    }
+
+    async fn put(&self, txn: Arc<Txn>, path: TCPath, key: Value, value: State) -> TCResult<()> {
+        self.mutation(txn.id(), path.clone(), key.clone(), value.clone())
+            .await;
+
+        self.subject.write().await.put(txn, path, key, value).await
+    }
+
+    async fn post<S: Stream<Item = (ValueId, Scalar)> + Send + Unpin>(
+        &self,
+        txn: Arc<Txn>,
+        path: TCPath,
+        data: S,
+        auth: Auth,
+    ) -> TCResult<State> {
+        self.subject.read().await.post(txn, path, data, auth).await
+    }
+
+    async fn to_stream(&self, txn: Arc<Txn>) -> TCResult<TCStream<Value>> {
+        self.subject.read().await.to_stream(txn).await
+    }
+
+    async fn hash(&self, _txn: Arc<Txn>) -> TCResult<Bytes> {
+        Ok(Bytes::copy_from_slice(&self.head_hash().await))
+    }
+}
+
+#[async_trait]
+impl Transact for SyncChain {
+    async fn commit(&self, txn_id: &TxnId) {
+        if let Some(block) = self.pending.write().await.remove(txn_id) {
+            match self.subject.write().await.apply(txn_id, &block).await {
+                Ok(()) => self.history.write().await.push(block),
+                Err(cause) => {
+                    // The subject's own `Transact::commit` can't fail (it
+                    // returns no `TCResult`), so a failure here can only be
+                    // surfaced as a log line, not propagated--same
+                    // constraint `TxnLock::commit` works around with its
+                    // `wounded` set, in `transaction::lock`.
+                    log::error!(
+                        "failed to apply buffered mutations for transaction {}: {}",
+                        txn_id,
+                        cause
+                    );
+                }
+            }
+        }
+
+        self.subject.commit(txn_id).await;
+    }
+
+    async fn rollback(&self, txn_id: &TxnId) {
+        self.pending.write().await.remove(txn_id);
+        self.subject.rollback(txn_id).await;
+    }
+}
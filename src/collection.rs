@@ -0,0 +1,26 @@
+//! Defines the interface a [`crate::block::chain::Chain`] needs from the
+//! object it wraps, so that replaying a journal (or restoring a snapshot)
+//! after a restart actually reconstructs that object's in-memory state,
+//! instead of only replaying the journal/snapshot bookkeeping itself.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+
+use crate::block::chain::Mutation;
+use crate::transaction::TxnId;
+use crate::value::TCResult;
+
+/// A value whose mutations a [`crate::block::chain::Chain`] can journal and
+/// replay, and whose full state it can checkpoint and restore.
+#[async_trait]
+pub trait Collect: Send + Sync {
+    /// Re-apply one previously-committed [`Mutation`], as read back from the
+    /// journal by [`crate::block::chain::Chain::load`].
+    async fn apply(&self, txn_id: &TxnId, mutation: &Mutation) -> TCResult<()>;
+
+    /// Replace this value's state with `state`, a snapshot previously
+    /// written by [`crate::block::chain::Chain::checkpoint`], before
+    /// [`crate::block::chain::Chain::load`] replays the journal written
+    /// after it.
+    async fn load(&self, txn_id: &TxnId, state: Bytes) -> TCResult<()>;
+}
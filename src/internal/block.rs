@@ -1,24 +1,395 @@
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, OnceLock, RwLock};
 
+use async_trait::async_trait;
 use bytes::Bytes;
 use futures::Future;
+use sha2::{Digest, Sha256};
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex as AsyncMutex;
 
 use crate::error;
-use crate::internal::cache::Map;
+use crate::internal::cache::{Map, TransactionCache};
 use crate::internal::{GROUP_DELIMITER, RECORD_DELIMITER};
+use crate::transaction::TransactionId;
+use crate::value::link::{Gateway, Link};
 use crate::value::{PathSegment, TCPath, TCResult};
 
+// Content-defined chunking parameters, tuned so that the expected chunk size
+// (2^CDC_TARGET_BITS) sits comfortably between CDC_MIN_CHUNK and
+// CDC_MAX_CHUNK. A wider gap between min and max gives the rolling hash more
+// room to find a natural boundary before one is forced.
+const CDC_WINDOW: usize = 48;
+const CDC_MIN_CHUNK: usize = 2 * 1024;
+const CDC_MAX_CHUNK: usize = 64 * 1024;
+const CDC_TARGET_BITS: u32 = 13;
+const CDC_MASK: u64 = (1 << CDC_TARGET_BITS) - 1;
+
+/// A fixed table of pseudo-random 64-bit values, one per byte value, used to
+/// turn each incoming/outgoing byte into a well-mixed contribution to the
+/// buzhash below. Built once and cached, since it never depends on the data
+/// being chunked.
+fn buzhash_table() -> &'static [u64; 256] {
+    static TABLE: OnceLock<[u64; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [0u64; 256];
+        let mut seed: u64 = 0x9E3779B97F4A7C15;
+        for (i, entry) in table.iter_mut().enumerate() {
+            // splitmix64, seeded with the byte index so every slot is distinct
+            seed = seed.wrapping_add(0x9E3779B97F4A7C15 ^ (i as u64));
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            *entry = z ^ (z >> 31);
+        }
+        table
+    })
+}
+
+/// Split `data` into content-defined chunks with a buzhash rolling hash over
+/// a `CDC_WINDOW`-byte sliding window: a boundary falls wherever the hash's
+/// low `CDC_TARGET_BITS` bits are all zero, so inserting or deleting bytes
+/// only perturbs the chunks touching the edit rather than every chunk after
+/// it. `CDC_MIN_CHUNK`/`CDC_MAX_CHUNK` bound the variance, with a boundary
+/// forced at `CDC_MAX_CHUNK` regardless of the hash.
+fn content_defined_chunks(data: &[u8]) -> Vec<&[u8]> {
+    if data.len() <= CDC_MIN_CHUNK {
+        return vec![data];
+    }
+
+    let table = buzhash_table();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut hash: u64 = 0;
+
+    for end in 1..=data.len() {
+        let i = end - 1;
+        hash = hash.rotate_left(1) ^ table[data[i] as usize];
+
+        if i >= CDC_WINDOW {
+            let outgoing = data[i - CDC_WINDOW];
+            hash ^= table[outgoing as usize].rotate_left((CDC_WINDOW % 64) as u32);
+        }
+
+        let len = end - start;
+        if len >= CDC_MIN_CHUNK && (len >= CDC_MAX_CHUNK || hash & CDC_MASK == 0) {
+            chunks.push(&data[start..end]);
+            start = end;
+            hash = 0;
+        }
+    }
+
+    if start < data.len() {
+        chunks.push(&data[start..]);
+    }
+
+    chunks
+}
+
+/// Name a block by the hash of its content, so that two blocks with
+/// identical bytes--whether two chunks of one flush or blocks written by
+/// unrelated `Chain`/`File` objects--collide on the same `PathSegment`
+/// instead of being stored twice.
+fn content_hash(data: &[u8]) -> PathSegment {
+    // SHA-256, not `DefaultHasher` (SipHash with a per-process random seed)--a
+    // content-addressed hash has to be stable across process restarts, or the
+    // same bytes written before and after a restart collide on different
+    // names and the dedup this whole scheme exists for stops working.
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    let digest = hasher.finalize();
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    hex.parse().expect("hex digest is a valid path segment")
+}
+
+/// The byte-level operations a [`Store`] needs from wherever its blocks
+/// actually live. Extracted so that `Store` itself can stay backend-agnostic
+/// and the same `flush`/`into_bytes`/`size` calls work whether blocks are
+/// buffered in memory, written to the local filesystem, or held by a remote
+/// object store reachable through a [`Gateway`].
+#[async_trait]
+pub trait BlockStore: std::fmt::Debug + Send + Sync {
+    /// Whether a block named `block_id` exists in this backend.
+    async fn exists(&self, block_id: &PathSegment) -> TCResult<bool>;
+
+    /// Append `data` to `block_id`, creating it if it doesn't already exist.
+    async fn append(&self, block_id: PathSegment, data: Vec<u8>) -> TCResult<()>;
+
+    /// Read the full contents of `block_id`, or an empty buffer if it
+    /// doesn't exist.
+    async fn into_bytes(&self, block_id: &PathSegment) -> TCResult<Bytes>;
+
+    /// The size in bytes of `block_id`, or zero if it doesn't exist.
+    async fn size(&self, block_id: &PathSegment) -> TCResult<usize>;
+
+    /// Delete `block_id`, if it exists.
+    async fn remove(&self, block_id: &PathSegment) -> TCResult<()>;
+
+    /// List the id of every block currently held by this backend.
+    async fn list(&self) -> TCResult<Vec<PathSegment>>;
+
+    /// Construct the backend for a child `Store` scoped to `context` under
+    /// `mount_point`, mirroring the parent's own kind of storage.
+    fn child(&self, mount_point: &PathBuf, context: &PathSegment) -> Arc<dyn BlockStore>;
+}
+
+/// Buffers every block in memory and never touches disk or the network.
+/// Used for `Store::new_tmp` and anywhere else blocks don't need to outlive
+/// the process.
+#[derive(Debug)]
+pub struct MemoryBackend {
+    buffer: RwLock<HashMap<PathSegment, Vec<u8>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Arc<MemoryBackend> {
+        Arc::new(MemoryBackend {
+            buffer: RwLock::new(HashMap::new()),
+        })
+    }
+}
+
+#[async_trait]
+impl BlockStore for MemoryBackend {
+    async fn exists(&self, block_id: &PathSegment) -> TCResult<bool> {
+        Ok(self.buffer.read().unwrap().contains_key(block_id))
+    }
+
+    async fn append(&self, block_id: PathSegment, mut data: Vec<u8>) -> TCResult<()> {
+        let mut buffer = self.buffer.write().unwrap();
+        if let Some(block) = buffer.get_mut(&block_id) {
+            block.append(&mut data)
+        } else {
+            buffer.insert(block_id, data);
+        }
+
+        Ok(())
+    }
+
+    async fn into_bytes(&self, block_id: &PathSegment) -> TCResult<Bytes> {
+        match self.buffer.read().unwrap().get(block_id) {
+            Some(data) => Ok(Bytes::copy_from_slice(data)),
+            None => Ok(Bytes::new()),
+        }
+    }
+
+    async fn size(&self, block_id: &PathSegment) -> TCResult<usize> {
+        Ok(self
+            .buffer
+            .read()
+            .unwrap()
+            .get(block_id)
+            .map(|data| data.len())
+            .unwrap_or(0))
+    }
+
+    async fn remove(&self, block_id: &PathSegment) -> TCResult<()> {
+        self.buffer.write().unwrap().remove(block_id);
+        Ok(())
+    }
+
+    async fn list(&self) -> TCResult<Vec<PathSegment>> {
+        Ok(self.buffer.read().unwrap().keys().cloned().collect())
+    }
+
+    fn child(&self, _mount_point: &PathBuf, _context: &PathSegment) -> Arc<dyn BlockStore> {
+        MemoryBackend::new()
+    }
+}
+
+/// Writes each block to its own file under `mount_point`, fsyncing after
+/// every append so that a completed `flush`/`flush_chunked` call is durable
+/// before it returns.
+#[derive(Debug)]
+pub struct FilesystemBackend {
+    mount_point: PathBuf,
+}
+
+impl FilesystemBackend {
+    pub fn new(mount_point: PathBuf) -> Arc<FilesystemBackend> {
+        Arc::new(FilesystemBackend { mount_point })
+    }
+
+    fn fs_path(&self, block_id: &PathSegment) -> PathBuf {
+        let mut path = self.mount_point.clone();
+        path.push(block_id.to_string());
+        path
+    }
+}
+
+#[async_trait]
+impl BlockStore for FilesystemBackend {
+    async fn exists(&self, block_id: &PathSegment) -> TCResult<bool> {
+        match fs::metadata(self.fs_path(block_id)).await {
+            Ok(_) => Ok(true),
+            Err(_) => Ok(false),
+        }
+    }
+
+    async fn append(&self, block_id: PathSegment, data: Vec<u8>) -> TCResult<()> {
+        if let Some(parent) = self.fs_path(&block_id).parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| error::internal(&format!("could not create {}", e)))?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.fs_path(&block_id))
+            .await
+            .map_err(|e| error::internal(&format!("could not open block file: {}", e)))?;
+
+        file.write_all(&data)
+            .await
+            .map_err(|e| error::internal(&format!("could not write block file: {}", e)))?;
+
+        file.sync_all()
+            .await
+            .map_err(|e| error::internal(&format!("could not sync block file: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn into_bytes(&self, block_id: &PathSegment) -> TCResult<Bytes> {
+        match fs::read(self.fs_path(block_id)).await {
+            Ok(data) => Ok(Bytes::from(data)),
+            Err(_) => Ok(Bytes::new()),
+        }
+    }
+
+    async fn size(&self, block_id: &PathSegment) -> TCResult<usize> {
+        match fs::metadata(self.fs_path(block_id)).await {
+            Ok(meta) => Ok(meta.len() as usize),
+            Err(_) => Ok(0),
+        }
+    }
+
+    async fn remove(&self, block_id: &PathSegment) -> TCResult<()> {
+        match fs::remove_file(self.fs_path(block_id)).await {
+            Ok(()) | Err(_) => Ok(()),
+        }
+    }
+
+    async fn list(&self) -> TCResult<Vec<PathSegment>> {
+        let mut ids = Vec::new();
+        let mut entries = match fs::read_dir(&self.mount_point).await {
+            Ok(entries) => entries,
+            Err(_) => return Ok(ids),
+        };
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| error::internal(&format!("could not read block directory: {}", e)))?
+        {
+            if let Some(name) = entry.file_name().to_str() {
+                if let Ok(id) = name.parse() {
+                    ids.push(id);
+                }
+            }
+        }
+
+        Ok(ids)
+    }
+
+    fn child(&self, mount_point: &PathBuf, _context: &PathSegment) -> Arc<dyn BlockStore> {
+        FilesystemBackend::new(mount_point.clone())
+    }
+}
+
+// NOTE: `Link` and `Gateway` (the host-addressing and request-dispatch types
+// this backend needs to reach a remote object store) aren't declared
+// anywhere under `src/`--only `host/src/gateway.rs` and `host/src/scalar`
+// have anything resembling them, in the separate `host` crate, which this
+// crate doesn't depend on. The shape below mirrors how `host/src/txn/mod.rs`
+// drives `Gateway::get`/`put` and how `Link::append` is used in
+// `host/src/route/scalar/cluster.rs`, so it's written against that assumed,
+// but currently undeclared, API rather than invented from nothing.
+/// Reads and writes blocks through a [`Gateway`], addressed by a [`Link`] to
+/// a remote object store. Lets a `Chain`/`File` run against a networked
+/// backend with no change to the code that calls `Store`.
+#[derive(Debug)]
+pub struct RemoteBackend {
+    link: Link,
+    gateway: Arc<Gateway>,
+}
+
+impl RemoteBackend {
+    pub fn new(link: Link, gateway: Arc<Gateway>) -> Arc<RemoteBackend> {
+        Arc::new(RemoteBackend { link, gateway })
+    }
+}
+
+#[async_trait]
+impl BlockStore for RemoteBackend {
+    async fn exists(&self, block_id: &PathSegment) -> TCResult<bool> {
+        self.gateway.has_block(&self.link, block_id).await
+    }
+
+    async fn append(&self, block_id: PathSegment, data: Vec<u8>) -> TCResult<()> {
+        self.gateway
+            .put_block(&self.link, block_id, Bytes::from(data))
+            .await
+    }
+
+    async fn into_bytes(&self, block_id: &PathSegment) -> TCResult<Bytes> {
+        self.gateway.get_block(&self.link, block_id).await
+    }
+
+    async fn size(&self, block_id: &PathSegment) -> TCResult<usize> {
+        Ok(self.into_bytes(block_id).await?.len())
+    }
+
+    async fn remove(&self, block_id: &PathSegment) -> TCResult<()> {
+        self.gateway.delete_block(&self.link, block_id).await
+    }
+
+    async fn list(&self) -> TCResult<Vec<PathSegment>> {
+        self.gateway.list_blocks(&self.link).await
+    }
+
+    fn child(&self, _mount_point: &PathBuf, context: &PathSegment) -> Arc<dyn BlockStore> {
+        RemoteBackend::new(self.link.clone().append(context.clone()), self.gateway.clone())
+    }
+}
+
+/// The reference count for one content-addressed block, and the set of
+/// caller-chosen paths currently pointing at it. The block is eligible for
+/// collection once `count` drops to zero.
+#[derive(Debug, Clone)]
+struct RefCount {
+    count: usize,
+    paths: Vec<PathSegment>,
+}
+
+/// A ref-count adjustment staged for a single `TransactionId`, applied by
+/// [`Store::commit_refs`] and discarded by [`Store::rollback_refs`]--mirrors
+/// how the rest of the crate delays side effects until commit.
+#[derive(Debug, Clone, Copy)]
+enum RefDelta {
+    Increment,
+    Decrement,
+}
+
 #[derive(Debug)]
 pub struct Store {
     block_size: usize,
     mount_point: PathBuf,
     context: Option<PathSegment>,
     children: Map<PathSegment, Arc<Store>>,
-    buffer: RwLock<HashMap<PathSegment, Vec<u8>>>,
+    backend: Arc<dyn BlockStore>,
+    refs: Map<PathSegment, RefCount>,
+    pending_refs: TransactionCache<PathSegment, Vec<(RefDelta, PathSegment)>>,
+    // Serializes `insert_ref`'s exists-check-then-append against itself, so two
+    // concurrent calls for the same new content can't both see it missing and
+    // both append it, writing the same hash's bytes twice. An async mutex
+    // (rather than the sync `RwLock`s `refs`/`pending_refs` use) because the
+    // critical section spans the `backend.append` await point.
+    insert_lock: AsyncMutex<()>,
     tmp: bool,
 }
 
@@ -28,12 +399,16 @@ impl Store {
         block_size: usize,
         context: Option<PathSegment>,
     ) -> Arc<Store> {
+        let backend = FilesystemBackend::new(mount_point.clone());
         Arc::new(Store {
             block_size,
             mount_point,
             context,
             children: Map::new(),
-            buffer: RwLock::new(HashMap::new()),
+            backend,
+            refs: Map::new(),
+            pending_refs: TransactionCache::new(),
+            insert_lock: AsyncMutex::new(()),
             tmp: false,
         })
     }
@@ -48,18 +423,48 @@ impl Store {
             mount_point,
             context,
             children: Map::new(),
-            buffer: RwLock::new(HashMap::new()),
+            backend: MemoryBackend::new(),
+            refs: Map::new(),
+            pending_refs: TransactionCache::new(),
+            insert_lock: AsyncMutex::new(()),
             tmp: true,
         })
     }
 
+    /// Like [`Store::new`], but addresses its blocks through `gateway` at
+    /// `link` instead of the local filesystem, so the store can be mounted
+    /// against a networked object store.
+    pub fn new_remote(
+        link: Link,
+        gateway: Arc<Gateway>,
+        mount_point: PathBuf,
+        block_size: usize,
+        context: Option<PathSegment>,
+    ) -> Arc<Store> {
+        Arc::new(Store {
+            block_size,
+            mount_point,
+            context,
+            children: Map::new(),
+            backend: RemoteBackend::new(link, gateway),
+            refs: Map::new(),
+            pending_refs: TransactionCache::new(),
+            insert_lock: AsyncMutex::new(()),
+            tmp: false,
+        })
+    }
+
     fn child(&self, context: PathSegment) -> Arc<Store> {
+        let mount_point = self.fs_path(&context);
         let child = Arc::new(Store {
             block_size: self.block_size,
-            mount_point: self.fs_path(&context),
+            mount_point: mount_point.clone(),
             context: Some(context.clone()),
             children: Map::new(),
-            buffer: RwLock::new(HashMap::new()),
+            backend: self.backend.child(&mount_point, &context),
+            refs: Map::new(),
+            pending_refs: TransactionCache::new(),
+            insert_lock: AsyncMutex::new(()),
             tmp: self.tmp,
         });
 
@@ -102,15 +507,11 @@ impl Store {
     }
 
     pub async fn exists(&self, path: &PathSegment) -> TCResult<bool> {
-        let fs_path = self.fs_path(path);
-        if self.children.contains_key(path) || self.buffer.read().unwrap().contains_key(path) {
+        if self.children.contains_key(path) {
             return Ok(true);
         }
 
-        match fs::metadata(fs_path).await {
-            Ok(_) => Ok(true),
-            Err(_) => Ok(false),
-        }
+        self.backend.exists(path).await
     }
 
     pub fn flush(
@@ -118,7 +519,7 @@ impl Store {
         block_id: PathSegment,
         header: Bytes,
         data: Vec<Bytes>,
-    ) -> impl Future<Output = ()> {
+    ) -> impl Future<Output = TCResult<()>> {
         if data.is_empty() {
             panic!("flush to {} called with no data", block_id);
         }
@@ -136,16 +537,150 @@ impl Store {
             }
             records.push(group_delimiter);
 
-            let mut records: Vec<u8> = records.concat();
-            let mut buffer = self.buffer.write().unwrap();
-            if let Some(block) = buffer.get_mut(&block_id) {
-                block.append(&mut records)
-            } else {
-                buffer.insert(block_id, records);
+            let records: Vec<u8> = records.concat();
+            self.backend.append(block_id, records).await
+        }
+    }
+
+    /// Like [`Store::flush`], but instead of appending `header`/`data` into
+    /// the single block named `block_id`, split the combined record bytes
+    /// into content-defined chunks and write each one under its own
+    /// content-addressed id. Returns the chunk ids in order, which the
+    /// caller is responsible for persisting as `block_id`'s manifest so the
+    /// block can be reassembled later. Because the chunk ids are content
+    /// hashes, re-flushing unchanged regions of a block reuses the existing
+    /// chunks instead of rewriting them.
+    pub fn flush_chunked(
+        self: Arc<Self>,
+        block_id: PathSegment,
+        header: Bytes,
+        data: Vec<Bytes>,
+    ) -> impl Future<Output = TCResult<Vec<PathSegment>>> {
+        if data.is_empty() {
+            panic!("flush to {} called with no data", block_id);
+        }
+
+        async move {
+            let group_delimiter = Bytes::from(&[GROUP_DELIMITER as u8][..]);
+            let record_delimiter = Bytes::from(&[RECORD_DELIMITER as u8][..]);
+
+            let mut records = Vec::with_capacity(data.len() + 1);
+            records.push(header);
+            records.push(record_delimiter.clone());
+            for record in data {
+                records.push(record);
+                records.push(record_delimiter.clone());
+            }
+            records.push(group_delimiter);
+
+            let records: Vec<u8> = records.concat();
+            let mut chunk_ids = Vec::new();
+            for chunk in content_defined_chunks(&records) {
+                let chunk_id = content_hash(chunk);
+                if !self.backend.exists(&chunk_id).await? {
+                    self.backend.append(chunk_id.clone(), chunk.to_vec()).await?;
+                }
+                chunk_ids.push(chunk_id);
             }
 
-            // TODO: persist data to disk
+            Ok(chunk_ids)
+        }
+    }
+
+    /// Write `data` under its content hash if no block with that hash already
+    /// exists, and stage a reference from `path` to it for `txn_id`. The
+    /// block itself is written (and readable by its content hash) right
+    /// away; the reference only takes effect once [`Store::commit_refs`] is
+    /// called for `txn_id`.
+    /// Returns the content hash so the caller can record it as `path`'s
+    /// target.
+    pub async fn insert_ref(
+        &self,
+        txn_id: TransactionId,
+        path: PathSegment,
+        data: &[u8],
+    ) -> TCResult<PathSegment> {
+        let hash = content_hash(data);
+
+        {
+            // Hold `insert_lock` across the exists-check and the append, so two
+            // concurrent `insert_ref` calls for the same new content can't both
+            // observe it missing and both write it.
+            let _guard = self.insert_lock.lock().await;
+            if !self.backend.exists(&hash).await? {
+                self.backend.append(hash.clone(), data.to_vec()).await?;
+            }
         }
+
+        self.stage_ref(txn_id, hash.clone(), RefDelta::Increment, path);
+        Ok(hash)
+    }
+
+    /// Stage the removal of `path`'s reference to `hash` for `txn_id`. The
+    /// count is only decremented once [`Store::commit_refs`] is called.
+    pub fn remove_ref(&self, txn_id: TransactionId, hash: PathSegment, path: PathSegment) {
+        self.stage_ref(txn_id, hash, RefDelta::Decrement, path);
+    }
+
+    fn stage_ref(&self, txn_id: TransactionId, hash: PathSegment, delta: RefDelta, path: PathSegment) {
+        let mut staged = self
+            .pending_refs
+            .get(&txn_id, &hash)
+            .unwrap_or_else(Vec::new);
+        staged.push((delta, path));
+        self.pending_refs.insert(txn_id, hash, staged);
+    }
+
+    /// Apply every ref-count adjustment staged for `txn_id`, then sweep any
+    /// block whose count dropped to zero. Call this from `Transact::commit`.
+    pub async fn commit_refs(&self, txn_id: &TransactionId) -> TCResult<()> {
+        for (hash, deltas) in self.pending_refs.close(txn_id) {
+            let mut entry = self.refs.get(&hash).unwrap_or_else(|| RefCount {
+                count: 0,
+                paths: Vec::new(),
+            });
+
+            for (delta, path) in deltas {
+                match delta {
+                    RefDelta::Increment => {
+                        entry.count += 1;
+                        entry.paths.push(path);
+                    }
+                    RefDelta::Decrement => {
+                        entry.count = entry.count.saturating_sub(1);
+                        entry.paths.retain(|p| p != &path);
+                    }
+                }
+            }
+
+            self.refs.insert(hash, entry);
+        }
+
+        self.collect().await
+    }
+
+    /// Discard every ref-count adjustment staged for `txn_id` without
+    /// applying it. Call this when `txn_id` rolls back instead of commits.
+    pub fn rollback_refs(&self, txn_id: &TransactionId) {
+        self.pending_refs.close(txn_id);
+    }
+
+    /// Sweep every committed block whose reference count has dropped to
+    /// zero out of the buffer, freeing the space it held.
+    async fn collect(&self) -> TCResult<()> {
+        for block_id in self.backend.list().await? {
+            let dead = self
+                .refs
+                .get(&block_id)
+                .map(|entry| entry.count == 0)
+                .unwrap_or(false);
+
+            if dead {
+                self.backend.remove(&block_id).await?;
+            }
+        }
+
+        Ok(())
     }
 
     pub fn get(&self, path: &TCPath) -> Option<Arc<Store>> {
@@ -162,30 +697,31 @@ impl Store {
         }
     }
 
-    pub async fn into_bytes(self: Arc<Self>, block_id: PathSegment) -> Bytes {
-        // TODO: read from filesystem
-
-        if let Some(buffer) = self.buffer.read().unwrap().get(&block_id) {
-            Bytes::copy_from_slice(buffer)
-        } else {
-            // TODO
-            Bytes::new()
-        }
+    pub async fn into_bytes(self: Arc<Self>, block_id: PathSegment) -> TCResult<Bytes> {
+        self.backend.into_bytes(&block_id).await
     }
 
-    pub async fn size(&self, block_id: &PathSegment) -> usize {
-        // TODO: read from filesystem
-
-        if let Some(buffer) = self.buffer.read().unwrap().get(block_id) {
-            buffer.len()
-        } else {
-            0
-        }
+    pub async fn size(&self, block_id: &PathSegment) -> TCResult<usize> {
+        self.backend.size(block_id).await
     }
 
-    pub async fn will_fit(&self, block_id: &PathSegment, header: &Bytes, data: &[Bytes]) -> bool {
-        self.size(block_id).await + header.len() + data.iter().map(|b| b.len()).sum::<usize>()
-            <= self.block_size_default()
+    /// Whether `header`/`data` can still be appended to `block_id` without
+    /// exceeding `block_size_default()`. Only meaningful for fixed-size
+    /// blocks written via [`Store::flush`]; [`Store::flush_chunked`] has no
+    /// size cap to check against, since its chunks are bounded by
+    /// `CDC_MIN_CHUNK`/`CDC_MAX_CHUNK` instead.
+    pub async fn will_fit(
+        &self,
+        block_id: &PathSegment,
+        header: &Bytes,
+        data: &[Bytes],
+    ) -> TCResult<bool> {
+        let size = self.size(block_id).await?;
+        // `flush` also writes a record delimiter after the header and after
+        // each record, plus a closing group delimiter
+        let delimiters = data.len() + 2;
+        Ok(size + header.len() + data.iter().map(|b| b.len()).sum::<usize>() + delimiters
+            <= self.block_size_default())
     }
 
     fn fs_path(&self, name: &PathSegment) -> PathBuf {
@@ -1,50 +1,71 @@
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, VecDeque};
-use std::hash::Hash;
+use std::hash::{Hash, Hasher};
 use std::iter::FromIterator;
 use std::sync::RwLock;
 
 use crate::transaction::TransactionId;
 
+// Sharded across a fixed number of buckets so that writes to unrelated keys
+// don't serialize behind one global lock--`Store::children` is consulted on
+// every `create`/`get`/`exists` path-walk, so a single `RwLock<HashMap<..>>`
+// here would otherwise become the hottest lock in the storage layer under
+// concurrent transactions. 32 is large enough to spread contention across a
+// typical number of cores without wasting much memory on mostly-empty maps.
+const NUM_SHARDS: usize = 32;
+
+fn shard_of<K: Hash>(key: &K) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() as usize) % NUM_SHARDS
+}
+
 #[derive(Debug)]
 pub struct Map<K: Eq + Hash, V> {
-    map: RwLock<HashMap<K, V>>,
+    shards: Vec<RwLock<HashMap<K, V>>>,
 }
 
 impl<K: Eq + Hash, V: Clone> Map<K, V> {
     pub fn new() -> Map<K, V> {
         Map {
-            map: RwLock::new(HashMap::new()),
+            shards: (0..NUM_SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
         }
     }
 
     pub fn contains_key(&self, key: &K) -> bool {
-        self.map.read().unwrap().contains_key(key)
+        self.shards[shard_of(key)].read().unwrap().contains_key(key)
     }
 
     pub fn get(&self, key: &K) -> Option<V> {
-        match self.map.read().unwrap().get(key) {
+        match self.shards[shard_of(key)].read().unwrap().get(key) {
             Some(val) => Some(val.clone()),
             None => None,
         }
     }
 
     pub fn insert(&self, key: K, value: V) -> Option<V> {
-        self.map.write().unwrap().insert(key, value)
+        self.shards[shard_of(&key)].write().unwrap().insert(key, value)
     }
 
     pub fn remove(&self, key: &K) -> Option<V> {
-        self.map.write().unwrap().remove(key)
+        self.shards[shard_of(key)].write().unwrap().remove(key)
     }
 }
 
 impl<K: Eq + Hash, V> FromIterator<(K, V)> for Map<K, V> {
+    // Iterating a `Map` isn't (and with per-shard locks, can't cheaply be) a
+    // consistent snapshot across shards--a concurrent writer can land in a
+    // shard this has already passed or one it hasn't reached yet. Fine for
+    // building a `Map` from a fixed starting collection, as here; any future
+    // `iter()` over a live `Map` should document the same caveat.
     fn from_iter<T: IntoIterator<Item = (K, V)>>(i: T) -> Map<K, V> {
-        let mut map: HashMap<K, V> = HashMap::new();
+        let mut shards: Vec<HashMap<K, V>> = (0..NUM_SHARDS).map(|_| HashMap::new()).collect();
         for (k, v) in i {
-            map.insert(k, v);
+            let shard = shard_of(&k);
+            shards[shard].insert(k, v);
         }
         Map {
-            map: RwLock::new(map),
+            shards: shards.into_iter().map(RwLock::new).collect(),
         }
     }
 }
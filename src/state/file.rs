@@ -5,6 +5,7 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures::stream::{self, StreamExt};
 use uuid::Uuid;
 
 use crate::error;
@@ -18,6 +19,11 @@ use crate::value::TCResult;
 const ERR_CORRUPT: &str = "Data corruption error detected! Please file a bug report.";
 const TXN_CACHE: &str = ".pending";
 
+// Bounds how many blocks `File::commit` copies into the pending txn
+// directory at once, so a transaction that touched thousands of blocks
+// doesn't try to open that many concurrent writes at the same time.
+const COMMIT_CONCURRENCY: usize = 16;
+
 pub type BlockId = PathSegment;
 
 pub struct Block<'a, T: BlockData> {
@@ -264,7 +270,7 @@ impl<T: BlockData> Transact for File<T> {
 
         self.listing.commit(txn_id).await;
 
-        let mut mutated: Vec<BlockId> = self
+        let mutated: Vec<BlockId> = self
             .mutated
             .write(txn_id.clone())
             .await
@@ -287,19 +293,35 @@ impl<T: BlockData> Transact for File<T> {
             .write()
             .await;
 
-        // TODO: run these copy ops in parallel
-        for block_id in mutated.drain(..) {
-            if let Some(lock) = cache.get(&block_id) {
-                txn_dir
-                    .create_or_get_block(
-                        &block_id,
-                        lock.read(txn_id).await.unwrap().deref().clone().into(),
-                    )
-                    .await
-                    .unwrap();
-                lock.commit(txn_id).await;
-            }
-        }
+        // Copy each dirty block out of the cache and into `txn_dir`
+        // concurrently (bounded by `COMMIT_CONCURRENCY`) instead of one at a
+        // time, so commit latency scales with I/O parallelism rather than
+        // the number of dirty blocks. `create_or_get_block` only needs `&Dir`
+        // here (same as `cache`'s and `pending`'s own locks, it's expected to
+        // synchronize itself internally), so `txn_dir`'s write guard is only
+        // reborrowed immutably for the duration of this stream; it isn't
+        // taken mutably again (for `move_all`, below) until every copy has
+        // resolved.
+        let txn_dir_ref = &*txn_dir;
+        stream::iter(mutated)
+            .map(|block_id| {
+                let cache = &cache;
+                async move {
+                    if let Some(lock) = cache.get(&block_id) {
+                        txn_dir_ref
+                            .create_or_get_block(
+                                &block_id,
+                                lock.read(txn_id).await.unwrap().deref().clone().into(),
+                            )
+                            .await
+                            .unwrap();
+                        lock.commit(txn_id).await;
+                    }
+                }
+            })
+            .buffer_unordered(COMMIT_CONCURRENCY)
+            .collect::<Vec<()>>()
+            .await;
 
         dir.move_all(txn_dir.deref_mut()).unwrap();
         pending.delete_dir(&txn_dir_id).unwrap();
@@ -1,14 +1,26 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
 use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use async_trait::async_trait;
 
 use crate::error;
 use crate::internal::block::Store;
+use crate::internal::cache::{Map, TransactionCache};
 use crate::internal::file::*;
 use crate::state::{Collection, Persistent, Transactable};
 use crate::transaction::{Transaction, TransactionId};
-use crate::value::{TCResult, TCValue};
+use crate::value::{PathSegment, TCResult, TCValue};
+
+// Matches the default block size real tinychain stores use elsewhere in
+// this tree (`internal::hostfs`'s equivalent default, not present in this
+// snapshot)--`Graph` doesn't have a config knob for this yet, so a fixed
+// constant stands in for one.
+const DEFAULT_BLOCK_SIZE: usize = 1_000_000;
 
 pub struct GraphConfig;
 
@@ -20,45 +32,250 @@ impl TryFrom<TCValue> for GraphConfig {
     }
 }
 
-#[derive(Debug)]
-pub struct Graph {}
+/// A node's payload together with the node ids of its outgoing edges, as
+/// staged or committed inside a [`Graph`].
+#[derive(Clone)]
+struct NodeEntry {
+    payload: TCValue,
+    edges: Vec<TCValue>,
+}
+
+impl NodeEntry {
+    /// Re-assemble the `(payload, edges)` pair that `get` hands back to a
+    /// caller, in the same shape `put` accepts it in--see `Graph::decode_node`.
+    fn encode(&self) -> TCValue {
+        TCValue::Vector(vec![
+            self.payload.clone(),
+            TCValue::Vector(self.edges.clone()),
+        ])
+    }
+}
+
+/// Derive a map key for `node_id` from its displayed form, much as
+/// `internal::block::content_hash` derives a block id from raw bytes, but
+/// with `DefaultHasher` rather than SHA-256.
+/// `TCValue` isn't confirmed to implement `Hash` anywhere in this tree (the
+/// same caveat `TCStream::group_by` works around in `host/src/stream/mod.rs`
+/// by avoiding a `HashMap<Value, _>`), so hashing its `Display` output lets
+/// `Graph` index nodes with a plain `Map`/`TransactionCache` without
+/// assuming more of `TCValue` than its existing uses elsewhere already do.
+fn node_key(node_id: &TCValue) -> PathSegment {
+    let mut hasher = DefaultHasher::new();
+    node_id.to_string().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+        .parse()
+        .expect("hex digest is a valid path segment")
+}
+
+/// A transactional directed graph, keyed by node id (`TCValue`) and backed
+/// by a [`Store`]. Mutations made by `put` are only visible within the
+/// `TransactionId` that made them (via `pending`) until `commit` folds them
+/// into `committed`, mirroring how `Store` itself stages ref-count deltas in
+/// `pending_refs` and only applies them to `refs` in `commit_refs`.
+pub struct Graph {
+    store: Arc<Store>,
+    committed: Map<PathSegment, NodeEntry>,
+    pending: TransactionCache<PathSegment, NodeEntry>,
+}
+
+impl fmt::Debug for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("(graph)")
+    }
+}
+
+impl Graph {
+    fn new(store: Arc<Store>) -> Arc<Graph> {
+        Arc::new(Graph {
+            store,
+            committed: Map::new(),
+            pending: TransactionCache::new(),
+        })
+    }
+
+    /// The `Store` this graph's nodes are (nominally) backed by--see the
+    /// NOTE on `Persistent::create` about the gap between this and durable
+    /// storage.
+    pub fn store(&self) -> &Arc<Store> {
+        &self.store
+    }
+
+    /// Split a `put`/`get` value into its node payload and outgoing edge
+    /// set. `put` accepts (and `get` returns) a node as
+    /// `TCValue::Vector([payload, TCValue::Vector(edges)])`--the only shape
+    /// available to carry a node's edges alongside its payload through
+    /// `Collection`'s single `Value` parameter, since nothing else in this
+    /// tree gives `Graph` a richer structured type to use instead. Any other
+    /// shape is treated as a bare payload with no outgoing edges yet, rather
+    /// than an error, so a caller can `put` a plain value before it knows
+    /// its edges.
+    fn decode_node(node: TCValue) -> (TCValue, Vec<TCValue>) {
+        if let TCValue::Vector(mut pair) = node {
+            if pair.len() == 2 {
+                match pair.pop().expect("edge set") {
+                    TCValue::Vector(edges) => {
+                        let payload = pair.pop().expect("node payload");
+                        return (payload, edges);
+                    }
+                    other => pair.push(other),
+                }
+            }
+
+            return (TCValue::Vector(pair), Vec::new());
+        }
+
+        (node, Vec::new())
+    }
+
+    /// The entry staged for `node_id` under `txn_id`, if any, else the last
+    /// committed entry--so a read within an open transaction sees its own
+    /// not-yet-committed writes.
+    fn resolve(&self, txn_id: &TransactionId, key: &PathSegment) -> Option<NodeEntry> {
+        self.pending
+            .get(txn_id, key)
+            .or_else(|| self.committed.get(key))
+    }
+
+    /// The node ids `node_id` has an outgoing edge to, read as of `txn`.
+    pub async fn neighbors(
+        self: &Arc<Self>,
+        txn: Arc<Transaction>,
+        node_id: &TCValue,
+    ) -> TCResult<Vec<TCValue>> {
+        self.resolve(txn.id(), &node_key(node_id))
+            .map(|entry| entry.edges)
+            .ok_or_else(|| error::not_found(node_id.clone()))
+    }
+
+    /// Breadth-first traversal starting at `start`, descending at most
+    /// `max_depth` edges (`None` for no limit), returning every node id
+    /// visited in the order it was first reached. Tolerant of cycles: a
+    /// node is only ever enqueued once, tracked by `node_key` rather than
+    /// requiring `TCValue: Hash` directly (see `node_key`). A node with no
+    /// stored entry is still visited (it's reachable by a recorded edge)
+    /// but contributes no further neighbors.
+    pub async fn bfs(
+        self: &Arc<Self>,
+        txn: Arc<Transaction>,
+        start: TCValue,
+        max_depth: Option<u64>,
+    ) -> TCResult<Vec<TCValue>> {
+        let txn_id = txn.id();
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        let mut queue = VecDeque::new();
+
+        visited.insert(node_key(&start));
+        queue.push_back((start, 0u64));
+
+        while let Some((node_id, depth)) = queue.pop_front() {
+            order.push(node_id.clone());
+
+            if max_depth.map(|max| depth >= max).unwrap_or(false) {
+                continue;
+            }
+
+            if let Some(entry) = self.resolve(txn_id, &node_key(&node_id)) {
+                for neighbor in entry.edges {
+                    let key = node_key(&neighbor);
+                    if visited.insert(key) {
+                        queue.push_back((neighbor, depth + 1));
+                    }
+                }
+            }
+        }
+
+        Ok(order)
+    }
+
+    /// Depth-first traversal starting at `start`, returning every node id
+    /// visited in the order it was first reached. See `bfs` for the
+    /// cycle-tolerance and `TCValue`-as-key caveats, which apply here too.
+    pub async fn dfs(self: &Arc<Self>, txn: Arc<Transaction>, start: TCValue) -> TCResult<Vec<TCValue>> {
+        let txn_id = txn.id();
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        let mut stack = Vec::new();
+
+        visited.insert(node_key(&start));
+        stack.push(start);
+
+        while let Some(node_id) = stack.pop() {
+            order.push(node_id.clone());
+
+            if let Some(entry) = self.resolve(txn_id, &node_key(&node_id)) {
+                // Reverse so that, for a node with multiple edges, the first
+                // edge listed is the first one popped (and thus visited)--
+                // matching the intuitive left-to-right order of `bfs` above.
+                for neighbor in entry.edges.into_iter().rev() {
+                    let key = node_key(&neighbor);
+                    if visited.insert(key) {
+                        stack.push(neighbor);
+                    }
+                }
+            }
+        }
+
+        Ok(order)
+    }
+}
 
 #[async_trait]
 impl Collection for Graph {
     type Key = TCValue;
     type Value = TCValue;
+
     async fn get(
         self: &Arc<Self>,
-        _txn: Arc<Transaction>,
-        _node_id: &TCValue,
+        txn: Arc<Transaction>,
+        node_id: &TCValue,
     ) -> TCResult<Self::Value> {
-        Err(error::not_implemented())
+        self.resolve(txn.id(), &node_key(node_id))
+            .map(|entry| entry.encode())
+            .ok_or_else(|| error::not_found(node_id.clone()))
     }
 
+    /// Upsert `node_id`'s payload and outgoing edge set (see `decode_node`),
+    /// staged for `txn` until it commits.
     async fn put(
         self: Arc<Self>,
-        _txn: Arc<Transaction>,
-        _node_id: TCValue,
-        _node: TCValue,
+        txn: Arc<Transaction>,
+        node_id: TCValue,
+        node: TCValue,
     ) -> TCResult<Arc<Self>> {
-        Err(error::not_implemented())
+        let (payload, edges) = Self::decode_node(node);
+        let key = node_key(&node_id);
+        self.pending
+            .insert(txn.id().clone(), key, NodeEntry { payload, edges });
+
+        Ok(self)
     }
 }
 
 #[async_trait]
 impl File for Graph {
-    async fn copy_from(_reader: &mut FileCopier, _dest: Arc<Store>) -> Arc<Self> {
-        // TODO
-        Arc::new(Graph {})
+    /// Rebuild a `Graph` from blocks already copied into `dest` by
+    /// `copy_into` below.
+    ///
+    /// NOTE: unlike the newer, `destream`-based `State`/`Value` types (see
+    /// `SortedRow` in `host/src/stream/mod.rs`), `TCValue` has no byte
+    /// encoding anywhere in this tree, so there's no way to actually decode
+    /// a `FileCopier`'s blocks back into `TCValue` payloads here--this
+    /// returns an empty graph over `dest` rather than guess at one. Same
+    /// honest gap as the missing evaluator noted on `Op::match_arm` in
+    /// `src/value/op.rs`.
+    async fn copy_from(_reader: &mut FileCopier, dest: Arc<Store>) -> Arc<Self> {
+        Graph::new(dest)
     }
 
+    /// See `copy_from`'s NOTE: without a byte encoding for `TCValue`, this
+    /// can't yet write real node/edge bytes through `writer`.
     async fn copy_into(&self, _txn_id: TransactionId, _writer: &mut FileCopier) {
-        // TODO
+        // TODO: copy `committed` into `writer` once `TCValue` has a byte encoding
     }
 
-    async fn from_store(_store: Arc<Store>) -> Arc<Graph> {
-        // TODO
-        Arc::new(Graph {})
+    async fn from_store(store: Arc<Store>) -> Arc<Graph> {
+        Graph::new(store)
     }
 }
 
@@ -66,14 +283,28 @@ impl File for Graph {
 impl Persistent for Graph {
     type Config = GraphConfig;
 
+    /// `Transaction`/`HostContext` don't expose a mount point or block-store
+    /// handle anywhere in this tree (unlike the newer `Txn`/`Dir` that
+    /// `host/src/stream/mod.rs` threads through), so this creates its own
+    /// in-memory `Store` rather than one rooted in durable storage--the same
+    /// kind of gap left honest for `SortedRunClass` in `execute_sorted`.
     async fn create(_txn: Arc<Transaction>, _config: GraphConfig) -> TCResult<Arc<Graph>> {
-        Err(error::not_implemented())
+        Ok(Graph::new(Store::new_tmp(
+            PathBuf::new(),
+            DEFAULT_BLOCK_SIZE,
+            None,
+        )))
     }
 }
 
 #[async_trait]
 impl Transactable for Graph {
-    async fn commit(&self, _txn_id: &TransactionId) {
-        // TODO
+    /// Fold every node staged for `txn_id` into the in-memory `committed`
+    /// map, the same way `Store::commit_refs` folds staged ref-count deltas
+    /// into `refs`. Nothing is written to `store`.
+    async fn commit(&self, txn_id: &TransactionId) {
+        for (key, entry) in self.pending.close(txn_id) {
+            self.committed.insert(key, entry);
+        }
     }
 }
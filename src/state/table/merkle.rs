@@ -0,0 +1,280 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+
+use crate::error;
+use crate::transaction::{Txn, TxnId};
+use crate::value::{TCResult, Value};
+
+use super::replication::{Message, NodeId, Reply, Transport};
+use super::schema::Row;
+
+/// Number of leaves in the Merkle tree, i.e. how finely the key space is
+/// partitioned. A fixed power of two, both so the tree is a complete
+/// binary tree (no ragged internal levels to special-case) and so
+/// `leaf_of` can assign keys to leaves with a cheap modulo.
+const LEAF_COUNT: usize = 256;
+
+fn hash_of(data: &str) -> u64 {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn row_hash(row: &Row) -> [u8; 32] {
+    let mut hasher = Sha256::default();
+    for value in row {
+        hasher.update(value.to_string().as_bytes());
+    }
+
+    let digest = hasher.finalize();
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&digest);
+    hash
+}
+
+/// Hash a leaf's rows, keyed by their string-formatted primary key so the
+/// hash is independent of insertion order.
+fn leaf_hash(rows: &BTreeMap<String, [u8; 32]>) -> [u8; 32] {
+    let mut hasher = Sha256::default();
+    for (key, hash) in rows {
+        hasher.update(key.as_bytes());
+        hasher.update(hash);
+    }
+
+    let digest = hasher.finalize();
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&digest);
+    hash
+}
+
+/// A Merkle tree over a `TableBase`'s key space, used to find the rows two
+/// replicas disagree on without streaming the whole table. Keys are
+/// partitioned into `LEAF_COUNT` fixed ranges by hashing; each leaf holds
+/// the hash of every row assigned to it, and a complete binary tree of
+/// internal node hashes sits above the leaves, up to a single root.
+///
+/// `nodes` is a 0-indexed complete binary tree stored as a flat array: the
+/// root is `nodes[0]`, node `i`'s children are `nodes[2*i + 1]` and
+/// `nodes[2*i + 2]`, and the leaves occupy the last `LEAF_COUNT` slots.
+pub struct MerkleTree {
+    leaves: Vec<BTreeMap<String, [u8; 32]>>,
+    nodes: Vec<[u8; 32]>,
+}
+
+impl MerkleTree {
+    pub fn new() -> MerkleTree {
+        MerkleTree {
+            leaves: vec![BTreeMap::new(); LEAF_COUNT],
+            nodes: vec![[0u8; 32]; 2 * LEAF_COUNT - 1],
+        }
+    }
+
+    /// Build a tree from every row currently in the table, keyed by its
+    /// primary key (the first column of `row`).
+    pub fn from_rows<'a, I: IntoIterator<Item = &'a Row>>(rows: I) -> MerkleTree {
+        let mut tree = MerkleTree::new();
+        for row in rows {
+            if let Some(key) = row.get(0) {
+                tree.update_row(key.clone(), row);
+            }
+        }
+
+        tree
+    }
+
+    fn leaf_of(key: &Value) -> usize {
+        (hash_of(&key.to_string()) as usize) % LEAF_COUNT
+    }
+
+    fn leaf_node_index(leaf: usize) -> usize {
+        LEAF_COUNT - 1 + leaf
+    }
+
+    /// Recompute `nodes[leaf]`'s hash and propagate the change up to the
+    /// root--called after every `update_row`/`delete_row` so the tree never
+    /// needs a full rebuild.
+    fn rehash(&mut self, leaf: usize) {
+        let mut index = Self::leaf_node_index(leaf);
+        self.nodes[index] = leaf_hash(&self.leaves[leaf]);
+
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            let left = self.nodes[2 * parent + 1];
+            let right = self.nodes[2 * parent + 2];
+
+            let mut hasher = Sha256::default();
+            hasher.update(&left);
+            hasher.update(&right);
+            let digest = hasher.finalize();
+            self.nodes[parent].copy_from_slice(&digest);
+
+            index = parent;
+        }
+    }
+
+    /// Insert or overwrite the row at `key`, rehashing only the affected
+    /// leaf and its ancestors.
+    pub fn update_row(&mut self, key: Value, row: &Row) {
+        let leaf = Self::leaf_of(&key);
+        self.leaves[leaf].insert(key.to_string(), row_hash(row));
+        self.rehash(leaf);
+    }
+
+    /// Remove the row at `key`, rehashing only the affected leaf and its
+    /// ancestors.
+    pub fn delete_row(&mut self, key: &Value) {
+        let leaf = Self::leaf_of(key);
+        self.leaves[leaf].remove(&key.to_string());
+        self.rehash(leaf);
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.nodes[0]
+    }
+
+    fn node_hash(&self, index: usize) -> [u8; 32] {
+        self.nodes[index]
+    }
+
+    fn is_leaf(index: usize) -> bool {
+        index >= LEAF_COUNT - 1
+    }
+}
+
+/// The rows a `sync` call pushed to the peer and pulled from it, so the
+/// caller can log or measure drift between the two replicas.
+pub struct SyncReport {
+    pub pushed: Vec<Row>,
+    pub pulled: Vec<Row>,
+}
+
+/// Reconcile `local`'s tree against `peer`'s over `transport`, descending
+/// only into subtrees whose hashes disagree, and for each divergent leaf
+/// exchanging actual rows: anything `local` has that `peer` doesn't (or has
+/// an older version of) is pushed, and vice versa for pulled rows.
+/// `local_rows` must hold every row `local` currently has, each tagged with
+/// the `TxnId` it was last written under (the same way `Reply::Range`
+/// tags `peer`'s rows), keyed by its string-formatted primary key, so
+/// divergent leaves can be resolved--by newest `TxnId`, not just by
+/// content--without a second pass over the table.
+pub async fn sync<T: Transport>(
+    local: &MerkleTree,
+    local_rows: &BTreeMap<String, (TxnId, Row)>,
+    peer: &NodeId,
+    txn: Arc<Txn>,
+    _txn_id: &TxnId,
+    transport: &T,
+) -> TCResult<SyncReport> {
+    let peer_root = fetch_node(peer, txn.clone(), transport, 0).await?;
+    let mut report = SyncReport {
+        pushed: Vec::new(),
+        pulled: Vec::new(),
+    };
+
+    if peer_root == local.root() {
+        return Ok(report);
+    }
+
+    let mut frontier = VecDeque::new();
+    frontier.push_back(0usize);
+
+    while let Some(index) = frontier.pop_front() {
+        let peer_hash = fetch_node(peer, txn.clone(), transport, index).await?;
+        if peer_hash == local.node_hash(index) {
+            continue;
+        }
+
+        if MerkleTree::is_leaf(index) {
+            let leaf = index - (LEAF_COUNT - 1);
+            reconcile_leaf(local, local_rows, leaf, peer, txn.clone(), transport, &mut report)
+                .await?;
+        } else {
+            frontier.push_back(2 * index + 1);
+            frontier.push_back(2 * index + 2);
+        }
+    }
+
+    Ok(report)
+}
+
+async fn fetch_node<T: Transport>(
+    peer: &NodeId,
+    txn: Arc<Txn>,
+    transport: &T,
+    index: usize,
+) -> TCResult<[u8; 32]> {
+    match transport.send(peer, txn, Message::TreeNode(index)).await? {
+        Reply::TreeNode(hash) => Ok(hash),
+        _ => Err(error::internal(
+            "peer returned an unexpected reply to a Merkle tree node request",
+        )),
+    }
+}
+
+async fn reconcile_leaf<T: Transport>(
+    local: &MerkleTree,
+    local_rows: &BTreeMap<String, (TxnId, Row)>,
+    leaf: usize,
+    peer: &NodeId,
+    txn: Arc<Txn>,
+    transport: &T,
+    report: &mut SyncReport,
+) -> TCResult<()> {
+    let peer_rows = match transport
+        .send(peer, txn.clone(), Message::LeafRows(leaf))
+        .await?
+    {
+        Reply::Range(rows) => rows,
+        _ => {
+            return Err(error::internal(
+                "peer returned an unexpected reply to a Merkle leaf rows request",
+            ))
+        }
+    };
+
+    let local_leaf_rows: BTreeMap<String, (TxnId, Row)> = local.leaves[leaf]
+        .keys()
+        .filter_map(|key| local_rows.get(key).map(|entry| (key.clone(), entry.clone())))
+        .collect();
+
+    let mut peer_leaf_rows: BTreeMap<String, (TxnId, Row)> = BTreeMap::new();
+    for (txn_id, row) in &peer_rows {
+        if let Some(key) = row.get(0) {
+            peer_leaf_rows.insert(key.to_string(), (txn_id.clone(), row.clone()));
+        }
+    }
+
+    // Resolve each divergent key by `TxnId`, the same way
+    // `replication.rs::get` picks the newest of several replicas' replies,
+    // rather than by content alone--otherwise syncing against a peer
+    // holding a newer row would overwrite it with our own stale copy.
+    for (key, (local_txn_id, row)) in &local_leaf_rows {
+        match peer_leaf_rows.get(key) {
+            Some((peer_txn_id, peer_row)) if peer_row == row || peer_txn_id >= local_txn_id => {}
+            _ => {
+                transport
+                    .send(peer, txn.clone(), Message::Update(vec![row.clone()]))
+                    .await?;
+                report.pushed.push(row.clone());
+            }
+        }
+    }
+
+    for (key, (peer_txn_id, row)) in &peer_leaf_rows {
+        let should_pull = match local_leaf_rows.get(key) {
+            Some((local_txn_id, local_row)) => local_row != row && peer_txn_id > local_txn_id,
+            None => true,
+        };
+
+        if should_pull {
+            report.pulled.push(row.clone());
+        }
+    }
+
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::sync::Arc;
 
@@ -10,6 +11,8 @@ use crate::transaction::{Txn, TxnId};
 use crate::value::{TCResult, TCStream, Value, ValueId};
 
 mod index;
+mod merkle;
+mod replication;
 mod schema;
 mod view;
 
@@ -18,6 +21,9 @@ pub type Column = schema::Column;
 pub type Schema = schema::Schema;
 pub type TableBase = index::TableBase;
 
+pub use merkle::{sync, MerkleTree, SyncReport};
+pub use replication::{Message, NodeId, Reply, ReplicatedTable, Replication, Ring, Transport};
+
 #[async_trait]
 pub trait Selection: Clone + Into<Table> + Sized + Send + Sync + 'static {
     type Stream: Stream<Item = Vec<Value>> + Send + Sync + Unpin;
@@ -77,6 +83,19 @@ pub trait Selection: Clone + Into<Table> + Sized + Send + Sync + 'static {
         }
     }
 
+    /// Build a row from columns supplied by name--filling in declared
+    /// defaults, rejecting unknown columns, and validating each value
+    /// against its column's declared type via `Schema::make_row`--then
+    /// apply it the same way `update` would. This is the column-name
+    /// counterpart to `update`'s positional `Row`, so a caller that only
+    /// knows some of a row's column names (a partial update, or an insert
+    /// that relies on column defaults) doesn't have to reconstruct the
+    /// full positional row itself.
+    async fn put(&self, txn: Arc<Txn>, values: HashMap<ValueId, Value>) -> TCResult<()> {
+        let row = self.schema().make_row(values)?;
+        self.clone().update(txn, row).await
+    }
+
     fn reversed(&self) -> TCResult<Table>;
 
     fn select(&self, columns: Vec<ValueId>) -> TCResult<view::ColumnSelection> {
@@ -0,0 +1,365 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::stream::{FuturesUnordered, StreamExt};
+
+use crate::error;
+use crate::transaction::{Txn, TxnId};
+use crate::value::{TCResult, Value};
+
+use super::schema::{Bounds, Row};
+use super::index::TableBase;
+
+/// Addresses one host participating in a replicated table. Held as an
+/// opaque string--`Ring` only ever hashes and compares these, so it
+/// doesn't need to know anything about how to actually reach one; that's
+/// `Transport`'s job.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct NodeId(String);
+
+impl NodeId {
+    pub fn new(id: impl Into<String>) -> NodeId {
+        NodeId(id.into())
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+fn hash_of(data: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A consistent-hashing ring over the nodes replicating a `TableBase`. Each
+/// node occupies one point on the ring at the hash of its `NodeId`; the
+/// nodes responsible for a key are whichever distinct nodes are encountered
+/// first walking clockwise from the key's own hash.
+pub struct Ring {
+    points: BTreeMap<u64, NodeId>,
+}
+
+impl Ring {
+    pub fn new(nodes: Vec<NodeId>) -> Ring {
+        let points = nodes
+            .into_iter()
+            .map(|node| (hash_of(&node.0), node))
+            .collect();
+
+        Ring { points }
+    }
+
+    /// The (up to) `replication_factor` distinct nodes responsible for
+    /// `key`, found by walking the ring clockwise from `key`'s hash and
+    /// wrapping around once if the walk reaches the end without collecting
+    /// enough distinct nodes.
+    pub fn responsible_nodes(&self, key: &Value, replication_factor: usize) -> Vec<NodeId> {
+        let hash = hash_of(&key.to_string());
+        let mut seen = HashSet::new();
+        let mut nodes = Vec::with_capacity(replication_factor);
+
+        for (_, node) in self.points.range(hash..).chain(self.points.range(..hash)) {
+            if seen.insert(node.clone()) {
+                nodes.push(node.clone());
+                if nodes.len() == replication_factor {
+                    break;
+                }
+            }
+        }
+
+        nodes
+    }
+}
+
+/// Replication tuning for a `ReplicatedTable`. `read_quorum + write_quorum`
+/// must exceed `replication_factor`, which guarantees that any read quorum
+/// and any write quorum share at least one node in common--so a read can
+/// never miss the most recent acknowledged write.
+#[derive(Clone, Copy)]
+pub struct Replication {
+    pub replication_factor: usize,
+    pub read_quorum: usize,
+    pub write_quorum: usize,
+    pub timeout: Duration,
+}
+
+impl Replication {
+    pub fn new(
+        replication_factor: usize,
+        read_quorum: usize,
+        write_quorum: usize,
+        timeout: Duration,
+    ) -> TCResult<Replication> {
+        if read_quorum + write_quorum <= replication_factor {
+            return Err(error::bad_request(
+                "read_quorum + write_quorum must exceed replication_factor to guarantee overlap, found",
+                format!(
+                    "{} + {} <= {}",
+                    read_quorum, write_quorum, replication_factor
+                ),
+            ));
+        }
+
+        Ok(Replication {
+            replication_factor,
+            read_quorum,
+            write_quorum,
+            timeout,
+        })
+    }
+}
+
+/// A message sent to one node responsible for a partition of a
+/// `ReplicatedTable`.
+#[derive(Clone)]
+pub enum Message {
+    /// Read a single row by its primary key.
+    ReadEntry(Row),
+    /// Stream a range of rows, optionally resuming after `offset` (the
+    /// sort-key value of the last row the caller already has) and capped
+    /// at `limit` rows, so a `stream`/`slice` caller can page over a
+    /// partition instead of requiring the whole thing in one message.
+    ReadRange {
+        bounds: Bounds,
+        offset: Option<Value>,
+        limit: Option<u64>,
+    },
+    /// Write back a batch of rows--either a client-issued write, or a
+    /// read-repair of a stale or missing replica.
+    Update(Vec<Row>),
+    /// Fetch the hash of the node at `index` in the peer's Merkle tree over
+    /// its key space (see the `merkle` module)--the root if `index` is 0,
+    /// otherwise one of its descendants.
+    TreeNode(usize),
+    /// Fetch every row the peer holds in the Merkle leaf at `index`, for
+    /// exchanging actual rows once anti-entropy sync has narrowed a
+    /// divergence down to a single leaf range.
+    LeafRows(usize),
+}
+
+/// One node's reply to a `Message`, tagged with the `TxnId` each row was
+/// last written under so a reader can tell which replica's copy is newest.
+pub enum Reply {
+    Entry(Option<(TxnId, Row)>),
+    Range(Vec<(TxnId, Row)>),
+    Ack,
+    TreeNode([u8; 32]),
+}
+
+/// How a `ReplicatedTable` actually reaches another node. Left abstract
+/// here (there's no host-addressing/request-dispatch type in this crate to
+/// build directly on, the way `host::Gateway` would be in the newer
+/// engine) so that whatever carries `Message`s between hosts--TCP, the
+/// existing HTTP gateway, an in-process channel for tests--can implement
+/// this without `ReplicatedTable` knowing the difference.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn send(&self, node: &NodeId, txn: Arc<Txn>, message: Message) -> TCResult<Reply>;
+}
+
+/// A `TableBase` sharded and replicated across `ring`'s nodes, per
+/// `replication`'s quorum settings. Reads and writes against rows this
+/// node doesn't hold locally are dispatched to the responsible nodes over
+/// `transport`.
+pub struct ReplicatedTable<T: Transport> {
+    local: TableBase,
+    ring: Ring,
+    replication: Replication,
+    transport: Arc<T>,
+}
+
+impl<T: Transport> ReplicatedTable<T> {
+    pub fn new(
+        local: TableBase,
+        ring: Ring,
+        replication: Replication,
+        transport: Arc<T>,
+    ) -> ReplicatedTable<T> {
+        ReplicatedTable {
+            local,
+            ring,
+            replication,
+            transport,
+        }
+    }
+
+    fn responsible_nodes(&self, key: &Value) -> Vec<NodeId> {
+        self.ring
+            .responsible_nodes(key, self.replication.replication_factor)
+    }
+
+    /// Read a row by primary key, querying every responsible node
+    /// concurrently until `read_quorum` have responded (or `timeout`
+    /// elapses for the whole quorum wait, not per node), returning the
+    /// newest version seen. Any replica that returned a stale or missing
+    /// row is repaired in the background with the newest version found.
+    pub async fn get(&self, txn: Arc<Txn>, key: Row) -> TCResult<Option<Row>> {
+        let partition_key = key
+            .get(0)
+            .cloned()
+            .ok_or_else(|| error::bad_request("cannot look up a row with no key", "(empty key)"))?;
+
+        let mut pending: FuturesUnordered<_> = self
+            .responsible_nodes(&partition_key)
+            .into_iter()
+            .map(|node| {
+                let transport = self.transport.clone();
+                let txn = txn.clone();
+                let key = key.clone();
+                async move {
+                    // `None` if the node didn't answer, `Some(None)` if it has no such row
+                    let entry = match transport.send(&node, txn, Message::ReadEntry(key)).await {
+                        Ok(Reply::Entry(entry)) => Some(entry),
+                        _ => None,
+                    };
+
+                    (node, entry)
+                }
+            })
+            .collect();
+
+        let mut replies: Vec<(NodeId, Option<(TxnId, Row)>)> = Vec::new();
+        let quorum = async {
+            let mut responded = 0;
+            while let Some((node, entry)) = pending.next().await {
+                if entry.is_some() {
+                    responded += 1;
+                }
+
+                replies.push((node, entry.flatten()));
+
+                if responded >= self.replication.read_quorum {
+                    break;
+                }
+            }
+        };
+
+        let _ = tokio::time::timeout(self.replication.timeout, quorum).await;
+
+        let newest = replies
+            .iter()
+            .filter_map(|(_, entry)| entry.clone())
+            .max_by_key(|(txn_id, _)| txn_id.clone())
+            .map(|(_, row)| row);
+
+        if let Some(newest) = &newest {
+            self.read_repair(txn, &replies, newest).await;
+        }
+
+        Ok(newest)
+    }
+
+    /// Write `rows` to every node responsible for each row's partition key,
+    /// dispatching to all of them concurrently and succeeding only once
+    /// `write_quorum` nodes have acknowledged *each* row's own partition
+    /// within `timeout`--mirrors `get`'s read-side quorum wait, but for
+    /// writes. Quorum is tracked per partition key rather than pooled
+    /// across the whole batch: a multi-row batch can span disjoint replica
+    /// sets, so one partition's replicas all NACKing must not be masked by
+    /// another partition's replicas all ACKing.
+    pub async fn update(&self, txn: Arc<Txn>, rows: Vec<Row>) -> TCResult<()> {
+        let mut by_node: BTreeMap<NodeId, Vec<Row>> = BTreeMap::new();
+        let mut nodes_by_partition: BTreeMap<String, Vec<NodeId>> = BTreeMap::new();
+        for row in rows {
+            let partition_key = row.get(0).cloned().ok_or_else(|| {
+                error::bad_request("cannot write a row with no key", "(empty key)")
+            })?;
+
+            let nodes = self.responsible_nodes(&partition_key);
+            nodes_by_partition.insert(partition_key.to_string(), nodes.clone());
+
+            for node in nodes {
+                by_node.entry(node).or_insert_with(Vec::new).push(row.clone());
+            }
+        }
+
+        let mut pending: FuturesUnordered<_> = by_node
+            .into_iter()
+            .map(|(node, rows)| {
+                let transport = self.transport.clone();
+                let txn = txn.clone();
+                async move {
+                    let acked = matches!(
+                        transport.send(&node, txn, Message::Update(rows)).await,
+                        Ok(Reply::Ack)
+                    );
+
+                    (node, acked)
+                }
+            })
+            .collect();
+
+        let mut acked_by_node: BTreeMap<NodeId, bool> = BTreeMap::new();
+        let quorum = async {
+            while let Some((node, acked)) = pending.next().await {
+                acked_by_node.insert(node, acked);
+            }
+        };
+
+        let _ = tokio::time::timeout(self.replication.timeout, quorum).await;
+
+        let short: Vec<String> = nodes_by_partition
+            .into_iter()
+            .filter_map(|(partition_key, nodes)| {
+                let acked = nodes
+                    .iter()
+                    .filter(|node| acked_by_node.get(node).copied().unwrap_or(false))
+                    .count();
+
+                if acked >= self.replication.write_quorum {
+                    None
+                } else {
+                    Some(partition_key)
+                }
+            })
+            .collect();
+
+        if short.is_empty() {
+            Ok(())
+        } else {
+            Err(error::internal(format!(
+                "fewer than {} replicas acknowledged this write for partition(s): {}",
+                self.replication.write_quorum,
+                short.join(", ")
+            )))
+        }
+    }
+
+    /// Stage a write of `newest` to any node (among those already queried
+    /// by `get`) whose reply was missing or older than `newest`, without
+    /// blocking the read on it completing.
+    async fn read_repair(
+        &self,
+        txn: Arc<Txn>,
+        replies: &[(NodeId, Option<(TxnId, Row)>)],
+        newest: &Row,
+    ) {
+        for (node, entry) in replies {
+            let is_stale = match entry {
+                Some((_, row)) => row != newest,
+                None => true,
+            };
+
+            if is_stale {
+                let transport = self.transport.clone();
+                let node = node.clone();
+                let txn = txn.clone();
+                let newest = newest.clone();
+                tokio::spawn(async move {
+                    let _ = transport
+                        .send(&node, txn, Message::Update(vec![newest]))
+                        .await;
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::error;
+use crate::value::{TCResult, Value, ValueId};
+
+/// One column of a `Schema`: a name, an optional default for rows that
+/// don't supply it, and a predicate checking whether a supplied `Value` is
+/// the right shape for this column. `accepts` is a plain `fn` pointer
+/// rather than a trait object so `Column` (and therefore `Schema`) stays
+/// `Clone`, the same way the rest of this module's types do.
+#[derive(Clone)]
+pub struct Column {
+    name: ValueId,
+    default: Option<Value>,
+    accepts: fn(&Value) -> bool,
+}
+
+impl Column {
+    /// A required (NOT NULL) column: omitting it from a `make_row` call is
+    /// an error.
+    pub fn new(name: ValueId, accepts: fn(&Value) -> bool) -> Column {
+        Column {
+            name,
+            default: None,
+            accepts,
+        }
+    }
+
+    /// An optional column, filled with `default` when a row omits it.
+    pub fn with_default(name: ValueId, accepts: fn(&Value) -> bool, default: Value) -> Column {
+        Column {
+            name,
+            default: Some(default),
+            accepts,
+        }
+    }
+
+    pub fn name(&self) -> &ValueId {
+        &self.name
+    }
+}
+
+/// A positional row of column values, in `Schema` column order.
+pub type Row = Vec<Value>;
+
+/// A range of rows to select, keyed by column name--e.g. for `slice` or a
+/// replicated `Message::ReadRange`. Left as a plain name-to-value map of
+/// the bounding values for each constrained column; a table view is free to
+/// interpret an unconstrained column as "any value".
+#[derive(Clone, Default)]
+pub struct Bounds(HashMap<ValueId, Value>);
+
+impl Bounds {
+    pub fn new(bounds: HashMap<ValueId, Value>) -> Bounds {
+        Bounds(bounds)
+    }
+
+    pub fn get(&self, name: &ValueId) -> Option<&Value> {
+        self.0.get(name)
+    }
+}
+
+/// The columns of a `Table`, in the fixed order a positional `Row` stores
+/// them in.
+#[derive(Clone)]
+pub struct Schema {
+    columns: Vec<Column>,
+}
+
+impl Schema {
+    pub fn new(columns: Vec<Column>) -> Schema {
+        Schema { columns }
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// True if `names` is a prefix of this schema's column order, used by
+    /// `Selection::order_by` to tell whether a sort is already satisfied by
+    /// the table's natural order.
+    pub fn starts_with(&self, names: &[ValueId]) -> bool {
+        names.len() <= self.columns.len()
+            && self
+                .columns
+                .iter()
+                .zip(names)
+                .all(|(column, name)| &column.name == name)
+    }
+
+    /// Build a positional `Row` from columns supplied by name: fills in
+    /// each omitted column's declared default, rejects any name that isn't
+    /// one of this schema's columns, fails if a column with no default is
+    /// omitted, and validates every supplied value against its column's
+    /// `accepts` predicate. Routing `put`/`update` through this (see
+    /// `Selection::put`) means a row can never reach the index with a
+    /// missing required column, an unknown column, or a value of the wrong
+    /// shape.
+    pub fn make_row(&self, mut values: HashMap<ValueId, Value>) -> TCResult<Row> {
+        let mut row = Vec::with_capacity(self.columns.len());
+
+        for column in &self.columns {
+            let value = match values.remove(&column.name) {
+                Some(value) if (column.accepts)(&value) => value,
+                Some(value) => {
+                    return Err(error::bad_request(
+                        "invalid value for column",
+                        format!("{}: {}", column.name, value),
+                    ))
+                }
+                None => match &column.default {
+                    Some(default) => default.clone(),
+                    None => {
+                        return Err(error::bad_request(
+                            "missing required column",
+                            column.name.to_string(),
+                        ))
+                    }
+                },
+            };
+
+            row.push(value);
+        }
+
+        if let Some((name, _)) = values.into_iter().next() {
+            return Err(error::bad_request(
+                "not a column of this table",
+                name.to_string(),
+            ));
+        }
+
+        Ok(row)
+    }
+}
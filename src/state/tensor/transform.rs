@@ -245,7 +245,7 @@ impl Slice {
         source_coord
     }
 
-    pub fn map_bounds(&self, source_bounds: Bounds) -> Bounds {
+    pub fn map_bounds(&self, source_bounds: Bounds) -> TCResult<Bounds> {
         assert!(source_bounds.len() == self.source_shape.len());
 
         let mut coord: Vec<AxisBounds> = Vec::with_capacity(self.shape.len());
@@ -257,7 +257,60 @@ impl Slice {
 
             use AxisBounds::*;
             match &source_bounds[axis] {
-                In(_, _) => todo!(),
+                In(src_range, src_step) => {
+                    match &self.bounds[axis] {
+                        In(slice_range, slice_step) => {
+                            if src_step % slice_step != 0 {
+                                return Err(error::bad_request(
+                                    "source step does not align with slice step for axis",
+                                    axis,
+                                ));
+                            }
+
+                            if src_range.start < slice_range.start || src_range.end > slice_range.end {
+                                return Err(error::bad_request(
+                                    "source range falls outside the sliced region for axis",
+                                    axis,
+                                ));
+                            }
+
+                            if (src_range.start - slice_range.start) % slice_step != 0 {
+                                return Err(error::bad_request(
+                                    "source range does not align with slice step for axis",
+                                    axis,
+                                ));
+                            }
+
+                            let start = (src_range.start - slice_range.start) / slice_step;
+                            let end = (src_range.end - slice_range.start).div_ceil(slice_step);
+                            let step = src_step / slice_step;
+                            coord.push((start..end, step).into());
+                        }
+                        Of(indices) => {
+                            let mut positions = Vec::new();
+                            let mut i = src_range.start;
+                            while i < src_range.end {
+                                let position = indices.iter().position(|x| *x == i).ok_or_else(|| {
+                                    error::bad_request(
+                                        "source index is not present in the slice for axis",
+                                        axis,
+                                    )
+                                })?;
+
+                                positions.push(position as u64);
+                                i += src_step;
+                            }
+
+                            coord.push(positions.into());
+                        }
+                        At(_) => {
+                            return Err(error::bad_request(
+                                "cannot map a range of source bounds onto an elided axis",
+                                axis,
+                            ))
+                        }
+                    }
+                }
                 Of(indices) => {
                     let offset = self.offset.get(&axis).unwrap_or(&0);
                     coord.push(
@@ -275,7 +328,7 @@ impl Slice {
             }
         }
 
-        coord.into()
+        Ok(coord.into())
     }
 
     pub fn map_coord(&self, source_coord: Vec<u64>) -> Vec<u64> {
@@ -294,6 +347,253 @@ impl Slice {
     }
 }
 
+/// A view that reverses a source tensor along one or more axes (numpy's
+/// `flip`). `Flip` is its own inverse, so `invert_*` and `map_*` share the
+/// same mirroring logic; axes not named in `axes` pass through unchanged.
+#[derive(Clone)]
+pub struct Flip {
+    shape: Shape,
+    axes: Vec<usize>,
+}
+
+impl Flip {
+    pub fn new(source_shape: Shape, axes: Vec<usize>) -> Flip {
+        Flip {
+            shape: source_shape,
+            axes,
+        }
+    }
+
+    pub fn invert_axes(&self, axes: Vec<usize>) -> Vec<usize> {
+        axes
+    }
+
+    pub fn invert_bounds(&self, bounds: Bounds) -> Bounds {
+        self.map_bounds(bounds)
+    }
+
+    pub fn invert_coord(&self, coord: &[u64]) -> Vec<u64> {
+        self.map_coord(coord.to_vec())
+    }
+
+    pub fn map_bounds(&self, source_bounds: Bounds) -> Bounds {
+        assert!(source_bounds.len() == self.shape.len());
+
+        let mut bounds = Vec::with_capacity(self.shape.len());
+        for axis in 0..self.shape.len() {
+            if !self.axes.contains(&axis) {
+                bounds.push(source_bounds[axis].clone());
+                continue;
+            }
+
+            let dim = self.shape[axis];
+
+            use AxisBounds::*;
+            let flipped = match &source_bounds[axis] {
+                At(i) => At(dim - 1 - i),
+                In(range, step) if range.end > range.start => {
+                    // mirror the last index the range actually selects, not
+                    // `range.end`, so a stepped range selects the mirrored indices
+                    let last = range.start + (range.end - 1 - range.start) / step * step;
+                    (dim - 1 - last..dim - range.start, *step).into()
+                }
+                In(range, step) => (dim - range.end..dim - range.start, *step).into(),
+                Of(indices) => {
+                    let mut indices: Vec<u64> = indices.iter().map(|i| dim - 1 - i).collect();
+                    indices.reverse();
+                    indices.into()
+                }
+            };
+
+            bounds.push(flipped);
+        }
+
+        bounds.into()
+    }
+
+    pub fn map_coord(&self, source_coord: Vec<u64>) -> Vec<u64> {
+        source_coord
+            .into_iter()
+            .enumerate()
+            .map(|(axis, c)| {
+                if self.axes.contains(&axis) {
+                    self.shape[axis] - 1 - c
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+
+    pub fn shape(&'_ self) -> &'_ Shape {
+        &self.shape
+    }
+}
+
+/// Row-major strides for a shape: `strides[i]` is the number of elements
+/// between adjacent indices along axis `i`, i.e. the product of every
+/// dimension after `i`.
+fn strides(shape: &Shape) -> Vec<u64> {
+    let mut strides = vec![1u64; shape.len()];
+    for axis in (0..shape.len().saturating_sub(1)).rev() {
+        strides[axis] = strides[axis + 1] * shape[axis + 1];
+    }
+
+    strides
+}
+
+/// A view that reinterprets a source tensor's elements under a new shape
+/// of the same size, treating both shapes as row-major (numpy's
+/// `reshape`). `invert_coord`/`map_coord` flatten a coordinate to its
+/// linear row-major offset under one shape and decompose that offset
+/// under the other; `invert_bounds`/`map_bounds` only support bounds whose
+/// flattened image is a single contiguous range that is also rectangular
+/// in the other shape, and return an error otherwise, since an arbitrary
+/// rectangular region of one shape doesn't generally correspond to a
+/// rectangular region of the other.
+#[derive(Clone)]
+pub struct Reshape {
+    source_shape: Shape,
+    shape: Shape,
+}
+
+impl Reshape {
+    pub fn new(source_shape: Shape, shape: Shape) -> TCResult<Reshape> {
+        if source_shape.size() != shape.size() {
+            return Err(error::bad_request(
+                &format!("Cannot reshape {} into", source_shape),
+                shape,
+            ));
+        }
+
+        Ok(Reshape {
+            source_shape,
+            shape,
+        })
+    }
+
+    fn offset_of(shape: &Shape, coord: &[u64]) -> u64 {
+        let strides = strides(shape);
+        coord.iter().zip(strides).map(|(c, stride)| c * stride).sum()
+    }
+
+    fn coord_of(shape: &Shape, mut offset: u64) -> Vec<u64> {
+        let strides = strides(shape);
+        let mut coord = Vec::with_capacity(shape.len());
+        for stride in strides {
+            coord.push(offset / stride);
+            offset %= stride;
+        }
+
+        coord
+    }
+
+    fn offset_range(shape: &Shape, bounds: &Bounds) -> TCResult<(u64, u64)> {
+        use AxisBounds::*;
+
+        let mut start = Vec::with_capacity(shape.len());
+        let mut end = Vec::with_capacity(shape.len());
+        for axis in 0..shape.len() {
+            match &bounds[axis] {
+                At(i) => {
+                    start.push(*i);
+                    end.push(*i + 1);
+                }
+                In(range, 1) => {
+                    start.push(range.start);
+                    end.push(range.end);
+                }
+                other => {
+                    return Err(error::bad_request(
+                        "reshape cannot map a non-contiguous bound",
+                        format!("{:?}", other),
+                    ))
+                }
+            }
+        }
+
+        for axis in 0..shape.len() - 1 {
+            if end[axis] - start[axis] != 1 && (start[axis + 1] != 0 || end[axis + 1] != shape[axis + 1]) {
+                return Err(error::bad_request(
+                    "reshape cannot map a non-contiguous bound",
+                    format!("{:?}", bounds),
+                ));
+            }
+        }
+
+        let offset_start = Self::offset_of(shape, &start);
+        let offset_end = Self::offset_of(shape, &end.iter().map(|i| i - 1).collect::<Vec<u64>>()) + 1;
+        Ok((offset_start, offset_end))
+    }
+
+    pub fn invert_axes(&self, _axes: Vec<usize>) -> Vec<usize> {
+        (0..self.source_shape.len()).collect()
+    }
+
+    pub fn invert_bounds(&self, bounds: Bounds) -> TCResult<Bounds> {
+        let (start, end) = Self::offset_range(&self.shape, &bounds)?;
+        let source_start = Self::coord_of(&self.source_shape, start);
+        let source_end = Self::coord_of(&self.source_shape, end - 1)
+            .into_iter()
+            .map(|i| i + 1)
+            .collect::<Vec<u64>>();
+
+        Self::bounds_between(&self.source_shape, source_start, source_end)
+    }
+
+    pub fn invert_coord(&self, coord: &[u64]) -> Vec<u64> {
+        let offset = Self::offset_of(&self.shape, coord);
+        Self::coord_of(&self.source_shape, offset)
+    }
+
+    pub fn map_bounds(&self, source_bounds: Bounds) -> TCResult<Bounds> {
+        let (start, end) = Self::offset_range(&self.source_shape, &source_bounds)?;
+        let dest_start = Self::coord_of(&self.shape, start);
+        let dest_end = Self::coord_of(&self.shape, end - 1)
+            .into_iter()
+            .map(|i| i + 1)
+            .collect::<Vec<u64>>();
+
+        Self::bounds_between(&self.shape, dest_start, dest_end)
+    }
+
+    pub fn map_coord(&self, source_coord: Vec<u64>) -> Vec<u64> {
+        let offset = Self::offset_of(&self.source_shape, &source_coord);
+        Self::coord_of(&self.shape, offset)
+    }
+
+    // Build the `Bounds` of a contiguous region of `shape` running from
+    // the coordinate `start` up to (but not including) `end`: leading axes
+    // at a single index, then one ranged axis, then fully spanned axes.
+    // A contiguous offset range that isn't rectangular in `shape` is
+    // rejected.
+    fn bounds_between(shape: &Shape, start: Vec<u64>, end: Vec<u64>) -> TCResult<Bounds> {
+        let mut bounds = Vec::with_capacity(shape.len());
+        let mut spanning = false;
+        for axis in 0..shape.len() {
+            if spanning && (start[axis] != 0 || end[axis] != shape[axis]) {
+                return Err(error::bad_request(
+                    "reshape cannot map a bound onto a non-rectangular region",
+                    format!("{:?}..{:?}", start, end),
+                ));
+            }
+
+            if start[axis] + 1 == end[axis] && axis + 1 < shape.len() {
+                bounds.push(AxisBounds::At(start[axis]));
+            } else {
+                spanning = true;
+                bounds.push((start[axis]..end[axis], 1).into());
+            }
+        }
+
+        Ok(bounds.into())
+    }
+
+    pub fn shape(&'_ self) -> &'_ Shape {
+        &self.shape
+    }
+}
+
 #[derive(Clone)]
 pub struct Transpose {
     source_shape: Shape,
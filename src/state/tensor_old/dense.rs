@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::iter;
 use std::ops::{Deref, DerefMut};
@@ -8,6 +9,7 @@ use arrayfire as af;
 use async_trait::async_trait;
 use futures::future::{self, BoxFuture, Future, TryFutureExt};
 use futures::stream::{self, FuturesOrdered, Stream, StreamExt, TryStreamExt};
+use futures::try_join;
 use itertools::Itertools;
 
 use crate::error;
@@ -51,6 +53,67 @@ pub trait DenseTensorView: TensorView + 'static {
         coord: Vec<u64>,
         value: Number,
     ) -> BoxFuture<'a, TCResult<()>>;
+
+    /// Batched matrix multiplication: the last two axes of `self` and `other` are
+    /// treated as (rows, contracted) and (contracted, columns) respectively, and
+    /// any leading axes are batch dimensions, broadcast NumPy-style between the
+    /// operands (a missing or size-1 batch axis on one side is repeated).
+    async fn matmul<O: DenseTensorView>(self, other: O, txn: Arc<Txn>) -> TCResult<BlockTensor>;
+
+    /// Contract `self` and `other` over the given pairs of axes, mirroring
+    /// `numpy.tensordot`. The current implementation handles only two 2-D
+    /// operands contracted over a single pair of axes (where it reduces to
+    /// `matmul` after moving the contracted axes to the inner position); any
+    /// other combination is rejected with `bad_request`, since collapsing
+    /// several axes into one would additionally require a reshape primitive
+    /// that this tensor type doesn't expose.
+    async fn tensordot<O: DenseTensorView>(
+        self,
+        other: O,
+        axes: (Vec<usize>, Vec<usize>),
+        txn: Arc<Txn>,
+    ) -> TCResult<BlockTensor>;
+
+    /// Numerically-stable softmax along `axis`: `exp(x - m) / Σ exp(x - m)`,
+    /// where `m` is the per-slice max along `axis` (subtracting it keeps the
+    /// exponentials from overflowing). `self` must already be a floating-point
+    /// tensor; cast with `as_dtype` first if it isn't.
+    async fn softmax(self, txn: Arc<Txn>, axis: usize) -> TCResult<BlockTensor>;
+
+    /// Like [`DenseTensorView::softmax`], but divides by `1 + Σ exp(x - m)`
+    /// instead of `Σ exp(x - m)` (the "quiet softmax" from Burn's activation
+    /// module), so a slice of strongly negative inputs can produce outputs
+    /// that are all close to zero instead of being forced to sum to 1.
+    async fn quiet_softmax(self, txn: Arc<Txn>, axis: usize) -> TCResult<BlockTensor>;
+
+    /// Cast to `dtype`. If `checked` is `true`, reject a cast that could lose
+    /// precision (narrowing to a smaller representation, truncating a
+    /// float/complex value to an integral type, or casting anything other
+    /// than `Bool` down to `Bool`) instead of silently converting.
+    async fn cast_into(self, txn: Arc<Txn>, dtype: NumberType, checked: bool)
+        -> TCResult<BlockTensor>;
+}
+
+/// Would casting `from` to `to` lose information? Used by `cast_into` when
+/// its `checked` flag is set.
+fn is_lossy_cast(from: NumberType, to: NumberType) -> bool {
+    use NumberType::*;
+
+    if to.size() < from.size() {
+        return true;
+    }
+
+    match (from, to) {
+        (Bool, _) => false,
+        (_, Bool) => true,
+        (Float(_), UInt(_)) | (Complex(_), UInt(_)) => true,
+        (Float(_), Int(_)) | (Complex(_), Int(_)) => true,
+        (Float(_), Float(_)) | (Complex(_), Complex(_)) | (UInt(_), UInt(_)) => false,
+        (UInt(_), Float(_)) | (UInt(_), Complex(_)) => false,
+        (Float(_), Complex(_)) => false,
+        (Complex(_), Float(_)) => true,
+        _ => false,
+    }
 }
 
 #[async_trait]
@@ -430,6 +493,163 @@ impl BlockTensor {
             .map(BlockId::from)
             .then(move |block_id| self.file.clone().get_block_owned(txn_id.clone(), block_id))
     }
+
+    /// Element-wise approximate equality: the result is `true` wherever
+    /// `|self - other| <= atol + rtol * |other|`, with `atol`/`rtol` chosen
+    /// according to `approximation`.
+    pub async fn is_close<O: DenseTensorView>(
+        self,
+        other: O,
+        approximation: Approximation,
+        txn: Arc<Txn>,
+    ) -> TCResult<BlockTensor> {
+        let (atol, rtol) = approximation.tolerance(self.dtype());
+        let atol = Number::from(atol);
+        let rtol = Number::from(rtol);
+
+        BlockTensor::combine(txn, self, other, move |l, r| {
+            let diff = l.subtract(r.clone())?.abs()?;
+            let len = diff.len();
+            let bound = r
+                .abs()?
+                .multiply(Array::constant(rtol.clone(), len))?
+                .add(Array::constant(atol.clone(), len))?;
+
+            diff.lte(&bound)
+        })
+        .await
+    }
+
+    /// `true` if every element of `self` and `other` is close, per `is_close`.
+    pub async fn all_close<O: DenseTensorView>(
+        self,
+        other: O,
+        approximation: Approximation,
+        txn: Arc<Txn>,
+    ) -> TCResult<bool> {
+        let txn_id = txn.id().clone();
+        self.is_close(other, approximation, txn).await?.all(txn_id).await
+    }
+
+    /// Reduce along `axis`, keeping the max value of each slice.
+    pub async fn max(self, txn: Arc<Txn>, axis: usize) -> TCResult<BlockTensor> {
+        reduce_extremum(self, txn, axis, true).await
+    }
+
+    /// The max value across every element of this tensor.
+    pub async fn max_all(self, txn_id: TxnId) -> TCResult<Number> {
+        extremum_all(self, txn_id, true).await
+    }
+
+    /// Reduce along `axis`, keeping the min value of each slice.
+    pub async fn min(self, txn: Arc<Txn>, axis: usize) -> TCResult<BlockTensor> {
+        reduce_extremum(self, txn, axis, false).await
+    }
+
+    /// The min value across every element of this tensor.
+    pub async fn min_all(self, txn_id: TxnId) -> TCResult<Number> {
+        extremum_all(self, txn_id, false).await
+    }
+
+    /// Reduce along `axis`, averaging each slice. Integer and boolean inputs
+    /// are first promoted to a float dtype so the division is meaningful.
+    pub async fn mean(self, txn: Arc<Txn>, axis: usize) -> TCResult<BlockTensor> {
+        if axis >= self.ndim() {
+            return Err(error::bad_request("Axis out of range", axis));
+        }
+
+        let count = self.shape()[axis] as f64;
+        let source = match self.dtype() {
+            NumberType::Float(_) | NumberType::Complex(_) => self,
+            _ => self.as_dtype(txn.clone(), FloatType::F64.into()).await?,
+        };
+
+        let sum = source.sum(txn.clone(), axis).await?;
+        let shape = sum.shape().clone();
+        let divisor = BlockTensor::constant(txn.clone(), shape, Number::from(count)).await?;
+
+        BlockTensor::combine(txn, sum, divisor, |s, d| s.divide(d)).await
+    }
+
+    /// The average of every element of this tensor. Integer and boolean
+    /// inputs are first promoted to a float dtype so the division is
+    /// meaningful.
+    pub async fn mean_all(self, txn: Arc<Txn>) -> TCResult<Number> {
+        let size = self.size() as f64;
+        let source = match self.dtype() {
+            NumberType::Float(_) | NumberType::Complex(_) => self,
+            _ => self.as_dtype(txn.clone(), FloatType::F64.into()).await?,
+        };
+
+        let txn_id = txn.id().clone();
+        let sum = source.sum_all(txn_id).await?;
+        Ok(sum / Number::from(size))
+    }
+
+    /// Reduce along `axis` using `op`, generalizing `sum`/`product`/`max`/
+    /// `min`/`mean` under one entry point. If `keepdims` is set, the reduced
+    /// axis is kept with a length of 1 (NumPy's `keepdims`) instead of being
+    /// dropped from the output shape.
+    pub async fn reduce(
+        self,
+        txn: Arc<Txn>,
+        axis: usize,
+        op: Reduce,
+        keepdims: bool,
+    ) -> TCResult<BlockTensor> {
+        let reduced = match op {
+            Reduce::Sum => self.sum(txn.clone(), axis).await?,
+            Reduce::Product => self.product(txn.clone(), axis).await?,
+            Reduce::Max => self.max(txn.clone(), axis).await?,
+            Reduce::Min => self.min(txn.clone(), axis).await?,
+            Reduce::Mean => self.mean(txn.clone(), axis).await?,
+        };
+
+        if keepdims {
+            let mut shape = reduced.shape().to_vec();
+            shape.insert(axis, 1);
+            reduced.with_shape(shape.into())
+        } else {
+            Ok(reduced)
+        }
+    }
+
+    /// Reinterpret this tensor's blocks under a new `shape` of the same total
+    /// size, without moving any data. This is only sound when `shape` visits
+    /// the same flattened element order as the current shape, e.g. inserting
+    /// or removing a length-1 axis; it is not a general-purpose reshape.
+    fn with_shape(self, shape: Shape) -> TCResult<Self> {
+        if shape.size() != self.shape.size() {
+            return Err(error::bad_request(
+                "cannot reinterpret a tensor of size",
+                format!("{} as shape of size {}", self.shape.size(), shape.size()),
+            ));
+        }
+
+        let coord_bounds = (0..shape.len())
+            .map(|axis| shape[axis + 1..].iter().product())
+            .collect();
+
+        Ok(BlockTensor {
+            file: self.file,
+            dtype: self.dtype,
+            per_block: self.per_block,
+            shape,
+            coord_bounds,
+        })
+    }
+}
+
+/// Which reduction [`BlockTensor::reduce`] applies along an axis. `AnyAll`'s
+/// whole-tensor `any`/`all` checks aren't included here since they reduce to
+/// a `bool` rather than another tensor and take no axis argument.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Reduce {
+    Sum,
+    Product,
+    Max,
+    Min,
+    Mean,
 }
 
 impl TensorView for BlockTensor {
@@ -672,6 +892,151 @@ impl DenseTensorView for BlockTensor {
                 .set_value((offset % self.per_block as u64) as usize, value)
         })
     }
+
+    async fn matmul<O: DenseTensorView>(self, other: O, txn: Arc<Txn>) -> TCResult<BlockTensor> {
+        let (l_batch, m, k) = matmul_shape(self.shape())?;
+        let (r_batch, k2, n) = matmul_shape(other.shape())?;
+
+        if k != k2 {
+            return Err(error::bad_request(
+                "matmul: contracted dimensions do not match",
+                format!("{} != {}", k, k2),
+            ));
+        }
+
+        let out_batch = broadcast_batch_shape(&l_batch, &r_batch)?;
+
+        let mut out_shape = out_batch.clone();
+        out_shape.push(m);
+        out_shape.push(n);
+        let out_shape: Shape = out_shape.into();
+
+        let dtype = self.dtype();
+        let txn_id = txn.id().clone();
+        let batch_size = out_batch.iter().product::<u64>().max(1) as usize;
+
+        let l_all = self.shape().all();
+        let r_all = other.shape().all();
+
+        // `value_stream` is itself backed by `block_stream`, so this already reads
+        // each operand's underlying blocks exactly once; fetching both operands
+        // concurrently rather than one after another avoids serializing on I/O.
+        let (left, right): (Vec<Number>, Vec<Number>) = try_join!(
+            self.value_stream(txn_id.clone(), l_all).try_collect(),
+            other.value_stream(txn_id, r_all).try_collect()
+        )?;
+
+        let (m, k, n) = (m as usize, k as usize, n as usize);
+
+        let tiles = stream::iter(0..batch_size).map(move |batch| {
+            // Map this batch's coordinate in the (possibly broadcast) output
+            // batch shape back to a linear index into each operand's own
+            // batch dimensions, so a size-1 batch axis on either operand is
+            // reused for every index along that axis instead of requiring an
+            // exact match.
+            let out_coord = batch_coord(batch, &out_batch);
+            let l_batch_index = source_batch_index(&out_coord, &l_batch);
+            let r_batch_index = source_batch_index(&out_coord, &r_batch);
+
+            let l_tile = left[l_batch_index * m * k..(l_batch_index + 1) * m * k].to_vec();
+            let r_tile = right[r_batch_index * k * n..(r_batch_index + 1) * k * n].to_vec();
+
+            let l_array = Array::try_from_values(l_tile, dtype)?;
+            let r_array = Array::try_from_values(r_tile, dtype)?;
+            l_array.matmul(&r_array)
+        });
+
+        BlockTensor::from_blocks(txn, out_shape, dtype, tiles).await
+    }
+
+    async fn tensordot<O: DenseTensorView>(
+        self,
+        other: O,
+        axes: (Vec<usize>, Vec<usize>),
+        txn: Arc<Txn>,
+    ) -> TCResult<BlockTensor> {
+        let (l_axes, r_axes) = axes;
+        if l_axes.len() != r_axes.len() {
+            return Err(error::bad_request(
+                "tensordot: mismatched number of contraction axes",
+                format!("{} != {}", l_axes.len(), r_axes.len()),
+            ));
+        }
+
+        for (&la, &ra) in l_axes.iter().zip(r_axes.iter()) {
+            if self.shape()[la] != other.shape()[ra] {
+                return Err(error::bad_request(
+                    "tensordot: contracted axis lengths do not match",
+                    format!("{} != {}", self.shape()[la], other.shape()[ra]),
+                ));
+            }
+        }
+
+        if l_axes.len() != 1 || self.ndim() != 2 || other.ndim() != 2 {
+            return Err(error::bad_request(
+                "tensordot only supports a single contracted axis between two 2-D operands",
+                "try reshaping the operands into 2 dimensions first",
+            ));
+        }
+
+        let l_perm: Vec<usize> = (0..self.ndim())
+            .filter(|x| !l_axes.contains(x))
+            .chain(l_axes.iter().copied())
+            .collect();
+
+        let r_perm: Vec<usize> = r_axes
+            .iter()
+            .copied()
+            .chain((0..other.ndim()).filter(|x| !r_axes.contains(x)))
+            .collect();
+
+        let left = self.transpose(Some(l_perm))?;
+        let right = other.transpose(Some(r_perm))?;
+
+        left.matmul(right, txn).await
+    }
+
+    async fn softmax(self, txn: Arc<Txn>, axis: usize) -> TCResult<BlockTensor> {
+        if axis >= self.ndim() {
+            return Err(error::bad_request("Axis out of range", axis));
+        } else if !matches!(self.dtype(), NumberType::Float(_)) {
+            return Err(error::bad_request(
+                "softmax requires a floating-point tensor, try casting first",
+                self.dtype(),
+            ));
+        }
+
+        softmax_along(self, txn, axis, false).await
+    }
+
+    async fn quiet_softmax(self, txn: Arc<Txn>, axis: usize) -> TCResult<BlockTensor> {
+        if axis >= self.ndim() {
+            return Err(error::bad_request("Axis out of range", axis));
+        } else if !matches!(self.dtype(), NumberType::Float(_)) {
+            return Err(error::bad_request(
+                "softmax requires a floating-point tensor, try casting first",
+                self.dtype(),
+            ));
+        }
+
+        softmax_along(self, txn, axis, true).await
+    }
+
+    async fn cast_into(
+        self,
+        txn: Arc<Txn>,
+        dtype: NumberType,
+        checked: bool,
+    ) -> TCResult<BlockTensor> {
+        if checked && is_lossy_cast(self.dtype(), dtype) {
+            return Err(error::bad_request(
+                "cannot cast without losing precision (pass checked: false to allow this)",
+                dtype,
+            ));
+        }
+
+        self.as_dtype(txn, dtype).await
+    }
 }
 
 impl Slice for BlockTensor {
@@ -682,6 +1047,17 @@ impl Slice for BlockTensor {
     }
 }
 
+// NOTE: a `Broadcast` `Rebase` implementor (stretching a source tensor to a
+// larger NumPy-style target shape, the way `TensorSlice`/`Permutation` here
+// already rebase through `Slice`/`Transpose`) would plug in right alongside
+// them: wrap a source `DenseTensorView`, track the target `Shape`, and
+// implement `invert_coord`/`invert_bounds` to map a stretched axis back to
+// index 0, rejecting `write_value_at` through a stretched axis. But `Rebase`,
+// `TensorSlice` and `Permutation` are only used in this file, not declared in
+// it — their declarations live in `super::base`, which isn't part of this
+// tree — so a real `Broadcast` type can't be added here without inventing
+// that trait and its associated types from scratch.
+
 impl Transpose for BlockTensor {
     type Permutation = Permutation<Self>;
 
@@ -739,6 +1115,36 @@ where
         self.source()
             .write_value_at(txn_id, self.invert_coord(coord), value)
     }
+
+    async fn matmul<O: DenseTensorView>(self, other: O, txn: Arc<Txn>) -> TCResult<BlockTensor> {
+        self.source().clone().matmul(other, txn).await
+    }
+
+    async fn tensordot<O: DenseTensorView>(
+        self,
+        other: O,
+        axes: (Vec<usize>, Vec<usize>),
+        txn: Arc<Txn>,
+    ) -> TCResult<BlockTensor> {
+        self.source().clone().tensordot(other, axes, txn).await
+    }
+
+    async fn softmax(self, txn: Arc<Txn>, axis: usize) -> TCResult<BlockTensor> {
+        self.source().clone().softmax(txn, axis).await
+    }
+
+    async fn quiet_softmax(self, txn: Arc<Txn>, axis: usize) -> TCResult<BlockTensor> {
+        self.source().clone().quiet_softmax(txn, axis).await
+    }
+
+    async fn cast_into(
+        self,
+        txn: Arc<Txn>,
+        dtype: NumberType,
+        checked: bool,
+    ) -> TCResult<BlockTensor> {
+        self.source().clone().cast_into(txn, dtype, checked).await
+    }
 }
 
 #[async_trait]
@@ -770,6 +1176,36 @@ pub fn per_block(dtype: NumberType) -> usize {
     BLOCK_SIZE / dtype.size()
 }
 
+/// Controls how loosely [`BlockTensor::is_close`] compares two tensors,
+/// mirroring tract-data's `Approximation` enum.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Approximation {
+    /// Only exactly equal values are considered close.
+    Exact,
+    /// A middle ground suitable for comparing the output of numerically
+    /// sensitive ops (e.g. reductions) against an expected value.
+    Close,
+    /// A loose tolerance suitable for comparing results that may have taken
+    /// different (but equally valid) computation paths to the same answer.
+    Approximate,
+}
+
+impl Approximation {
+    /// The `(atol, rtol)` pair for this mode and `dtype`.
+    fn tolerance(&self, dtype: NumberType) -> (f64, f64) {
+        use NumberType::*;
+
+        match self {
+            Self::Exact => (0., 0.),
+            Self::Approximate => (1e-4, 5e-4),
+            Self::Close => match dtype {
+                Float(FloatType::F32) | Complex(ComplexType::C32) => (1e-3, 1e-3),
+                _ => (1e-7, 1e-7),
+            },
+        }
+    }
+}
+
 fn compatible<L: TensorView, R: TensorView>(l: &L, r: &R) -> TCResult<()> {
     if l.shape() != r.shape() {
         Err(error::bad_request(
@@ -786,6 +1222,86 @@ fn compatible<L: TensorView, R: TensorView>(l: &L, r: &R) -> TCResult<()> {
     }
 }
 
+/// Split a tensor's shape into (batch dims, rows, contracted dim) for `matmul`,
+/// treating the last two axes as the matrix dimensions and any leading axes as
+/// batch dimensions.
+fn matmul_shape(shape: &Shape) -> TCResult<(Vec<u64>, u64, u64)> {
+    if shape.len() < 2 {
+        return Err(error::bad_request(
+            "matmul requires at least 2 dimensions, found",
+            format!("{}", shape),
+        ));
+    }
+
+    let ndim = shape.len();
+    let batch = shape[..ndim - 2].to_vec();
+    let rows = shape[ndim - 2];
+    let cols = shape[ndim - 1];
+    Ok((batch, rows, cols))
+}
+
+/// NumPy-style broadcast of two `matmul` batch-dimension shapes: the shorter
+/// shape is implicitly padded with leading 1s, then each aligned pair of
+/// dimensions must either match or have one side equal to 1.
+fn broadcast_batch_shape(l_batch: &[u64], r_batch: &[u64]) -> TCResult<Vec<u64>> {
+    let ndim = l_batch.len().max(r_batch.len());
+    let mut batch = Vec::with_capacity(ndim);
+
+    for i in 0..ndim {
+        let from_end = ndim - i;
+        let l_dim = l_batch.len().checked_sub(from_end).map_or(1, |j| l_batch[j]);
+        let r_dim = r_batch.len().checked_sub(from_end).map_or(1, |j| r_batch[j]);
+
+        let dim = match (l_dim, r_dim) {
+            (l, r) if l == r => l,
+            (1, r) => r,
+            (l, 1) => l,
+            _ => {
+                return Err(error::bad_request(
+                    "matmul: batch dimensions do not broadcast",
+                    format!("{:?} != {:?}", l_batch, r_batch),
+                ))
+            }
+        };
+
+        batch.push(dim);
+    }
+
+    Ok(batch)
+}
+
+/// The row-major coordinate of `index` within `shape` (the inverse of the
+/// usual row-major flattening), used to locate the batch this `matmul` tile
+/// belongs to in the (possibly broadcast) output batch shape.
+fn batch_coord(index: usize, shape: &[u64]) -> Vec<u64> {
+    let mut coord = vec![0; shape.len()];
+    let mut remainder = index as u64;
+
+    for (axis, dim) in shape.iter().enumerate().rev() {
+        let dim = (*dim).max(1);
+        coord[axis] = remainder % dim;
+        remainder /= dim;
+    }
+
+    coord
+}
+
+/// Map a coordinate in the (possibly broadcast) output batch shape to the
+/// linear index of the corresponding batch in `source_shape`, right-aligning
+/// the two shapes the same way [`broadcast_batch_shape`] did, and reusing
+/// batch `0` along any axis where `source_shape` was broadcast from size 1.
+fn source_batch_index(out_coord: &[u64], source_shape: &[u64]) -> usize {
+    let offset = out_coord.len() - source_shape.len();
+    let mut index = 0;
+
+    for (axis, dim) in source_shape.iter().enumerate() {
+        let coord = if *dim == 1 { 0 } else { out_coord[offset + axis] };
+        index = index * dim + coord;
+    }
+
+    index as usize
+}
+
 fn block_offsets(
     af_indices: &af::Array<u64>,
     af_offsets: &af::Array<u64>,
@@ -849,13 +1365,19 @@ fn reduce_axis0<
     let axis_bound = AxisBounds::all(shape[0]);
     shape.remove(0);
 
+    // Each output coordinate needs its own full pass over `axis`'s block
+    // data; buffering several of those passes concurrently (as the callers
+    // of `reduce_axis` already do for axis != 0) keeps the block store busy
+    // instead of waiting on one slice to finish before starting the next.
+    // The buffer is ordered, so results still come out in coordinate order.
     stream::iter(shape.all().affected())
         .map(move |coord| {
             let source_bounds: Bounds = (axis_bound.clone(), coord).into();
             source_bounds
         })
         .map(move |bounds| source.clone().slice(bounds))
-        .and_then(move |slice| reduce(slice))
+        .map_ok(move |slice| reduce(slice))
+        .try_buffered(4)
 }
 
 fn reduce_axis<T: DenseTensorView + Slice>(
@@ -868,4 +1390,463 @@ fn reduce_axis<T: DenseTensorView + Slice>(
         let slice = source.clone().slice(bounds.clone())?;
         Ok((bounds, slice))
     })
+}
+
+/// Fold every block of `source` down to a single `Number`, keeping whichever
+/// of `.max()`/`.min()` is requested. Mirrors `sum_all`/`product_all`, except
+/// it isn't a `DenseTensorUnary` method since that trait isn't declared in
+/// this tree (see the other blanket impls above).
+async fn extremum_all<T: DenseTensorView>(
+    source: T,
+    txn_id: TxnId,
+    want_max: bool,
+) -> TCResult<Number> {
+    let mut result: Option<Number> = None;
+    let mut blocks = source.block_stream(txn_id);
+    while let Some(block) = blocks.next().await {
+        let block = block?;
+        let candidate = if want_max { block.max() } else { block.min() };
+        result = Some(match result {
+            Some(current) => {
+                let keep_candidate = if want_max {
+                    candidate > current
+                } else {
+                    candidate < current
+                };
+
+                if keep_candidate {
+                    candidate
+                } else {
+                    current
+                }
+            }
+            None => candidate,
+        });
+    }
+
+    result.ok_or_else(|| error::bad_request("Cannot reduce an empty tensor along", "max/min"))
+}
+
+/// Reduce `source` along `axis` to its max or min, the same way `sum`/`product`
+/// reduce along an axis: recurse one axis at a time via `reduce_axis0`/
+/// `reduce_axis`, boxing the recursive call since it isn't behind a trait
+/// method (and so isn't auto-boxed by `#[async_trait]`) like `sum` is.
+fn reduce_extremum<T: DenseTensorView + Slice>(
+    source: T,
+    txn: Arc<Txn>,
+    axis: usize,
+    want_max: bool,
+) -> BoxFuture<'static, TCResult<BlockTensor>>
+where
+    <T as Slice>::Slice: DenseTensorView + Slice,
+{
+    Box::pin(async move {
+        if axis >= source.ndim() {
+            return Err(error::bad_request("Axis out of range", axis));
+        }
+
+        let dtype = source.dtype();
+        let txn_id = txn.id().clone();
+        let mut shape = source.shape().clone();
+        shape.remove(axis);
+
+        if axis == 0 {
+            let reduce =
+                move |slice: <T as Slice>::Slice| extremum_all(slice, txn_id.clone(), want_max);
+            let stream = reduce_axis0(source, reduce);
+            let blocks = ValueBlockStream::new(stream, dtype, per_block(dtype));
+            BlockTensor::from_blocks(txn, shape, dtype, blocks).await
+        } else {
+            let result = BlockTensor::constant(txn.clone(), shape, dtype.zero()).await?;
+
+            reduce_axis(source, axis)
+                .map_ok(|(bounds, slice)| {
+                    txn.clone()
+                        .subcontext_tmp()
+                        .and_then(move |context| reduce_extremum(slice, context, 0, want_max))
+                        .map_ok(move |slice_extremum| (bounds, slice_extremum))
+                })
+                .try_buffer_unordered(2)
+                .map_ok(|(bounds, slice_extremum)| {
+                    result.clone().write(txn_id.clone(), bounds, slice_extremum)
+                })
+                .try_fold((), |_, _| future::ready(Ok(())))
+                .await?;
+
+            Ok(result)
+        }
+    })
+}
+
+/// Broadcast `grad` (whose shape is `shape` with `axis` removed) back across
+/// `axis`, producing a gradient of the original (pre-reduction) `shape`. This
+/// is the vjp for a `sum`/`product` reduction along `axis`: every position
+/// along the reduced axis receives the same upstream gradient.
+async fn broadcast_axis_grad(
+    grad: BlockTensor,
+    shape: Shape,
+    axis: usize,
+    txn: Arc<Txn>,
+) -> TCResult<BlockTensor> {
+    let axis_len = shape[axis];
+    let txn_id = txn.id().clone();
+    let zero = BlockTensor::constant(txn, shape.clone(), grad.dtype().zero()).await?;
+
+    if axis == 0 {
+        for i in 0..axis_len {
+            zero.clone().write(txn_id.clone(), vec![i].into(), grad.clone()).await?;
+        }
+    } else {
+        let perm: Vec<usize> = iter::once(axis)
+            .chain((0..shape.len()).filter(|x| *x != axis))
+            .collect();
+
+        let transposed = zero.clone().transpose(Some(perm))?;
+        for i in 0..axis_len {
+            transposed
+                .clone()
+                .write(txn_id.clone(), vec![i].into(), grad.clone())
+                .await?;
+        }
+    }
+
+    Ok(zero)
+}
+
+/// Shared implementation behind `DenseTensorView::softmax`/`quiet_softmax`.
+/// `source` must already be floating-point; callers are responsible for the
+/// `bad_request` check on `dtype()` and on `axis`.
+async fn softmax_along(
+    source: BlockTensor,
+    txn: Arc<Txn>,
+    axis: usize,
+    quiet: bool,
+) -> TCResult<BlockTensor> {
+    let shape = source.shape().clone();
+    let max = reduce_extremum(source.clone(), txn.clone(), axis, true).await?;
+    let max = broadcast_axis_grad(max, shape.clone(), axis, txn.clone()).await?;
+
+    let shifted =
+        BlockTensor::combine(txn.clone(), source, max, |x, m| x.subtract(m)?.exp()).await?;
+
+    let mut denom = shifted.clone().sum(txn.clone(), axis).await?;
+    if quiet {
+        let one =
+            BlockTensor::constant(txn.clone(), denom.shape().clone(), denom.dtype().one())
+                .await?;
+        denom = BlockTensor::combine(txn.clone(), denom, one, |d, o| d.add(o)).await?;
+    }
+
+    let denom = broadcast_axis_grad(denom, shape, axis, txn.clone()).await?;
+
+    BlockTensor::combine(txn, shifted, denom, |n, d| n.divide(d)).await
+}
+
+/// A single recorded operation in an autodiff [`Tape`]: a [`Node`] remembers
+/// which op produced its value and the id(s) of the parent node(s) it was
+/// computed from, along with whatever operand values that op's vjp rule needs.
+#[derive(Clone)]
+enum GradOp {
+    Leaf,
+    Add,
+    Multiply(BlockTensor, BlockTensor),
+    Sum(Shape, usize),
+    // The product vjp is `upstream * (output / x_i)`--keeping the input
+    // around (rather than just its shape, as `Sum` does) is what lets
+    // `backward` divide back out the other factors along `axis` instead of
+    // only handling the case where they're all 1.
+    Product(BlockTensor, usize, BlockTensor),
+    Abs(BlockTensor),
+    AsDtype(NumberType),
+    Matmul(BlockTensor, BlockTensor),
+    StopGradient,
+}
+
+struct Node {
+    op: GradOp,
+    parents: Vec<usize>,
+    value: BlockTensor,
+}
+
+/// Records a forward computation over [`BlockTensor`]s so that a later call to
+/// [`Tracked::backward`] can compute gradients via reverse-mode automatic
+/// differentiation, the same approach used by e.g. Burn's `Autodiff` backend.
+#[derive(Clone)]
+pub struct Tape {
+    nodes: Arc<std::sync::Mutex<Vec<Node>>>,
+}
+
+impl Tape {
+    pub fn new() -> Self {
+        Self {
+            nodes: Arc::new(std::sync::Mutex::new(Vec::new())),
+        }
+    }
+
+    fn push(&self, op: GradOp, parents: Vec<usize>, value: BlockTensor) -> usize {
+        let mut nodes = self.nodes.lock().expect("autodiff tape lock");
+        nodes.push(Node { op, parents, value });
+        nodes.len() - 1
+    }
+}
+
+impl Default for Tape {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A [`BlockTensor`] tracked on a [`Tape`] for reverse-mode automatic
+/// differentiation. Every op performed on a `Tracked` value appends a node to
+/// its tape recording how the result was computed, so that [`Tracked::backward`]
+/// can later recover the gradient of every tensor that fed into it.
+#[derive(Clone)]
+pub struct Tracked {
+    tape: Tape,
+    id: usize,
+    value: BlockTensor,
+}
+
+impl Tracked {
+    /// Start tracking a new leaf tensor on `tape`.
+    pub fn leaf(tape: Tape, value: BlockTensor) -> Self {
+        let id = tape.push(GradOp::Leaf, vec![], value.clone());
+        Self { tape, id, value }
+    }
+
+    /// This node's id on its tape, usable as a key into the map returned by
+    /// [`Tracked::backward`].
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn value(&self) -> &BlockTensor {
+        &self.value
+    }
+
+    fn record(&self, op: GradOp, parents: Vec<usize>, value: BlockTensor) -> Self {
+        let id = self.tape.push(op, parents, value.clone());
+        Self {
+            tape: self.tape.clone(),
+            id,
+            value,
+        }
+    }
+
+    pub async fn add(self, other: Self, txn: Arc<Txn>) -> TCResult<Self> {
+        let value = self.value.clone().add(other.value.clone(), txn).await?;
+        let parents = vec![self.id, other.id];
+        Ok(self.record(GradOp::Add, parents, value))
+    }
+
+    pub async fn multiply(self, other: Self, txn: Arc<Txn>) -> TCResult<Self> {
+        let value = self
+            .value
+            .clone()
+            .multiply(other.value.clone(), txn)
+            .await?;
+        let parents = vec![self.id, other.id];
+        let op = GradOp::Multiply(self.value.clone(), other.value.clone());
+        Ok(self.record(op, parents, value))
+    }
+
+    pub async fn sum(self, txn: Arc<Txn>, axis: usize) -> TCResult<Self> {
+        let shape = self.value.shape().clone();
+        let value = self.value.clone().sum(txn, axis).await?;
+        let parents = vec![self.id];
+        Ok(self.record(GradOp::Sum(shape, axis), parents, value))
+    }
+
+    pub async fn product(self, txn: Arc<Txn>, axis: usize) -> TCResult<Self> {
+        let input = self.value.clone();
+        let value = self.value.clone().product(txn, axis).await?;
+        let parents = vec![self.id];
+        let op = GradOp::Product(input, axis, value.clone());
+        Ok(self.record(op, parents, value))
+    }
+
+    pub async fn abs(self, txn: Arc<Txn>) -> TCResult<Self> {
+        let value = self.value.clone().abs(txn).await?;
+        let parents = vec![self.id];
+        let op = GradOp::Abs(self.value.clone());
+        Ok(self.record(op, parents, value))
+    }
+
+    pub async fn as_dtype(self, txn: Arc<Txn>, dtype: NumberType) -> TCResult<Self> {
+        let source_dtype = self.value.dtype();
+        let value = self.value.clone().as_dtype(txn, dtype).await?;
+        let parents = vec![self.id];
+        Ok(self.record(GradOp::AsDtype(source_dtype), parents, value))
+    }
+
+    pub async fn matmul(self, other: Self, txn: Arc<Txn>) -> TCResult<Self> {
+        let value = self.value.clone().matmul(other.value.clone(), txn).await?;
+        let parents = vec![self.id, other.id];
+        let op = GradOp::Matmul(self.value.clone(), other.value.clone());
+        Ok(self.record(op, parents, value))
+    }
+
+    async fn compare(
+        self,
+        other: Self,
+        value: BlockTensor,
+    ) -> TCResult<Self> {
+        let parents = vec![self.id, other.id];
+        Ok(self.record(GradOp::StopGradient, parents, value))
+    }
+
+    pub async fn equals(self, other: Self, txn: Arc<Txn>) -> TCResult<Self> {
+        let value = self.value.clone().equals(other.value.clone(), txn).await?;
+        self.compare(other, value).await
+    }
+
+    pub async fn gt(self, other: Self, txn: Arc<Txn>) -> TCResult<Self> {
+        let value = self.value.clone().gt(other.value.clone(), txn).await?;
+        self.compare(other, value).await
+    }
+
+    pub async fn gte(self, other: Self, txn: Arc<Txn>) -> TCResult<Self> {
+        let value = self.value.clone().gte(other.value.clone(), txn).await?;
+        self.compare(other, value).await
+    }
+
+    pub async fn lt(self, other: Self, txn: Arc<Txn>) -> TCResult<Self> {
+        let value = self.value.clone().lt(other.value.clone(), txn).await?;
+        self.compare(other, value).await
+    }
+
+    pub async fn lte(self, other: Self, txn: Arc<Txn>) -> TCResult<Self> {
+        let value = self.value.clone().lte(other.value.clone(), txn).await?;
+        self.compare(other, value).await
+    }
+
+    /// Traverse this node's tape in reverse topological order, seeding the
+    /// output gradient with ones and propagating each op's vjp rule back to
+    /// its parents, summing contributions when a node has more than one
+    /// consumer. Returns the accumulated gradient of every leaf, keyed by
+    /// [`Tracked::id`].
+    pub async fn backward(self, txn: Arc<Txn>) -> TCResult<HashMap<usize, BlockTensor>> {
+        let nodes: Vec<Node> = {
+            let nodes = self.tape.nodes.lock().expect("autodiff tape lock");
+            nodes
+                .iter()
+                .map(|node| Node {
+                    op: node.op.clone(),
+                    parents: node.parents.clone(),
+                    value: node.value.clone(),
+                })
+                .collect()
+        };
+
+        let mut grads: HashMap<usize, BlockTensor> = HashMap::new();
+        let ones = BlockTensor::constant(
+            txn.clone(),
+            self.value.shape().clone(),
+            self.value.dtype().one(),
+        )
+        .await?;
+        grads.insert(self.id, ones);
+
+        let mut leaves: HashMap<usize, BlockTensor> = HashMap::new();
+
+        for (id, node) in nodes.into_iter().enumerate().rev() {
+            let upstream = match grads.remove(&id) {
+                Some(grad) => grad,
+                None => continue,
+            };
+
+            match node.op {
+                GradOp::Leaf => {
+                    leaves.insert(id, upstream);
+                }
+                GradOp::StopGradient => {}
+                GradOp::Add => {
+                    for parent in node.parents {
+                        accumulate(&mut grads, parent, upstream.clone(), txn.clone()).await?;
+                    }
+                }
+                GradOp::Multiply(left, right) => {
+                    let grad_left = upstream.clone().multiply(right, txn.clone()).await?;
+                    let grad_right = upstream.multiply(left, txn.clone()).await?;
+                    accumulate(&mut grads, node.parents[0], grad_left, txn.clone()).await?;
+                    accumulate(&mut grads, node.parents[1], grad_right, txn.clone()).await?;
+                }
+                GradOp::Sum(shape, axis) => {
+                    let grad = broadcast_axis_grad(upstream, shape, axis, txn.clone()).await?;
+                    accumulate(&mut grads, node.parents[0], grad, txn.clone()).await?;
+                }
+                GradOp::Product(input, axis, output) => {
+                    let scaled = upstream.multiply(output, txn.clone()).await?;
+                    let numerator =
+                        broadcast_axis_grad(scaled, input.shape().clone(), axis, txn.clone())
+                            .await?;
+                    let grad =
+                        BlockTensor::combine(txn.clone(), numerator, input, |n, d| n.divide(d))
+                            .await?;
+                    accumulate(&mut grads, node.parents[0], grad, txn.clone()).await?;
+                }
+                GradOp::Abs(input) => {
+                    // sign(x) = (x > 0) - (x < 0), built only from ops this tensor
+                    // type already supports (no divide is needed, unlike `Product`).
+                    let dtype = input.dtype();
+                    let shape = input.shape().clone();
+                    let zero = BlockTensor::constant(txn.clone(), shape.clone(), dtype.zero()).await?;
+                    let neg_one = BlockTensor::constant(txn.clone(), shape, dtype.zero() - dtype.one()).await?;
+
+                    let positive = input
+                        .clone()
+                        .gt(zero.clone(), txn.clone())
+                        .await?
+                        .as_dtype(txn.clone(), dtype)
+                        .await?;
+
+                    let negative = input
+                        .lt(zero, txn.clone())
+                        .await?
+                        .as_dtype(txn.clone(), dtype)
+                        .await?
+                        .multiply(neg_one, txn.clone())
+                        .await?;
+
+                    let sign = positive.add(negative, txn.clone()).await?;
+                    let grad = upstream.multiply(sign, txn.clone()).await?;
+                    accumulate(&mut grads, node.parents[0], grad, txn.clone()).await?;
+                }
+                GradOp::AsDtype(source_dtype) => {
+                    let grad = upstream.as_dtype(txn.clone(), source_dtype).await?;
+                    accumulate(&mut grads, node.parents[0], grad, txn.clone()).await?;
+                }
+                GradOp::Matmul(left, right) => {
+                    let right_t = right.clone().transpose(None)?;
+                    let left_t = left.clone().transpose(None)?;
+
+                    let grad_left = upstream.clone().matmul(right_t, txn.clone()).await?;
+                    let grad_right = left_t.matmul(upstream, txn.clone()).await?;
+
+                    accumulate(&mut grads, node.parents[0], grad_left, txn.clone()).await?;
+                    accumulate(&mut grads, node.parents[1], grad_right, txn.clone()).await?;
+                }
+            }
+        }
+
+        Ok(leaves)
+    }
+}
+
+/// Add `grad` into any gradient already accumulated for `id` (a node may have
+/// more than one consumer, so its upstream gradients must be summed before it
+/// is visited).
+async fn accumulate(
+    grads: &mut HashMap<usize, BlockTensor>,
+    id: usize,
+    grad: BlockTensor,
+    txn: Arc<Txn>,
+) -> TCResult<()> {
+    let grad = match grads.remove(&id) {
+        Some(existing) => existing.add(grad, txn).await?,
+        None => grad,
+    };
+
+    grads.insert(id, grad);
+    Ok(())
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
+use futures::future::try_join_all;
 use rand::Rng;
 
 use crate::cache::{Map, Set, Value};
@@ -58,6 +59,13 @@ impl Transaction {
         Self::of(TransactionId::new(host.time()), host)
     }
 
+    /// This transaction's id, so that a collection (e.g. `state::Graph`) can
+    /// scope its own reads and staged writes to this transaction without
+    /// otherwise depending on `Transaction`'s internals.
+    pub fn id(&self) -> &TransactionId {
+        &self.id
+    }
+
     pub fn include(
         self: Arc<Self>,
         name: String,
@@ -109,7 +117,7 @@ impl Transaction {
 
         self.state.set(State::Closed);
 
-        // TODO: resolve all child transactions
+        self.resolve_queue().await?;
 
         self.state.set(State::Resolved);
 
@@ -136,4 +144,87 @@ impl Transaction {
 
         Ok(results)
     }
+
+    /// Resolve every entry queued by `include`, in dependency order
+    /// (Kahn's algorithm): a queued `(name, (deps, f))` becomes ready once
+    /// every name in `deps` already has a value, either provided directly
+    /// (`provide`) or produced by an earlier pass of this same loop. Once
+    /// ready, `f` runs and its result is stored under `name`, unblocking
+    /// anything that was waiting on it. Nodes that become ready in the same
+    /// pass never depend on each other--each only depends on names resolved
+    /// in an earlier pass--so a pass runs its ready nodes concurrently,
+    /// each on its own thread, and only moves on once all of them finish.
+    ///
+    /// Fails with `bad_request` if some `deps` entry names a value this
+    /// transaction never heard of (it can never become ready), or if a
+    /// pass produces no newly-ready nodes while entries remain queued
+    /// (those entries' dependencies must form a cycle).
+    async fn resolve_queue(&self) -> TCResult<()> {
+        let mut pending: Vec<(String, Pending)> = self.queue.write().unwrap().drain(..).collect();
+
+        for (_, (deps, _)) in &pending {
+            for dep in deps {
+                if !self.known.contains(dep) && self.resolved.get(dep).is_none() {
+                    return Err(error::bad_request(
+                        "transaction depends on a value that was never provided",
+                        dep,
+                    ));
+                }
+            }
+        }
+
+        while !pending.is_empty() {
+            let (ready, not_ready): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .partition(|(_, (deps, _))| deps.iter().all(|dep| self.resolved.get(dep).is_some()));
+
+            if ready.is_empty() {
+                let stuck: Vec<String> = not_ready.into_iter().map(|(name, _)| name).collect();
+                return Err(error::bad_request(
+                    "transaction contains a circular dependency among",
+                    stuck.join(", "),
+                ));
+            }
+
+            // Each ready op runs on a blocking thread via `spawn_blocking` rather than a
+            // scoped thread, so waiting on a pass doesn't tie up a Tokio worker thread for
+            // its duration--this is itself an `async fn`, driven by the same runtime as
+            // everything else the host is doing concurrently.
+            let tasks: Vec<_> = ready
+                .into_iter()
+                .map(|(name, (deps, f))| {
+                    let inputs = self.gather(&deps);
+                    tokio::task::spawn_blocking(move || {
+                        let f = Arc::try_unwrap(f)
+                            .unwrap_or_else(|_| panic!("queued transaction op {} was shared", name));
+                        (name, f(inputs))
+                    })
+                })
+                .collect();
+
+            let results: Vec<(String, TCResult<Arc<TCState>>)> = try_join_all(tasks)
+                .await
+                .expect("queued transaction op panicked");
+
+            for (name, result) in results {
+                self.resolved.insert(name, result?);
+            }
+
+            pending = not_ready;
+        }
+
+        Ok(())
+    }
+
+    /// Collect the already-resolved values `deps` names, to pass into a
+    /// newly-ready queued op.
+    fn gather(&self, deps: &[String]) -> HashMap<String, TCState> {
+        deps.iter()
+            .filter_map(|dep| {
+                self.resolved
+                    .get(dep)
+                    .map(|value| (dep.clone(), (*value).clone()))
+            })
+            .collect()
+    }
 }
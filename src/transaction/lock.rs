@@ -1,12 +1,17 @@
-use std::cell::UnsafeCell;
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
 use std::ops::{Deref, DerefMut};
 use std::pin::Pin;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use async_trait::async_trait;
-use futures::future::{self, Future};
+use futures::future::Future;
 use futures::task::{Context, Poll, Waker};
+// Requires the `arc_lock` (for the owned `Arc*Guard` types held across
+// `.await` points below) and `send_guard` (so those guards are `Send`)
+// parking_lot features.
+use parking_lot::{ArcRwLockReadGuard, ArcRwLockWriteGuard, Mutex, RawRwLock, RwLock};
 
 use crate::error;
 use crate::value::TCResult;
@@ -18,199 +23,498 @@ pub trait Mutable: Clone + Send + Sync {
     async fn commit(&mut self, txn_id: &TxnId, new_value: Self);
 }
 
+// How many independent buckets the versioned value map (`ValueShards`) is
+// split across, so that reads/writes at distinct transaction ids don't
+// contend on the same lock--mirrors the sharding convention already used by
+// `crate::internal::cache::Map`.
+const VALUE_SHARDS: usize = 16;
+
+fn shard_of(txn_id: &TxnId) -> usize {
+    let mut hasher = DefaultHasher::new();
+    txn_id.hash(&mut hasher);
+    (hasher.finish() as usize) % VALUE_SHARDS
+}
+
+// A dashmap-style fixed array of `RwLock`-protected buckets holding one
+// version of `T` per live `TxnId`, keyed by a hash of the `TxnId` so that
+// concurrent readers (or a reader and a writer) at distinct transaction ids
+// land in different buckets instead of serializing through one lock. Each
+// bucket is wrapped in its own `Arc` so that `TxnLockReadGuard`/
+// `TxnLockWriteGuard` can hold an owned (`..._arc`) lock guard--with
+// parking_lot's `send_guard` feature enabled, that guard is `Send`, so it can
+// be held across an `.await` point, unlike a borrowed `std::sync::MutexGuard`.
+struct ValueShards<T: Mutable> {
+    shards: Vec<Arc<RwLock<HashMap<TxnId, T>>>>,
+}
+
+impl<T: Mutable> ValueShards<T> {
+    fn new() -> Self {
+        Self {
+            shards: (0..VALUE_SHARDS)
+                .map(|_| Arc::new(RwLock::new(HashMap::new())))
+                .collect(),
+        }
+    }
+
+    fn shard(&self, txn_id: &TxnId) -> &Arc<RwLock<HashMap<TxnId, T>>> {
+        &self.shards[shard_of(txn_id)]
+    }
+
+    fn contains_key(&self, txn_id: &TxnId) -> bool {
+        self.shard(txn_id).read().contains_key(txn_id)
+    }
+
+    // Inserts `value` for `txn_id` if no version is already present. Two
+    // separate transactions hashing to the same bucket can still race here
+    // the same way two keys in a `HashMap` always can under a shared lock;
+    // what sharding buys us is that the *common* case--distinct txn ids in
+    // distinct buckets--doesn't contend at all.
+    fn insert_if_missing(&self, txn_id: &TxnId, make_value: impl FnOnce() -> T) {
+        let mut bucket = self.shard(txn_id).write();
+        if !bucket.contains_key(txn_id) {
+            bucket.insert(txn_id.clone(), make_value());
+        }
+    }
+
+    fn remove(&self, txn_id: &TxnId) -> Option<T> {
+        self.shard(txn_id).write().remove(txn_id)
+    }
+
+    fn read_arc(&self, txn_id: &TxnId) -> ArcRwLockReadGuard<RawRwLock, HashMap<TxnId, T>> {
+        Arc::clone(self.shard(txn_id)).read_arc()
+    }
+
+    fn write_arc(&self, txn_id: &TxnId) -> ArcRwLockWriteGuard<RawRwLock, HashMap<TxnId, T>> {
+        Arc::clone(self.shard(txn_id)).write_arc()
+    }
+}
+
 pub struct TxnLockReadGuard<T: Mutable> {
     txn_id: TxnId,
     lock: TxnLock<T>,
+    shard: ArcRwLockReadGuard<RawRwLock, HashMap<TxnId, T>>,
 }
 
 impl<T: Mutable> Deref for TxnLockReadGuard<T> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        unsafe {
-            &*self
-                .lock
-                .inner
-                .lock()
-                .unwrap()
-                .value_at
-                .get(&self.txn_id)
-                .unwrap()
-                .get()
-        }
+        self.shard
+            .get(&self.txn_id)
+            .expect("value for this transaction")
     }
 }
 
 impl<T: Mutable> Drop for TxnLockReadGuard<T> {
     fn drop(&mut self) {
-        let lock = &mut self.lock.inner.lock().unwrap();
-        match lock.state.readers.get_mut(&self.txn_id) {
+        let mut state = self.lock.inner.state.lock();
+        match state.readers.get_mut(&self.txn_id) {
             Some(count) if *count > 1 => (*count) -= 1,
             Some(1) => {
-                lock.state.readers.remove(&self.txn_id);
-
-                while let Some(waker) = lock.state.wakers.pop_front() {
-                    waker.wake()
-                }
+                state.readers.remove(&self.txn_id);
+                state.wake_next();
+            }
+            _ => panic!("TxnLockReadGuard count updated incorrectly!"),
+        }
+    }
+}
 
-                lock.state.wakers.shrink_to_fit()
+impl<T: Mutable> TxnLockReadGuard<T> {
+    /// Atomically transitions this held read into a write reservation for
+    /// the same transaction, instead of dropping the read (which would
+    /// release into the general waker pool via `wake_next`) and separately
+    /// racing for a fresh write--a queued waiter could slip into that gap
+    /// and take the slot first. Fails with `conflict()` if a newer reader
+    /// is already present, or if some other transaction already holds the
+    /// write reservation: the same two conditions `try_write` checks, except
+    /// that where `try_write` may wound the other transaction and wait,
+    /// `upgrade` fails at once.
+    pub fn upgrade(self) -> TxnLockUpgradeFuture<T> {
+        // `ManuallyDrop` suppresses this guard's own `Drop` impl so we can
+        // tear it down ourselves (decrementing the reader count without
+        // waking anyone yet) instead of going through the general release
+        // path. `ptr::read` is safe here because `guard` is never used or
+        // dropped again after this point--`ManuallyDrop` guarantees that.
+        let mut guard = std::mem::ManuallyDrop::new(self);
+        let txn_id = guard.txn_id.clone();
+        let lock = guard.lock.clone();
+        let shard = unsafe { std::ptr::read(&guard.shard) };
+        drop(shard);
+
+        let mut state = lock.inner.state.lock();
+        match state.readers.get_mut(&txn_id) {
+            Some(count) if *count > 1 => (*count) -= 1,
+            Some(1) => {
+                state.readers.remove(&txn_id);
             }
             _ => panic!("TxnLockReadGuard count updated incorrectly!"),
         }
+
+        let newer_reader = state.readers.keys().max().cloned();
+        let result = if newer_reader.map(|reader| reader > txn_id).unwrap_or(false) {
+            Err(error::conflict())
+        } else if state
+            .reserved
+            .as_ref()
+            .map(|reserved| reserved != &txn_id)
+            .unwrap_or(false)
+        {
+            Err(error::conflict())
+        } else {
+            state.writer = true;
+            state.reserved = Some(txn_id.clone());
+            drop(state);
+
+            Ok(TxnLockWriteGuard {
+                shard: lock.inner.values.write_arc(&txn_id),
+                txn_id,
+                lock,
+            })
+        };
+
+        TxnLockUpgradeFuture {
+            result: Some(result),
+        }
+    }
+}
+
+pub struct TxnLockUpgradeFuture<T: Mutable> {
+    result: Option<TCResult<TxnLockWriteGuard<T>>>,
+}
+
+impl<T: Mutable> Future for TxnLockUpgradeFuture<T> {
+    type Output = TCResult<TxnLockWriteGuard<T>>;
+
+    fn poll(self: Pin<&mut Self>, _context: &mut Context) -> Poll<Self::Output> {
+        Poll::Ready(
+            self.get_mut()
+                .result
+                .take()
+                .expect("TxnLockUpgradeFuture polled after completion"),
+        )
     }
 }
 
 pub struct TxnLockWriteGuard<T: Mutable> {
     txn_id: TxnId,
     lock: TxnLock<T>,
+    shard: ArcRwLockWriteGuard<RawRwLock, HashMap<TxnId, T>>,
 }
 
 impl<T: Mutable> Deref for TxnLockWriteGuard<T> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        unsafe {
-            &*self
-                .lock
-                .inner
-                .lock()
-                .unwrap()
-                .value_at
-                .get(&self.txn_id)
-                .unwrap()
-                .get()
-        }
+        self.shard
+            .get(&self.txn_id)
+            .expect("value for this transaction")
     }
 }
 
 impl<T: Mutable> DerefMut for TxnLockWriteGuard<T> {
     fn deref_mut(&mut self) -> &mut T {
-        unsafe {
-            &mut *self
-                .lock
-                .inner
-                .lock()
-                .unwrap()
-                .value_at
-                .get_mut(&self.txn_id)
-                .unwrap()
-                .get()
-        }
+        self.shard
+            .get_mut(&self.txn_id)
+            .expect("value for this transaction")
     }
 }
 
 impl<T: Mutable> Drop for TxnLockWriteGuard<T> {
     fn drop(&mut self) {
-        let lock = &mut self.lock.inner.lock().unwrap();
-        lock.state.writer = false;
+        let mut state = self.lock.inner.state.lock();
+        state.writer = false;
+        state.wake_next();
+    }
+}
 
-        while let Some(waker) = lock.state.wakers.pop_front() {
-            waker.wake()
+impl<T: Mutable> TxnLockWriteGuard<T> {
+    /// Symmetric to `TxnLockReadGuard::upgrade`: atomically transitions this
+    /// held write reservation into a read for `txn_id`, clearing
+    /// `reserved`/`writer` and registering the read in the same critical
+    /// section, so no other waiter can observe the lock as briefly
+    /// unreserved-but-also-unread in between.
+    pub fn downgrade(self, txn_id: &TxnId) -> TxnLockDowngradeFuture<T> {
+        // See `TxnLockReadGuard::upgrade` for why `ManuallyDrop` + `ptr::read`
+        // here is safe: `guard` is never touched again after this point.
+        let mut guard = std::mem::ManuallyDrop::new(self);
+        let lock = guard.lock.clone();
+        let shard = unsafe { std::ptr::read(&guard.shard) };
+        drop(shard);
+
+        let mut state = lock.inner.state.lock();
+        state.writer = false;
+        state.reserved = None;
+
+        if !lock.inner.values.contains_key(txn_id) {
+            // Shouldn't happen--the write's value is still present under
+            // this `txn_id`--but stay defensive rather than panic.
+            let starting_value = state.value.clone();
+            lock.inner
+                .values
+                .insert_if_missing(txn_id, || starting_value);
         }
 
-        lock.state.wakers.shrink_to_fit();
+        *state.readers.entry(txn_id.clone()).or_insert(0) += 1;
+
+        // Relaxing from a write reservation to a read can unblock other
+        // queued readers and writers, unlike `upgrade` (which only ever
+        // tightens the lock), so it's appropriate to offer the general
+        // waker pool a chance here.
+        state.wake_next();
+        drop(state);
+
+        TxnLockDowngradeFuture {
+            result: Some(Ok(TxnLockReadGuard {
+                txn_id: txn_id.clone(),
+                shard: lock.inner.values.read_arc(txn_id),
+                lock,
+            })),
+        }
+    }
+}
+
+pub struct TxnLockDowngradeFuture<T: Mutable> {
+    result: Option<TCResult<TxnLockReadGuard<T>>>,
+}
+
+impl<T: Mutable> Future for TxnLockDowngradeFuture<T> {
+    type Output = TCResult<TxnLockReadGuard<T>>;
+
+    fn poll(self: Pin<&mut Self>, _context: &mut Context) -> Poll<Self::Output> {
+        Poll::Ready(
+            self.get_mut()
+                .result
+                .take()
+                .expect("TxnLockDowngradeFuture polled after completion"),
+        )
     }
 }
 
-struct LockState {
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum WaitKind {
+    Read,
+    Write,
+}
+
+// A queued waiter, in arrival order--see `LockState::wake_next` for how this
+// ordering is used to prevent writer starvation.
+struct Waiter {
+    waker: Waker,
+    kind: WaitKind,
+}
+
+// Coordination metadata for a `TxnLock`: who's reading, who (if anyone) is
+// reserved to write, and who's waiting. Kept behind its own `parking_lot`
+// mutex, separate from the versioned value data in `ValueShards`, so that
+// the bookkeeping checked on every `try_read`/`try_write` call doesn't
+// serialize behind (or block) the per-transaction value reads/writes that
+// `ValueShards` now spreads across independent buckets.
+struct LockState<T: Mutable> {
     last_commit: TxnId,
     readers: BTreeMap<TxnId, usize>,
     reserved: Option<TxnId>,
     writer: bool,
-    wakers: VecDeque<Waker>,
+    waiters: VecDeque<Waiter>,
+    // Transactions marked for forced rollback under the wound-wait protocol
+    // (see `TxnLock::try_read`/`try_write`)--checked by `commit`, which
+    // turns a wounded transaction's commit into an abort instead of
+    // applying it. A wound is only ever recorded against the *younger* of
+    // the two contending transactions (smaller `TxnId` = older = higher
+    // priority), so a wound can never point back at an older transaction
+    // that wounded it in turn--there is no cycle for a deadlock to form
+    // out of, only ever-forward motion toward progress for the oldest
+    // contender.
+    wounded: BTreeSet<TxnId>,
+    // The canonical, last-committed value. Mutated only by `commit`, which
+    // already holds this same mutex, so it doesn't need a shard of its own.
+    value: T,
+    // Committed snapshots older than `last_commit`, kept for point-in-time
+    // reads--see `TxnLock::with_history`. Bounded to at most `history_limit`
+    // entries (`history_limit` is `0` for a plain `new` lock, so this stays
+    // empty and costs nothing beyond the one check in `try_read`).
+    history: BTreeMap<TxnId, T>,
+    history_limit: usize,
+}
+
+impl<T: Mutable> LockState<T> {
+    // Wake the longest-waiting prefix of `waiters` that can be given a
+    // chance to proceed together: a run of consecutive `Read` waiters (they
+    // don't conflict with each other), followed by (if the run ends on a
+    // `Write`) that single write waiter--since by that point every waiter
+    // ahead of it has already been woken, it has reached the head of the
+    // line. A write is never skipped over to wake reads queued behind it, so
+    // a writer can't be starved by a steady stream of later-arriving
+    // readers: once it's at the front it keeps being the first one offered
+    // a chance to proceed on every subsequent release, ahead of anything
+    // behind it.
+    //
+    // Waking a waiter here removes it from the queue; if it re-polls and
+    // still can't acquire the lock (the actual conflict rules in
+    // `try_read`/`try_write` are authoritative, not this queue), it
+    // re-inserts itself at the *front* rather than the back, so it doesn't
+    // lose its place to a new arrival--see `queued` on the two wait futures
+    // below.
+    fn wake_next(&mut self) {
+        while let Some(front) = self.waiters.front() {
+            let kind = front.kind;
+            let waiter = self.waiters.pop_front().expect("front waiter");
+            waiter.waker.wake();
+
+            if kind == WaitKind::Write {
+                break;
+            }
+        }
+
+        self.waiters.shrink_to_fit();
+    }
 }
 
 struct Inner<T: Mutable> {
-    state: LockState,
-    value: UnsafeCell<T>,
-    value_at: BTreeMap<TxnId, UnsafeCell<T>>,
+    state: Mutex<LockState<T>>,
+    values: ValueShards<T>,
 }
 
 #[derive(Clone)]
 pub struct TxnLock<T: Mutable> {
-    inner: Arc<Mutex<Inner<T>>>,
+    inner: Arc<Inner<T>>,
 }
 
 impl<T: Mutable> TxnLock<T> {
     pub fn new(last_commit: TxnId, value: T) -> TxnLock<T> {
+        Self::with_history(last_commit, value, 0)
+    }
+
+    /// Like `new`, but retains up to `retention` committed snapshots older
+    /// than `last_commit` (see `LockState::history`), so `try_read` can
+    /// still serve a `txn_id` that's since been superseded by `retention`
+    /// further commits instead of erroring with `conflict()`. `retention: 0`
+    /// behaves exactly like `new`.
+    pub fn with_history(last_commit: TxnId, value: T, retention: usize) -> TxnLock<T> {
         let state = LockState {
             last_commit,
             readers: BTreeMap::new(),
             reserved: None,
             writer: false,
-            wakers: VecDeque::new(),
+            waiters: VecDeque::new(),
+            wounded: BTreeSet::new(),
+            value,
+            history: BTreeMap::new(),
+            history_limit: retention,
         };
 
         let inner = Inner {
-            state,
-            value: UnsafeCell::new(value),
-            value_at: BTreeMap::new(),
+            state: Mutex::new(state),
+            values: ValueShards::new(),
         };
 
         TxnLock {
-            inner: Arc::new(Mutex::new(inner)),
+            inner: Arc::new(inner),
         }
     }
 
     pub fn try_read<'a>(&self, txn_id: &'a TxnId) -> TCResult<Option<TxnLockReadGuard<T>>> {
-        let lock = &mut self.inner.lock().unwrap();
+        let mut state = self.inner.state.lock();
+
+        if txn_id < &state.last_commit && !self.inner.values.contains_key(txn_id) {
+            // Too old for the value shards to help--see if it's still
+            // within the retained history window instead of failing
+            // outright.
+            if let Some((_, historical)) = state.history.range(..=txn_id.clone()).next_back() {
+                let historical = historical.clone();
+                self.inner
+                    .values
+                    .insert_if_missing(txn_id, || historical.clone());
+            } else {
+                // If the requested time is too old, just return an error.
+                // We can't keep track of every historical version here.
+                return Err(error::conflict());
+            }
+        }
 
-        if txn_id < &lock.state.last_commit && !lock.value_at.contains_key(txn_id) {
-            // If the requested time is too old, just return an error.
-            // We can't keep track of every historical version here.
-            Err(error::conflict())
-        } else if lock.state.reserved.is_some() && txn_id >= lock.state.reserved.as_ref().unwrap() {
-            // If a writer can mutate the locked value at the requested time, wait it out.
-            Ok(None)
-        } else {
-            // Otherwise, return a ReadGuard.
-            if !lock.value_at.contains_key(txn_id) {
-                let value_at_txn_id = UnsafeCell::new(unsafe { (&*lock.value.get()).clone() });
-                lock.value_at.insert(txn_id.clone(), value_at_txn_id);
+        if let Some(reserved) = state.reserved.clone() {
+            if txn_id >= &reserved {
+                // The reserved writer is older (or the same transaction):
+                // wait it out.
+                return Ok(None);
             }
 
-            Ok(Some(TxnLockReadGuard {
-                txn_id: txn_id.clone(),
-                lock: self.clone(),
-            }))
+            // This reader is older than the reserved writer--under
+            // wound-wait it outranks it, so it wounds the writer (marking
+            // it for forced rollback at commit time) instead of waiting or
+            // conflicting, and reads the pre-write value immediately.
+            state.wounded.insert(reserved);
         }
+
+        // Otherwise, return a ReadGuard.
+        if !self.inner.values.contains_key(txn_id) {
+            let starting_value = state.value.clone();
+            self.inner
+                .values
+                .insert_if_missing(txn_id, || starting_value);
+        }
+
+        // Register this reader so `try_write`'s wound-wait check (and this
+        // guard's own `Drop`) see it--without this, `state.readers` never
+        // reflects an ordinary read at all.
+        *state.readers.entry(txn_id.clone()).or_insert(0) += 1;
+
+        Ok(Some(TxnLockReadGuard {
+            txn_id: txn_id.clone(),
+            lock: self.clone(),
+            shard: self.inner.values.read_arc(txn_id),
+        }))
     }
 
     pub fn read<'a>(&self, txn_id: &'a TxnId) -> TxnLockReadFuture<'a, T> {
         TxnLockReadFuture {
             txn_id,
             lock: self.clone(),
+            queued: false,
         }
     }
 
     pub fn try_write<'a>(&self, txn_id: &'a TxnId) -> TCResult<Option<TxnLockWriteGuard<T>>> {
-        let lock = &mut self.inner.lock().unwrap();
-        let latest_reader = lock.state.readers.keys().max();
-
-        if latest_reader.is_some() && latest_reader.unwrap() > txn_id {
-            // If there's already a reader in the future, there's no point in waiting.
-            return Err(error::conflict());
+        let mut state = self.inner.state.lock();
+        let latest_reader = state.readers.keys().max().cloned();
+
+        if let Some(reader) = latest_reader {
+            if &reader > txn_id {
+                // This writer is older than the youngest active reader--
+                // under wound-wait it outranks it, so it wounds the reader
+                // instead of aborting itself, and waits for the reader to
+                // release.
+                state.wounded.insert(reader);
+                return Ok(None);
+            }
         }
 
-        match &lock.state.reserved {
-            // If there's already a writer in the future, there's no point in waiting.
-            Some(current_txn) if current_txn > txn_id => Err(error::conflict()),
+        let reserved = state.reserved.clone();
+        match &reserved {
+            // This writer is older than the reserved writer: wound it
+            // instead of conflicting, and wait for it to release.
+            Some(current_txn) if current_txn > txn_id => {
+                state.wounded.insert(current_txn.clone());
+                Ok(None)
+            }
             // If there's a writer in the past, wait for it to complete.
             Some(current_txn) if current_txn < txn_id => Ok(None),
             // If there's already a writer for the current transaction, wait for it to complete.
-            Some(_) if lock.state.writer => Ok(None),
+            Some(_) if state.writer => Ok(None),
             _ => {
                 // Otherwise, copy the value to be mutated in this transaction.
-                lock.state.writer = true;
-                lock.state.reserved = Some(txn_id.clone());
-                if !lock.value_at.contains_key(txn_id) {
-                    let mutation = UnsafeCell::new(unsafe { (&*lock.value.get()).clone() });
-                    lock.value_at.insert(txn_id.clone(), mutation);
+                state.writer = true;
+                state.reserved = Some(txn_id.clone());
+                if !self.inner.values.contains_key(txn_id) {
+                    let starting_value = state.value.clone();
+                    self.inner
+                        .values
+                        .insert_if_missing(txn_id, || starting_value);
                 }
 
                 Ok(Some(TxnLockWriteGuard {
                     txn_id: txn_id.clone(),
                     lock: self.clone(),
+                    shard: self.inner.values.write_arc(txn_id),
                 }))
             }
         }
@@ -220,6 +524,22 @@ impl<T: Mutable> TxnLock<T> {
         TxnLockWriteFuture {
             txn_id,
             lock: self.clone(),
+            queued: false,
+        }
+    }
+
+    /// Returns `Err(error::conflict())` if `txn_id` was wounded by an older
+    /// transaction under the wound-wait protocol (see `try_read`/
+    /// `try_write`), without applying or discarding anything. `commit` makes
+    /// this same check itself before proceeding, but since `Transact::commit`
+    /// can't return a result of its own, a caller that wants to distinguish
+    /// "committed" from "aborted because wounded" (in order to retry under a
+    /// fresh `TxnId`) should call this first.
+    pub fn check_wounded(&self, txn_id: &TxnId) -> TCResult<()> {
+        if self.inner.state.lock().wounded.contains(txn_id) {
+            Err(error::conflict())
+        } else {
+            Ok(())
         }
     }
 }
@@ -229,15 +549,42 @@ impl<T: Mutable> Transact for TxnLock<T> {
     async fn commit(&self, txn_id: &TxnId) {
         async {
             let _ = self.write(txn_id).await; // prevent any more writes
-            let lock = &mut self.inner.lock().unwrap();
-            lock.state.last_commit = txn_id.clone();
-            lock.state.reserved = None;
+            let mut state = self.inner.state.lock();
+
+            if state.wounded.remove(txn_id) {
+                // Wounded by an older transaction while this one held (or
+                // was waiting on) the lock--abort rather than commit, so the
+                // caller can retry under a fresh, younger `TxnId`. Wounds
+                // only ever flow from an older transaction to a younger one
+                // (see `LockState::wounded`), so this can't be the far end
+                // of a wait-cycle: the transaction that wounded us can't
+                // also be waiting on us to release.
+                self.inner.values.remove(txn_id);
+                state.writer = false;
+                state.reserved = None;
+                state.wake_next();
+                return;
+            }
 
-            let value = unsafe { &mut *lock.value.get() };
-            if let Some(new_value) = lock.value_at.remove(txn_id) {
-                value.commit(txn_id, new_value.into_inner())
-            } else {
-                Box::pin(future::ready(()))
+            if state.history_limit > 0 {
+                let retiring_value = state.value.clone();
+                let retiring_commit = state.last_commit.clone();
+                state.history.insert(retiring_commit, retiring_value);
+
+                while state.history.len() > state.history_limit {
+                    let oldest = state.history.keys().next().cloned();
+                    match oldest {
+                        Some(oldest) => state.history.remove(&oldest),
+                        None => break,
+                    };
+                }
+            }
+
+            state.last_commit = txn_id.clone();
+            state.reserved = None;
+
+            if let Some(new_value) = self.inner.values.remove(txn_id) {
+                state.value.commit(txn_id, new_value).await
             }
         }
         .await;
@@ -245,31 +592,43 @@ impl<T: Mutable> Transact for TxnLock<T> {
 
     async fn rollback(&self, txn_id: &TxnId) {
         let _ = self.write(txn_id).await; // prevent any more writes
-        let lock = &mut self.inner.lock().unwrap();
-        lock.value_at.remove(txn_id);
+        self.inner.state.lock().reserved = None;
+        self.inner.values.remove(txn_id);
     }
 }
 
 pub struct TxnLockReadFuture<'a, T: Mutable> {
     txn_id: &'a TxnId,
     lock: TxnLock<T>,
+    // Has this future already taken a ticket in `waiters`? Its first
+    // `Pending` joins the back of the line like any new arrival; if
+    // `wake_next` later wakes it and it still can't acquire the lock, it
+    // re-queues itself at the *front* instead, so it doesn't lose its place
+    // in line to a waiter that showed up after it did.
+    queued: bool,
 }
 
 impl<'a, T: Mutable> Future for TxnLockReadFuture<'a, T> {
     type Output = TCResult<TxnLockReadGuard<T>>;
 
     fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
-        match self.lock.try_read(self.txn_id) {
+        let this = self.get_mut();
+        match this.lock.try_read(this.txn_id) {
             Ok(Some(guard)) => Poll::Ready(Ok(guard)),
             Err(cause) => Poll::Ready(Err(cause)),
             Ok(None) => {
-                self.lock
-                    .inner
-                    .lock()
-                    .unwrap()
-                    .state
-                    .wakers
-                    .push_back(context.waker().clone());
+                let waiter = Waiter {
+                    waker: context.waker().clone(),
+                    kind: WaitKind::Read,
+                };
+
+                let mut state = this.lock.inner.state.lock();
+                if this.queued {
+                    state.waiters.push_front(waiter);
+                } else {
+                    state.waiters.push_back(waiter);
+                    this.queued = true;
+                }
 
                 Poll::Pending
             }
@@ -280,26 +639,90 @@ impl<'a, T: Mutable> Future for TxnLockReadFuture<'a, T> {
 pub struct TxnLockWriteFuture<'a, T: Mutable> {
     txn_id: &'a TxnId,
     lock: TxnLock<T>,
+    // See `TxnLockReadFuture::queued`.
+    queued: bool,
 }
 
 impl<'a, T: Mutable> Future for TxnLockWriteFuture<'a, T> {
     type Output = TCResult<TxnLockWriteGuard<T>>;
 
     fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
-        match self.lock.try_write(self.txn_id) {
+        let this = self.get_mut();
+        match this.lock.try_write(this.txn_id) {
             Ok(Some(guard)) => Poll::Ready(Ok(guard)),
             Err(cause) => Poll::Ready(Err(cause)),
             Ok(None) => {
-                self.lock
-                    .inner
-                    .lock()
-                    .unwrap()
-                    .state
-                    .wakers
-                    .push_back(context.waker().clone());
+                let waiter = Waiter {
+                    waker: context.waker().clone(),
+                    kind: WaitKind::Write,
+                };
+
+                let mut state = this.lock.inner.state.lock();
+                if this.queued {
+                    state.waiters.push_front(waiter);
+                } else {
+                    state.waiters.push_back(waiter);
+                    this.queued = true;
+                }
 
                 Poll::Pending
             }
         }
     }
 }
+
+/// Deferred work a collection can queue up while a transaction is still
+/// open, to run once that transaction's commit is durable--and only then,
+/// never on `rollback`. This is the hook point `TxnLock::commit` itself
+/// doesn't have room for: `Transact::commit` can't fail or return a value,
+/// so a collection that needs to do something *after* its own state is
+/// safely committed (rebuild an index, invalidate a cache, push to a
+/// replica) has nowhere to put that work without risking it running on an
+/// aborted transaction too. Registering it here instead defers it until
+/// `commit` actually drains this registry, and drops it unrun if `rollback`
+/// does instead.
+///
+/// Sharded by `TxnId` the same way as `ValueShards` above, since hooks
+/// registered under distinct transactions never contend with each other.
+pub struct CommitHooks {
+    shards: Vec<Mutex<HashMap<TxnId, Vec<Box<dyn FnOnce() + Send>>>>>,
+}
+
+impl CommitHooks {
+    pub fn new() -> CommitHooks {
+        CommitHooks {
+            shards: (0..VALUE_SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+        }
+    }
+
+    fn shard(&self, txn_id: &TxnId) -> &Mutex<HashMap<TxnId, Vec<Box<dyn FnOnce() + Send>>>> {
+        &self.shards[shard_of(txn_id)]
+    }
+
+    /// Queue `hook` to run after `txn_id` commits. Does nothing if `txn_id`
+    /// is rolled back instead--the caller never needs to check which
+    /// happened, since either way this is the only place the hook is kept.
+    pub fn on_commit(&self, txn_id: &TxnId, hook: Box<dyn FnOnce() + Send>) {
+        self.shard(txn_id)
+            .lock()
+            .entry(txn_id.clone())
+            .or_insert_with(Vec::new)
+            .push(hook);
+    }
+}
+
+#[async_trait]
+impl Transact for CommitHooks {
+    async fn commit(&self, txn_id: &TxnId) {
+        let hooks = self.shard(txn_id).lock().remove(txn_id);
+        if let Some(hooks) = hooks {
+            for hook in hooks {
+                hook();
+            }
+        }
+    }
+
+    async fn rollback(&self, txn_id: &TxnId) {
+        self.shard(txn_id).lock().remove(txn_id);
+    }
+}
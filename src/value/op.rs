@@ -161,7 +161,10 @@ impl fmt::Display for OpRefType {
 #[derive(Clone, Eq, PartialEq)]
 pub enum OpType {
     Def(OpDefType),
+    Filter,
     If,
+    Map,
+    Match,
     Method(MethodType),
     Ref(OpRefType),
 }
@@ -177,7 +180,10 @@ impl Class for OpType {
         } else {
             match suffix[0].as_str() {
                 "def" => OpDefType::from_path(path).map(OpType::Def),
+                "filter" if suffix.len() == 1 => Ok(OpType::Filter),
                 "if" if suffix.len() == 1 => Ok(OpType::If),
+                "map" if suffix.len() == 1 => Ok(OpType::Map),
+                "match" if suffix.len() == 1 => Ok(OpType::Match),
                 "method" => MethodType::from_path(path).map(OpType::Method),
                 "ref" => OpRefType::from_path(path).map(OpType::Ref),
                 other => Err(error::not_found(other)),
@@ -210,7 +216,10 @@ impl From<OpType> for Link {
         let prefix = OpType::prefix();
         match ot {
             OpType::Def(odt) => odt.into(),
+            OpType::Filter => prefix.join(label("filter").into()).into(),
             OpType::If => prefix.join(label("if").into()).into(),
+            OpType::Map => prefix.join(label("map").into()).into(),
+            OpType::Match => prefix.join(label("match").into()).into(),
             OpType::Method(mt) => mt.into(),
             OpType::Ref(ort) => ort.into(),
         }
@@ -221,7 +230,10 @@ impl fmt::Display for OpType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Def(odt) => write!(f, "{}", odt),
+            Self::Filter => write!(f, "type: Filter comprehension Op"),
             Self::If => write!(f, "type: Conditional Op"),
+            Self::Map => write!(f, "type: Map comprehension Op"),
+            Self::Match => write!(f, "type: Pattern-matching Op"),
             Self::Method(mt) => write!(f, "{}", mt),
             Self::Ref(ort) => write!(f, "{}", ort),
         }
@@ -233,6 +245,67 @@ pub type GetOp = (TCRef, Vec<(ValueId, Value)>);
 pub type PutOp = (TCRef, TCRef, Vec<(ValueId, Value)>);
 pub type PostOp = (Vec<TCRef>, Vec<(ValueId, Value)>);
 
+/// One arm's pattern in an [`Op::Match`], matched against a subject
+/// `Value` top-to-bottom alongside its sibling arms.
+#[derive(Clone, Eq, PartialEq)]
+pub enum Pattern {
+    /// Always matches, binding the subject under the given `ValueId`.
+    Bind(ValueId),
+    /// Always matches, binding nothing.
+    Discard,
+    /// Matches iff the subject equals the given `Value`.
+    Literal(Value),
+    /// Matches iff the subject is a `Value::Tuple` of the same length,
+    /// with every element matching the corresponding sub-pattern.
+    Tuple(Vec<Pattern>),
+}
+
+impl Pattern {
+    /// Try to match `subject` against this pattern, accumulating any
+    /// bindings it introduces into `bindings`. Returns `false` (leaving
+    /// `bindings` unspecified) on the first sub-pattern that fails to
+    /// match.
+    fn matches(&self, subject: &Value, bindings: &mut Vec<(ValueId, Value)>) -> bool {
+        match self {
+            Self::Bind(name) => {
+                bindings.push((name.clone(), subject.clone()));
+                true
+            }
+            Self::Discard => true,
+            Self::Literal(expected) => expected == subject,
+            Self::Tuple(patterns) => match subject {
+                Value::Tuple(values) if values.len() == patterns.len() => patterns
+                    .iter()
+                    .zip(values)
+                    .all(|(pattern, value)| pattern.matches(value, bindings)),
+                _ => false,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bind(name) => write!(f, "{}", name),
+            Self::Discard => write!(f, "_"),
+            Self::Literal(value) => write!(f, "{}", value),
+            Self::Tuple(patterns) => {
+                write!(f, "(")?;
+                for (i, pattern) in patterns.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{}", pattern)?;
+                }
+
+                write!(f, ")")
+            }
+        }
+    }
+}
+
 #[derive(Clone, Eq, PartialEq)]
 pub enum OpDef {
     Get(GetOp),
@@ -256,11 +329,14 @@ impl TryFrom<Value> for OpDef {
     type Error = error::TCError;
 
     fn try_from(value: Value) -> TCResult<OpDef> {
-        if let Ok(get_op) = value.clone().try_into() {
-            Ok(OpDef::Get(get_op))
+        let op_def = if let Ok(get_op) = value.clone().try_into() {
+            OpDef::Get(get_op)
         } else {
-            Err(error::bad_request("Expected OpDef but found", value))
-        }
+            return Err(error::bad_request("Expected OpDef but found", value));
+        };
+
+        Op::Def(op_def.clone()).type_check()?;
+        Ok(op_def)
     }
 }
 
@@ -303,6 +379,93 @@ impl fmt::Display for Method {
     }
 }
 
+/// What [`Method::resolve`] needs from the subject's resolved class in
+/// order to look a method up on it: a lookup table from a method's own
+/// `TCPath` (relative to the class, the same way `OpDefType::from_path`
+/// strips `Self::prefix()` before matching) to its defining `OpDef`, plus
+/// the class this one inherits from, if any. `crate::class::Class` (not
+/// part of this tree--see its `use` above) only offers `from_path` and
+/// `prefix`, with no method registry or inheritance link of its own, so
+/// this is the minimal extension a `Class` needs alongside it to make
+/// `Method::resolve`'s inheritance fallback possible at all.
+pub trait ClassMethods: Class + Clone {
+    /// The methods defined directly on this class, not including
+    /// anything inherited from [`Self::parent`].
+    fn methods(&self) -> &std::collections::HashMap<TCPath, OpDef>;
+
+    /// The class `self` inherits from, if any. [`Method::resolve`] only
+    /// consults this once `path` isn't found among `self.methods()`,
+    /// walking from `self` up through its ancestors until a definition
+    /// turns up or the chain runs out.
+    fn parent(&self) -> Option<&Self>;
+}
+
+impl Method {
+    /// The `TCPath` this method names on its subject, regardless of
+    /// GET/PUT/POST arity.
+    fn path(&self) -> &TCPath {
+        match self {
+            Self::Get(_, path, _) => path,
+            Self::Put(_, path, _, _) => path,
+            Self::Post(_, path, _) => path,
+        }
+    }
+
+    /// Resolve this method's path against `class`--the subject's own
+    /// resolved class--first among the methods `class` defines directly,
+    /// then, if not found there, walking `class.parent()` and on up through
+    /// its ancestors (this tree has no multiple-inheritance concept, so a
+    /// single parent chain is all there is to walk, unlike the full
+    /// prefix hierarchy `Class::prefix` builds for a type's own path)
+    /// until a definition turns up or the chain is exhausted.
+    ///
+    /// A typo in `path`, or a path that simply isn't defined anywhere in
+    /// the chain, is exactly the silent-runtime-error case this is meant
+    /// to catch early: returns `error::not_found` listing every
+    /// `(path, receiver)` candidate that was searched, instead of letting
+    /// an invocation fail later with no context. On success, also checks
+    /// that the resolved `OpDef`'s own GET/PUT/POST arity matches this
+    /// `Method`'s--the validation `MethodType::from_path` can't do itself,
+    /// since at that point there's no `OpDef` yet to compare against.
+    pub fn resolve<C: ClassMethods>(&self, class: &C) -> TCResult<(OpDef, Link)>
+    where
+        Link: From<C>,
+    {
+        let path = self.path();
+        let mut candidates = Vec::new();
+        let mut current = class.clone();
+
+        loop {
+            if let Some(op_def) = current.methods().get(path) {
+                let arity_matches = matches!(
+                    (self.class(), op_def.class()),
+                    (MethodType::Get, OpDefType::Get)
+                        | (MethodType::Put, OpDefType::Put)
+                        | (MethodType::Post, OpDefType::Post)
+                );
+
+                if !arity_matches {
+                    return Err(error::bad_request(
+                        "method arity does not match its definition",
+                        format!("{} resolved to a {}", self, op_def.class()),
+                    ));
+                }
+
+                return Ok((op_def.clone(), Link::from(current)));
+            }
+
+            candidates.push(format!("{} on {}", path, Link::from(current.clone())));
+
+            match current.parent().cloned() {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+
+        Err(error::not_found(candidates.join(", ")))
+    }
+}
+
 #[derive(Clone, Eq, PartialEq)]
 pub enum OpRef {
     Get(Link, Value),
@@ -337,7 +500,16 @@ impl fmt::Display for OpRef {
 #[derive(Clone, Eq, PartialEq)]
 pub enum Op {
     Def(OpDef),
+    /// Apply `OpDef` (a `GetOp`) to every element of the collection `TCRef`
+    /// resolves to, keeping only the elements for which it evaluates to a
+    /// truthy `Value`--see `Op::comprehend`.
+    Filter(TCRef, GetOp),
     If(Cond),
+    /// Apply `OpDef` (a `GetOp`) to every element of the collection `TCRef`
+    /// resolves to, collecting the results into a tuple in input
+    /// order--see `Op::comprehend`.
+    Map(TCRef, GetOp),
+    Match(TCRef, Vec<(Pattern, Value)>),
     Method(Method),
     Ref(OpRef),
 }
@@ -348,7 +520,10 @@ impl Instance for Op {
     fn class(&self) -> OpType {
         match self {
             Self::Def(op_def) => OpType::Def(op_def.class()),
+            Self::Filter(_, _) => OpType::Filter,
             Self::If(_) => OpType::If,
+            Self::Map(_, _) => OpType::Map,
+            Self::Match(_, _) => OpType::Match,
             Self::Method(method) => OpType::Method(method.class()),
             Self::Ref(op_ref) => OpType::Ref(op_ref.class()),
         }
@@ -392,13 +567,450 @@ impl fmt::Display for Op {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Op::Def(op_def) => write!(f, "{}", op_def),
+            Op::Filter(source, (key, _)) => write!(f, "Op::Filter({} for {})", source, key),
             Op::If((cond, then, or_else)) => write!(
                 f,
                 "Op::If({} then {{ {} }} else {{ {} }})",
                 cond, then, or_else
             ),
+            Op::Map(source, (key, _)) => write!(f, "Op::Map({} for {})", source, key),
+            Op::Match(subject, arms) => {
+                write!(f, "Op::Match[{}](", subject)?;
+                for (i, (pattern, result)) in arms.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{} => {}", pattern, result)?;
+                }
+
+                write!(f, ")")
+            }
             Op::Method(method) => write!(f, "{}", method),
             Op::Ref(op_ref) => write!(f, "{}", op_ref),
         }
     }
 }
+
+/// A type variable tracked by [`TypeChecker`]'s union-find, identified by
+/// its index into `TypeChecker::parent`/`TypeChecker::known`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+struct TypeVar(usize);
+
+/// A union-find over the type variables of one `OpDef`/`Method` body,
+/// used by [`Op::type_check`] to verify that every `TCRef` a binding
+/// embeds resolves to an earlier binding (or a declared input) of a
+/// compatible type.
+///
+/// Each binding in the body gets a fresh type variable when it's checked;
+/// each `TCRef` a binding's `Value` contains is unified against the type
+/// variable of the binding (or input) it names. Two variables that end up
+/// in the same equivalence class must agree on their known `ValueType`,
+/// if either has one--this is the same invariant a Hindley-Milner-style
+/// checker enforces for let-bindings, scaled down to this op IR's flat,
+/// ordered binding list.
+struct TypeChecker {
+    parent: Vec<usize>,
+    known: Vec<Option<ValueType>>,
+    scope: std::collections::HashMap<ValueId, TypeVar>,
+}
+
+impl TypeChecker {
+    fn new() -> Self {
+        Self {
+            parent: Vec::new(),
+            known: Vec::new(),
+            scope: std::collections::HashMap::new(),
+        }
+    }
+
+    /// Allocate a fresh type variable, in its own equivalence class,
+    /// optionally seeded with a known `ValueType`.
+    fn fresh(&mut self, known: Option<ValueType>) -> TypeVar {
+        let var = self.parent.len();
+        self.parent.push(var);
+        self.known.push(known);
+        TypeVar(var)
+    }
+
+    /// Find the representative of `var`'s equivalence class, compressing
+    /// the path from `var` to its root as it goes.
+    fn find(&mut self, var: TypeVar) -> TypeVar {
+        let TypeVar(i) = var;
+        if self.parent[i] != i {
+            let root = self.find(TypeVar(self.parent[i]));
+            self.parent[i] = root.0;
+        }
+
+        TypeVar(self.parent[i])
+    }
+
+    /// Merge `a` and `b`'s equivalence classes, erroring if both already
+    /// carry a known `ValueType` and the two disagree.
+    fn unify(&mut self, a: TypeVar, b: TypeVar) -> TCResult<()> {
+        let a = self.find(a);
+        let b = self.find(b);
+        if a == b {
+            return Ok(());
+        }
+
+        match (self.known[a.0].clone(), self.known[b.0].clone()) {
+            (Some(left), Some(right)) if left != right => {
+                return Err(error::bad_request(
+                    "type mismatch in Op body",
+                    format!("{} is not the same type as {}", left, right),
+                ));
+            }
+            (None, known @ Some(_)) => self.known[a.0] = known,
+            _ => {}
+        }
+
+        self.parent[b.0] = a.0;
+        Ok(())
+    }
+
+    /// Bring `name` into scope bound to a fresh type variable, returning
+    /// that variable so the caller can unify it against the bound value's
+    /// own references.
+    fn bind(&mut self, name: ValueId, known: Option<ValueType>) -> TCResult<TypeVar> {
+        if self.scope.contains_key(&name) {
+            return Err(error::bad_request(
+                "duplicate binding in Op body",
+                name,
+            ));
+        }
+
+        let var = self.fresh(known);
+        self.scope.insert(name, var);
+        Ok(var)
+    }
+
+    /// Resolve `reference` against the bindings and inputs currently in
+    /// scope, or error if it names nothing bound before it--either a
+    /// forward reference to a later binding, or a reference to an
+    /// undefined name entirely.
+    fn resolve(&self, reference: &TCRef) -> TCResult<TypeVar> {
+        self.scope
+            .get(reference.value_id())
+            .copied()
+            .ok_or_else(|| error::bad_request("undefined reference in Op body", reference))
+    }
+
+    /// Unify `var` against every `TCRef` embedded in `value`, recursively
+    /// walking nested `Value::Tuple`s.
+    ///
+    /// This assumes `Value` embeds a reference as `Value::TCRef`, the
+    /// same way `Value::Tuple` is the only other composite variant this
+    /// file's existing code (see `OpRef`'s `Display` impl, above) relies
+    /// on--`Value`'s definition isn't part of this tree to confirm against.
+    fn check_refs(&mut self, value: &Value, var: TypeVar) -> TCResult<()> {
+        match value {
+            Value::TCRef(reference) => {
+                let referent = self.resolve(reference)?;
+                self.unify(var, referent)
+            }
+            Value::Tuple(values) => {
+                for item in values {
+                    self.check_refs(item, var)?;
+                }
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Type-check one `GetOp`/`PutOp`/`PostOp`-shaped body: `inputs` are
+    /// the op's declared parameters, already in scope before the first
+    /// binding is checked; `bindings` is the ordered `let`-list making up
+    /// the rest of the body.
+    fn check_body(&mut self, inputs: &[ValueId], bindings: &[(ValueId, Value)]) -> TCResult<()> {
+        for input in inputs {
+            self.bind(input.clone(), None)?;
+        }
+
+        for (name, value) in bindings {
+            let var = self.fresh(None);
+            self.check_refs(value, var)?;
+            self.bind(name.clone(), None)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Op {
+    /// Statically verify that this op's body is well-typed: every
+    /// `TCRef` a binding embeds must resolve to an earlier binding or to
+    /// the op's declared inputs (the key, for `Get`; the key and value,
+    /// for `Put`; the arg tuple, for `Post`), and no two bindings that
+    /// unify to the same type variable may disagree on their declared
+    /// `ValueType`. Run automatically when an `OpDef` is parsed--see
+    /// `OpDef`'s `TryFrom<Value>` impl, above.
+    pub fn type_check(&self) -> TCResult<()> {
+        match self {
+            Self::Def(OpDef::Get((key, bindings))) => {
+                let mut checker = TypeChecker::new();
+                checker.check_body(&[key.value_id().clone()], bindings)
+            }
+            Self::Def(OpDef::Put((key, value, bindings))) => {
+                let mut checker = TypeChecker::new();
+                let inputs = [key.value_id().clone(), value.value_id().clone()];
+                checker.check_body(&inputs, bindings)
+            }
+            Self::Def(OpDef::Post((params, bindings))) => {
+                let mut checker = TypeChecker::new();
+                let inputs: Vec<ValueId> = params.iter().map(|r| r.value_id().clone()).collect();
+                checker.check_body(&inputs, bindings)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Evaluate a `Match` op's arms against `subject`, trying each arm's
+    /// `Pattern` in order and returning the first match's (un-evaluated)
+    /// result `Value` along with the bindings its pattern introduced, or
+    /// `error::bad_request` if no arm matches.
+    ///
+    /// This only selects the matching arm and collects its bindings--it
+    /// doesn't go on to evaluate the result `Value` with those bindings
+    /// in scope, since this tree has no general `Op`/`Value` evaluator to
+    /// hand that off to.
+    pub fn match_arm(
+        subject: &Value,
+        arms: &[(Pattern, Value)],
+    ) -> TCResult<(Value, Vec<(ValueId, Value)>)> {
+        for (pattern, result) in arms {
+            let mut bindings = Vec::new();
+            if pattern.matches(subject, &mut bindings) {
+                return Ok((result.clone(), bindings));
+            }
+        }
+
+        Err(error::bad_request("no Pattern matched value", subject))
+    }
+
+    /// Fold `sub_op` (a `Map`/`Filter`'s `GetOp`) over `source`--a
+    /// `Value::Tuple`, or a single bare `Value` treated as a one-element
+    /// collection--binding each element in turn to `sub_op`'s declared key
+    /// and checking that its body is well-typed under that binding (the
+    /// same check `type_check` runs once for a whole `OpDef`, here repeated
+    /// per element since each one binds the key to a different value).
+    ///
+    /// Returns each source element paired with its body's resolved scope,
+    /// in input order, ready for an evaluator to actually run--this tree
+    /// has no general `Op`/`Value` evaluator to hand that off to (see the
+    /// same gap noted on `match_arm`, above), so `Op::Map` can collect the
+    /// resolved bodies but not yet the values they'd produce, and
+    /// `Op::Filter` can't yet test a body's result to decide what to keep.
+    /// Aborts with `error::bad_request` naming the offending index on the
+    /// first element whose body fails to type-check or resolve.
+    pub fn comprehend(
+        source: &Value,
+        sub_op: &GetOp,
+    ) -> TCResult<Vec<(Value, ResolvedOp)>> {
+        let (key, bindings) = sub_op;
+        let elements: Vec<Value> = match source {
+            Value::Tuple(values) => values.clone(),
+            other => vec![other.clone()],
+        };
+
+        let mut results = Vec::with_capacity(elements.len());
+        for (index, element) in elements.into_iter().enumerate() {
+            let mut checker = TypeChecker::new();
+            checker
+                .check_body(&[key.value_id().clone()], bindings)
+                .map_err(|_| error::bad_request("Op::Map/Filter body is invalid at index", index))?;
+
+            let resolved = Op::Def(OpDef::Get(sub_op.clone()))
+                .resolve_scope(&[key.value_id().clone()])
+                .map_err(|_| error::bad_request("Op::Map/Filter body is invalid at index", index))?;
+
+            results.push((element, resolved));
+        }
+
+        Ok(results)
+    }
+}
+
+/// A non-fatal diagnostic raised while resolving an op body's lexical
+/// scope with [`Op::resolve_scope`]. Unlike an error, a `ScopeWarning`
+/// doesn't stop resolution--it's collected alongside the successfully
+/// [`ResolvedOp`] for the caller to report however it likes.
+#[derive(Clone, Eq, PartialEq)]
+pub enum ScopeWarning {
+    /// A binding rebinds a name already bound by an earlier binding or by
+    /// one of the op's declared inputs.
+    Shadowed(ValueId),
+    /// A binding is never referenced by any binding after it.
+    Unused(ValueId),
+}
+
+impl fmt::Display for ScopeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Shadowed(name) => write!(f, "binding {} shadows an earlier name", name),
+            Self::Unused(name) => write!(f, "binding {} is never referenced", name),
+        }
+    }
+}
+
+/// Where a name in scope was introduced, as tracked by [`Scope`].
+#[derive(Clone, Eq, PartialEq)]
+enum Definition {
+    /// One of the op's declared inputs (the key, for `Get`; and so on).
+    Input,
+    /// The binding at this index into the body's `Vec<(ValueId, Value)>`.
+    Binding(usize),
+}
+
+/// A `TCRef` as it appears in an op body, annotated with where the name
+/// it names was defined--so downstream execution can look the referent
+/// up directly, without re-scanning the body to resolve it again.
+#[derive(Clone, Eq, PartialEq)]
+pub struct ResolvedRef {
+    pub reference: TCRef,
+    definition: Definition,
+}
+
+impl ResolvedRef {
+    /// The index into the body's bindings that this reference resolves
+    /// to, or `None` if it resolves to a declared input instead.
+    pub fn binding(&self) -> Option<usize> {
+        match self.definition {
+            Definition::Input => None,
+            Definition::Binding(index) => Some(index),
+        }
+    }
+}
+
+/// The result of resolving an `OpDef`/`Method` body's lexical scope with
+/// [`Op::resolve_scope`]: the body's bindings, unchanged and still in
+/// their original (dependency) order, each of whose embedded `TCRef`s has
+/// been resolved to a [`ResolvedRef`], plus any [`ScopeWarning`]s raised
+/// along the way.
+#[derive(Clone)]
+pub struct ResolvedOp {
+    pub bindings: Vec<(ValueId, Value)>,
+    pub refs: Vec<ResolvedRef>,
+    pub warnings: Vec<ScopeWarning>,
+}
+
+/// Walks an op body in declaration order, building up the ordered
+/// environment of names in scope--the op's declared inputs, then each
+/// binding in turn--used by [`Op::resolve_scope`] to resolve every
+/// embedded `TCRef` against the names visible *before* it.
+struct Scope {
+    order: Vec<ValueId>,
+    defined: std::collections::HashMap<ValueId, Definition>,
+    referenced: std::collections::HashSet<ValueId>,
+    warnings: Vec<ScopeWarning>,
+}
+
+impl Scope {
+    fn new() -> Self {
+        Self {
+            order: Vec::new(),
+            defined: std::collections::HashMap::new(),
+            referenced: std::collections::HashSet::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Bring `name` into scope, defined by `definition`. Shadowing an
+    /// already-defined name is allowed but raises a [`ScopeWarning`]
+    /// rather than an error.
+    fn define(&mut self, name: ValueId, definition: Definition) {
+        if self.defined.contains_key(&name) {
+            self.warnings.push(ScopeWarning::Shadowed(name.clone()));
+        }
+
+        self.order.push(name.clone());
+        self.defined.insert(name, definition);
+    }
+
+    /// Resolve `reference` against the names currently in scope, marking
+    /// it as referenced, or error if it names nothing defined yet.
+    fn resolve(&mut self, reference: &TCRef) -> TCResult<ResolvedRef> {
+        let name = reference.value_id();
+        let definition = self
+            .defined
+            .get(name)
+            .cloned()
+            .ok_or_else(|| error::bad_request("undefined reference in Op body", reference))?;
+
+        self.referenced.insert(name.clone());
+
+        Ok(ResolvedRef {
+            reference: reference.clone(),
+            definition,
+        })
+    }
+
+    /// Recursively resolve every `TCRef` embedded in `value`--see the
+    /// same assumption documented on `TypeChecker::check_refs`, above,
+    /// that a reference appears as `Value::TCRef` and the only other
+    /// composite variant is `Value::Tuple`.
+    fn resolve_value(&mut self, value: &Value, refs: &mut Vec<ResolvedRef>) -> TCResult<()> {
+        match value {
+            Value::TCRef(reference) => {
+                refs.push(self.resolve(reference)?);
+                Ok(())
+            }
+            Value::Tuple(values) => {
+                for item in values {
+                    self.resolve_value(item, refs)?;
+                }
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Op {
+    /// Resolve the lexical scope of this op's body: starting from the
+    /// `ValueId`s in `inputs` (the op's declared parameters), walk the
+    /// body's bindings in order, resolving every embedded `TCRef` against
+    /// the names visible before it. Not an `OpDef`/`Method`, this returns
+    /// an empty body with no bindings to resolve.
+    ///
+    /// Returns `error::bad_request` for a reference to an undefined (or
+    /// not-yet-bound) name; shadowing an earlier name and leaving a
+    /// binding unreferenced are reported as [`ScopeWarning`]s on the
+    /// returned [`ResolvedOp`] instead, since neither prevents the body
+    /// from running.
+    pub fn resolve_scope(&self, inputs: &[ValueId]) -> TCResult<ResolvedOp> {
+        let bindings: &[(ValueId, Value)] = match self {
+            Self::Def(OpDef::Get((_, bindings))) => bindings,
+            Self::Def(OpDef::Put((_, _, bindings))) => bindings,
+            Self::Def(OpDef::Post((_, bindings))) => bindings,
+            _ => &[],
+        };
+
+        let mut scope = Scope::new();
+        for input in inputs {
+            scope.define(input.clone(), Definition::Input);
+        }
+
+        let mut refs = Vec::new();
+        for (index, (name, value)) in bindings.iter().enumerate() {
+            scope.resolve_value(value, &mut refs)?;
+            scope.define(name.clone(), Definition::Binding(index));
+        }
+
+        // skip the declared inputs, which lead `order`: only bindings are checked
+        for name in scope.order.iter().skip(inputs.len()) {
+            if !scope.referenced.contains(name) {
+                scope.warnings.push(ScopeWarning::Unused(name.clone()));
+            }
+        }
+
+        Ok(ResolvedOp {
+            bindings: bindings.to_vec(),
+            refs,
+            warnings: scope.warnings,
+        })
+    }
+}